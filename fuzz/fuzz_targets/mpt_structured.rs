@@ -0,0 +1,126 @@
+//! Valid proofs from a generated trie, then mutated.
+//!
+//! The honest proof must verify to the true value with both the single-key
+//! and multiproof verifiers. After mutation a proof may be rejected, but
+//! proving any other value (including absence of a present key) is a forgery.
+
+#![no_main]
+
+use alloy_primitives::{Bytes, B256};
+use arbitrary::Arbitrary;
+use dvrpc_node::proof::fuzzing::verify_mpt_proof;
+use dvrpc_node::proof::trie::MemoryTrie;
+use dvrpc_node::proof::MultiProof;
+use libfuzzer_sys::fuzz_target;
+use std::collections::BTreeMap;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    entries: Vec<([u8; 32], Vec<u8>)>,
+    query: Query,
+    mutations: Vec<Mutation>,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Query {
+    Present(u8),
+    Absent([u8; 32]),
+}
+
+#[derive(Arbitrary, Debug)]
+enum Mutation {
+    Flip { node: u8, offset: u16, xor: u8 },
+    Truncate { node: u8, len: u16 },
+    Drop { node: u8 },
+    Duplicate { node: u8 },
+    Swap { a: u8, b: u8 },
+    Insert { at: u8, bytes: Vec<u8> },
+    Replace { node: u8, bytes: Vec<u8> },
+}
+
+fn apply(proof: &mut Vec<Bytes>, mutation: &Mutation) {
+    let pick = |i: u8, len: usize| (i as usize) % len.max(1);
+
+    match mutation {
+        Mutation::Flip { node, offset, xor } if !proof.is_empty() => {
+            let i = pick(*node, proof.len());
+            let mut bytes = proof[i].to_vec();
+            if !bytes.is_empty() {
+                let j = (*offset as usize) % bytes.len();
+                bytes[j] ^= (*xor).max(1);
+            }
+            proof[i] = bytes.into();
+        }
+        Mutation::Truncate { node, len } if !proof.is_empty() => {
+            let i = pick(*node, proof.len());
+            let len = (*len as usize).min(proof[i].len());
+            proof[i] = proof[i].slice(..len);
+        }
+        Mutation::Drop { node } if !proof.is_empty() => {
+            proof.remove(pick(*node, proof.len()));
+        }
+        Mutation::Duplicate { node } if !proof.is_empty() => {
+            let i = pick(*node, proof.len());
+            proof.insert(i, proof[i].clone());
+        }
+        Mutation::Swap { a, b } if !proof.is_empty() => {
+            let (a, b) = (pick(*a, proof.len()), pick(*b, proof.len()));
+            proof.swap(a, b);
+        }
+        Mutation::Insert { at, bytes } => {
+            let i = pick(*at, proof.len() + 1);
+            proof.insert(i.min(proof.len()), Bytes::copy_from_slice(bytes));
+        }
+        Mutation::Replace { node, bytes } if !proof.is_empty() => {
+            let i = pick(*node, proof.len());
+            proof[i] = Bytes::copy_from_slice(bytes);
+        }
+        _ => {}
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let mut trie = MemoryTrie::new();
+    let mut truth = BTreeMap::new();
+    for (key, value) in input.entries.iter().take(256) {
+        // Empty values mean deletion in an MPT
+        if value.is_empty() {
+            continue;
+        }
+        let key = B256::from(*key);
+        trie.insert(key, value.clone());
+        truth.insert(key, value.clone());
+    }
+
+    let key = match input.query {
+        Query::Present(i) if !truth.is_empty() => {
+            *truth.keys().nth(i as usize % truth.len()).unwrap()
+        }
+        Query::Present(_) => B256::ZERO,
+        Query::Absent(key) => B256::from(key),
+    };
+    let expected = truth.get(&key).cloned();
+
+    let root = trie.root();
+    let mut proof = trie.proof(key);
+
+    assert_eq!(verify_mpt_proof(root, &key, &proof).unwrap(), expected);
+    assert_eq!(
+        MultiProof::new(&proof).verifier().get(root, &key).unwrap(),
+        expected
+    );
+
+    if input.mutations.is_empty() {
+        return;
+    }
+    for mutation in input.mutations.iter().take(8) {
+        apply(&mut proof, mutation);
+    }
+
+    if let Ok(value) = verify_mpt_proof(root, &key, &proof) {
+        assert_eq!(value, expected, "forged proof accepted");
+    }
+    if let Ok(value) = MultiProof::new(&proof).verifier().get(root, &key) {
+        assert_eq!(value, expected, "forged multiproof accepted");
+    }
+});
@@ -0,0 +1,41 @@
+//! Arbitrary proofs through `verify_mpt_proof`.
+//!
+//! Input layout: 32-byte root, 32-byte key, then proof nodes each prefixed
+//! with a big-endian u16 length. `scripts/fetch_seed_corpus.py` writes real
+//! mainnet proofs in this layout.
+
+#![no_main]
+
+use alloy_primitives::{Bytes, B256};
+use dvrpc_node::proof::fuzzing::{keccak256, verify_mpt_proof};
+use libfuzzer_sys::fuzz_target;
+
+fn parse(data: &[u8]) -> Option<(B256, B256, Vec<Bytes>)> {
+    let root = B256::from_slice(data.get(..32)?);
+    let key = B256::from_slice(data.get(32..64)?);
+
+    let mut nodes = Vec::new();
+    let mut rest = &data[64..];
+    while rest.len() >= 2 {
+        let len = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+        let node = rest.get(2..2 + len)?;
+        nodes.push(Bytes::copy_from_slice(node));
+        rest = &rest[2 + len..];
+    }
+
+    Some((root, key, nodes))
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((root, key, proof)) = parse(data) else {
+        return;
+    };
+
+    if let Ok(Some(_)) = verify_mpt_proof(root, &key, &proof) {
+        // Anything proven present must be reachable from a node hashing to the root
+        assert!(
+            proof.iter().any(|node| keccak256(node) == root),
+            "proof accepted without a node matching the root"
+        );
+    }
+});
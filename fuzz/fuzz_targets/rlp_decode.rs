@@ -0,0 +1,35 @@
+//! Random bytes through every RLP and hex-prefix decoder.
+//!
+//! Decoders may reject input but must never panic or report consuming more
+//! bytes than they were given.
+
+#![no_main]
+
+use dvrpc_node::proof::fuzzing::{
+    decode_hp_path, decode_rlp_item, decode_rlp_length, decode_rlp_list, decode_storage_value,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok((content, len)) = decode_rlp_length(data) {
+        assert!(len > 0 && len <= data.len());
+        assert!(content.len() <= len);
+    }
+
+    if let Ok((item, len)) = decode_rlp_item(data) {
+        assert!(len > 0 && len <= data.len());
+        assert!(item.len() <= len);
+    }
+
+    if let Ok(items) = decode_rlp_list(data) {
+        assert!(items.iter().map(Vec::len).sum::<usize>() <= data.len());
+        for item in &items {
+            let _ = decode_rlp_list(item);
+            let _ = decode_hp_path(item);
+            let _ = decode_storage_value(item);
+        }
+    }
+
+    let _ = decode_hp_path(data);
+    let _ = decode_storage_value(data);
+});
@@ -0,0 +1,144 @@
+#!/usr/bin/env python3
+"""Build the fuzz seed corpus from real mainnet proofs.
+
+Fetches EIP-1186 proofs for a handful of well-known accounts and storage
+slots, plus an account that does not exist, and writes them to
+fuzz/seeds/<target>/:
+
+  rlp_decode/  every raw proof node
+  mpt_verify/  root || key || (u16 length || node)*
+
+Any execution RPC serving eth_getProof will do; without one, a public
+endpoint that needs no API key is used. Needs only the standard library.
+
+Usage: fetch_seed_corpus.py [execution-rpc-url] [block]
+"""
+
+import hashlib
+import json
+import os
+import struct
+import sys
+import urllib.request
+
+ACCOUNTS = {
+    # WETH, with its first balance slots
+    "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2": [0, 3],
+    # USDC proxy
+    "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48": [0, 9],
+    # EOA
+    "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045": [],
+    # Almost certainly absent, for an exclusion proof
+    "0x00000000000000000000000000000000dEaDbEEF": [],
+}
+
+DEFAULT_RPC = "https://ethereum-rpc.publicnode.com"
+
+SEEDS = os.path.join(os.path.dirname(os.path.abspath(__file__)), "..", "seeds")
+
+
+# Keccak-f[1600] round constants and rotation offsets, indexed [x][y]
+ROUND_CONSTANTS = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808A, 0x8000000080008000,
+    0x000000000000808B, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008A, 0x0000000000000088, 0x0000000080008009, 0x000000008000000A,
+    0x000000008000808B, 0x800000000000008B, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800A, 0x800000008000000A,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008,
+]
+ROTATIONS = [
+    [0, 36, 3, 41, 18],
+    [1, 44, 10, 45, 2],
+    [62, 6, 43, 15, 61],
+    [28, 55, 25, 21, 56],
+    [27, 20, 39, 8, 14],
+]
+MASK = (1 << 64) - 1
+
+
+def rotl(value, shift):
+    return ((value << shift) | (value >> (64 - shift))) & MASK if shift else value
+
+
+def keccak_f(a):
+    for rc in ROUND_CONSTANTS:
+        c = [a[x][0] ^ a[x][1] ^ a[x][2] ^ a[x][3] ^ a[x][4] for x in range(5)]
+        d = [c[(x - 1) % 5] ^ rotl(c[(x + 1) % 5], 1) for x in range(5)]
+        a = [[a[x][y] ^ d[x] for y in range(5)] for x in range(5)]
+        b = [[0] * 5 for _ in range(5)]
+        for x in range(5):
+            for y in range(5):
+                b[y][(2 * x + 3 * y) % 5] = rotl(a[x][y], ROTATIONS[x][y])
+        a = [[b[x][y] ^ (~b[(x + 1) % 5][y] & b[(x + 2) % 5][y]) for y in range(5)] for x in range(5)]
+        a[0][0] ^= rc
+    return a
+
+
+def keccak256(data: bytes) -> bytes:
+    """Ethereum's Keccak-256 (original padding, not SHA3-256)."""
+    rate = 136
+    padded = bytearray(data) + b"\x01" + bytes(-(len(data) + 1) % rate)
+    padded[-1] |= 0x80
+    a = [[0] * 5 for _ in range(5)]
+    for offset in range(0, len(padded), rate):
+        block = padded[offset:offset + rate]
+        for i in range(rate // 8):
+            a[i % 5][i // 5] ^= int.from_bytes(block[8 * i:8 * i + 8], "little")
+        a = keccak_f(a)
+    return b"".join(a[i % 5][i // 5].to_bytes(8, "little") for i in range(4))
+
+
+def rpc(url, method, params):
+    body = json.dumps({"jsonrpc": "2.0", "id": 1, "method": method, "params": params})
+    req = urllib.request.Request(url, body.encode(), {"Content-Type": "application/json"})
+    with urllib.request.urlopen(req) as resp:
+        reply = json.load(resp)
+    if "error" in reply:
+        sys.exit(f"{method} failed: {reply['error']}")
+    return reply["result"]
+
+
+def write(target, data):
+    path = os.path.join(SEEDS, target)
+    os.makedirs(path, exist_ok=True)
+    with open(os.path.join(path, hashlib.sha1(data).hexdigest()), "wb") as f:
+        f.write(data)
+
+
+def write_proof(root, key, nodes):
+    framed = root + key
+    for node in nodes:
+        write("rlp_decode", node)
+        framed += struct.pack(">H", len(node)) + node
+    write("mpt_verify", framed)
+
+
+def main():
+    if len(sys.argv) > 1 and sys.argv[1] in ("-h", "--help"):
+        sys.exit(__doc__)
+    url = sys.argv[1] if len(sys.argv) > 1 else DEFAULT_RPC
+    block = sys.argv[2] if len(sys.argv) > 2 else "finalized"
+
+    header = rpc(url, "eth_getBlockByNumber", [block, False])
+    state_root = bytes.fromhex(header["stateRoot"][2:])
+    block = header["number"]
+
+    for address, slots in ACCOUNTS.items():
+        keys = ["0x%064x" % slot for slot in slots]
+        proof = rpc(url, "eth_getProof", [address, keys, block])
+
+        account_key = keccak256(bytes.fromhex(address[2:]))
+        nodes = [bytes.fromhex(n[2:]) for n in proof["accountProof"]]
+        write_proof(state_root, account_key, nodes)
+
+        storage_root = bytes.fromhex(proof["storageHash"][2:])
+        for sp in proof["storageProof"]:
+            slot_key = keccak256(bytes.fromhex(sp["key"][2:].rjust(64, "0")))
+            nodes = [bytes.fromhex(n[2:]) for n in sp["proof"]]
+            write_proof(storage_root, slot_key, nodes)
+
+    print(f"Wrote seeds for block {int(block, 16)} to {os.path.normpath(SEEDS)}")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,90 @@
+//! Per-request access log.
+//!
+//! Each RPC request runs inside a [`scope`] that collects what the request
+//! touched: the upstream it was served from, how its response was verified
+//! and against which block. The RPC server writes these out as one structured
+//! log line per request under the `access` target. Outside a scope the
+//! `record_*` functions do nothing.
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use crate::metrics::VerificationOutcome;
+
+tokio::task_local! {
+    static ENTRY: Arc<Mutex<AccessEntry>>;
+}
+
+/// What one request touched.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessEntry {
+    /// Endpoint of the last upstream request, or "cache" for cached proofs.
+    pub upstream: Option<String>,
+    /// Least favourable verification outcome of the request.
+    pub verification: Option<VerificationOutcome>,
+    /// Block the response was proven against.
+    pub block: Option<u64>,
+}
+
+/// Run `request`, collecting its access entry.
+pub async fn scope<F: Future>(request: F) -> (F::Output, AccessEntry) {
+    let entry = Arc::new(Mutex::new(AccessEntry::default()));
+    let output = ENTRY.scope(entry.clone(), request).await;
+    let entry = entry.lock().unwrap().clone();
+    (output, entry)
+}
+
+fn update(f: impl FnOnce(&mut AccessEntry)) {
+    let _ = ENTRY.try_with(|entry| f(&mut entry.lock().unwrap()));
+}
+
+pub fn record_upstream(endpoint: &str) {
+    update(|entry| entry.upstream = Some(endpoint.to_string()));
+}
+
+/// Record a verification outcome. A request that checks several proofs is
+/// only as verified as its weakest one.
+pub fn record_verification(outcome: VerificationOutcome) {
+    update(|entry| {
+        entry.verification = Some(match entry.verification {
+            Some(previous) if rank(previous) >= rank(outcome) => previous,
+            _ => outcome,
+        })
+    });
+}
+
+pub fn record_block(number: u64) {
+    update(|entry| entry.block = Some(number));
+}
+
+fn rank(outcome: VerificationOutcome) -> u8 {
+    match outcome {
+        VerificationOutcome::Verified => 0,
+        VerificationOutcome::Skipped => 1,
+        VerificationOutcome::Failed => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scope_collects_weakest_outcome() {
+        let ((), entry) = scope(async {
+            record_upstream("https://rpc.example");
+            record_verification(VerificationOutcome::Verified);
+            record_verification(VerificationOutcome::Skipped);
+            record_verification(VerificationOutcome::Verified);
+            record_block(100);
+        })
+        .await;
+
+        assert_eq!(entry.upstream.as_deref(), Some("https://rpc.example"));
+        assert_eq!(entry.verification, Some(VerificationOutcome::Skipped));
+        assert_eq!(entry.block, Some(100));
+
+        // Outside a scope nothing is recorded, and nothing panics
+        record_block(1);
+    }
+}
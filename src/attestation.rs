@@ -0,0 +1,269 @@
+//! Operator signatures over verified responses.
+//!
+//! An attestation signs a digest of the method, params, result and the
+//! verified block the result was proven against, so a response can later be
+//! shown to have been vouched for by this node:
+//!
+//! ```text
+//! keccak256("dvrpc-attestation-v1" . keccak256(method) . keccak256(params)
+//!           . keccak256(result) . block_hash . state_root)
+//! ```
+//!
+//! `params` and `result` are hashed as compact JSON with object keys sorted.
+
+use alloy_primitives::{keccak256, Address, Bytes, B256, U64};
+use ed25519_dalek::Signer;
+use eyre::{bail, Context, Result};
+use k256::ecdsa::SigningKey;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::config::AttestationConfig;
+use crate::types::{Attestation, ConsensusProof};
+
+const DOMAIN: &[u8] = b"dvrpc-attestation-v1";
+
+/// Signature scheme of the operator key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scheme {
+    /// Recoverable ECDSA, signed over the digest as a prehash.
+    Secp256k1,
+    /// Ed25519 over the 32 digest bytes.
+    Ed25519,
+}
+
+enum Key {
+    Secp256k1(SigningKey),
+    Ed25519(ed25519_dalek::SigningKey),
+}
+
+/// Signs verified responses with the operator key.
+pub struct Attestor {
+    key: Key,
+}
+
+impl Attestor {
+    /// Load the key from `key_file`: a hex-encoded 32-byte secret key (or
+    /// Ed25519 seed), optionally `0x`-prefixed.
+    pub fn load(config: &AttestationConfig) -> Result<Self> {
+        let contents = fs::read_to_string(&config.key_file).wrap_err_with(|| {
+            format!(
+                "Failed to read attestation key {}",
+                config.key_file.display()
+            )
+        })?;
+        let secret = hex::decode(contents.trim().trim_start_matches("0x"))
+            .wrap_err("Attestation key must be hex-encoded")?;
+        Self::from_secret(config.scheme, &secret)
+    }
+
+    pub fn from_secret(scheme: Scheme, secret: &[u8]) -> Result<Self> {
+        let Ok(secret) = <[u8; 32]>::try_from(secret) else {
+            bail!("Attestation key must be 32 bytes, got {}", secret.len());
+        };
+        let key = match scheme {
+            Scheme::Secp256k1 => Key::Secp256k1(
+                SigningKey::from_bytes(&secret.into())
+                    .wrap_err("Invalid secp256k1 attestation key")?,
+            ),
+            Scheme::Ed25519 => Key::Ed25519(ed25519_dalek::SigningKey::from_bytes(&secret)),
+        };
+        Ok(Self { key })
+    }
+
+    pub fn scheme(&self) -> Scheme {
+        match self.key {
+            Key::Secp256k1(_) => Scheme::Secp256k1,
+            Key::Ed25519(_) => Scheme::Ed25519,
+        }
+    }
+
+    /// Public key: compressed SEC1 for secp256k1, 32 bytes for Ed25519.
+    pub fn public_key(&self) -> Bytes {
+        match &self.key {
+            Key::Secp256k1(key) => key
+                .verifying_key()
+                .to_encoded_point(true)
+                .as_bytes()
+                .to_vec()
+                .into(),
+            Key::Ed25519(key) => key.verifying_key().to_bytes().to_vec().into(),
+        }
+    }
+
+    /// Ethereum address of a secp256k1 key.
+    pub fn address(&self) -> Option<Address> {
+        match &self.key {
+            Key::Secp256k1(key) => Some(Address::from_public_key(key.verifying_key())),
+            Key::Ed25519(_) => None,
+        }
+    }
+
+    /// Sign a result verified against `consensus_proof`.
+    pub fn attest(
+        &self,
+        method: &str,
+        params: &serde_json::Value,
+        result: &serde_json::Value,
+        consensus_proof: &ConsensusProof,
+    ) -> Attestation {
+        let digest = digest(
+            method,
+            params,
+            result,
+            consensus_proof.block_hash,
+            consensus_proof.state_root,
+        );
+
+        let signature = match &self.key {
+            Key::Secp256k1(key) => {
+                // Signing a 32-byte prehash only fails for an all-zero digest
+                let (signature, recovery_id) = key
+                    .sign_prehash_recoverable(digest.as_slice())
+                    .expect("keccak256 digest is a valid prehash");
+                let mut bytes = signature.to_bytes().to_vec();
+                bytes.push(recovery_id.to_byte());
+                bytes
+            }
+            Key::Ed25519(key) => key.sign(digest.as_slice()).to_bytes().to_vec(),
+        };
+
+        Attestation {
+            scheme: self.scheme(),
+            signer: self.public_key(),
+            block_number: U64::from(consensus_proof.block_number),
+            block_hash: consensus_proof.block_hash,
+            state_root: consensus_proof.state_root,
+            signature: signature.into(),
+        }
+    }
+}
+
+/// Digest signed by an attestation.
+pub fn digest(
+    method: &str,
+    params: &serde_json::Value,
+    result: &serde_json::Value,
+    block_hash: B256,
+    state_root: B256,
+) -> B256 {
+    keccak256(
+        [
+            DOMAIN,
+            keccak256(method.as_bytes()).as_slice(),
+            keccak256(canonical_json(params)).as_slice(),
+            keccak256(canonical_json(result)).as_slice(),
+            block_hash.as_slice(),
+            state_root.as_slice(),
+        ]
+        .concat(),
+    )
+}
+
+/// Compact JSON with object keys sorted, independent of map ordering.
+fn canonical_json(value: &serde_json::Value) -> Vec<u8> {
+    fn sorted(value: &serde_json::Value) -> serde_json::Value {
+        match value {
+            serde_json::Value::Object(map) => {
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                serde_json::Value::Object(
+                    entries
+                        .into_iter()
+                        .map(|(k, v)| (k.clone(), sorted(v)))
+                        .collect(),
+                )
+            }
+            serde_json::Value::Array(items) => {
+                serde_json::Value::Array(items.iter().map(sorted).collect())
+            }
+            other => other.clone(),
+        }
+    }
+    serde_json::to_vec(&sorted(value)).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::Verifier;
+    use serde_json::json;
+
+    fn consensus_proof() -> ConsensusProof {
+        ConsensusProof {
+            state_root: B256::repeat_byte(0x11),
+            block_hash: B256::repeat_byte(0x22),
+            slot: 100,
+            block_number: 42,
+        }
+    }
+
+    #[test]
+    fn test_canonical_json_sorts_keys() {
+        let a = json!({"b": 1, "a": {"d": [1, {"f": 2, "e": 3}], "c": null}});
+        assert_eq!(
+            canonical_json(&a),
+            br#"{"a":{"c":null,"d":[1,{"e":3,"f":2}]},"b":1}"#
+        );
+    }
+
+    #[test]
+    fn test_secp256k1_attestation_recovers_signer() {
+        let attestor = Attestor::from_secret(Scheme::Secp256k1, &[7u8; 32]).unwrap();
+        let params = json!(["0x0000000000000000000000000000000000000001", "latest"]);
+        let result = json!("0x1");
+        let cp = consensus_proof();
+        let attestation = attestor.attest("eth_getBalance", &params, &result, &cp);
+
+        let signed = digest(
+            "eth_getBalance",
+            &params,
+            &result,
+            cp.block_hash,
+            cp.state_root,
+        );
+        let signature =
+            alloy_primitives::Signature::try_from(attestation.signature.as_ref()).unwrap();
+        assert_eq!(
+            signature.recover_address_from_prehash(&signed).unwrap(),
+            attestor.address().unwrap()
+        );
+        assert_eq!(attestation.signer.len(), 33);
+        assert_eq!(attestation.block_number, U64::from(42));
+
+        // A different result does not verify under the same signature
+        let other = digest(
+            "eth_getBalance",
+            &params,
+            &json!("0x2"),
+            cp.block_hash,
+            cp.state_root,
+        );
+        assert_ne!(
+            signature.recover_address_from_prehash(&other).unwrap(),
+            attestor.address().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_ed25519_attestation_verifies() {
+        let attestor = Attestor::from_secret(Scheme::Ed25519, &[9u8; 32]).unwrap();
+        let cp = consensus_proof();
+        let attestation = attestor.attest("eth_chainId", &json!([]), &json!("0x1"), &cp);
+
+        let key = ed25519_dalek::VerifyingKey::try_from(attestation.signer.as_ref()).unwrap();
+        let signature = ed25519_dalek::Signature::try_from(attestation.signature.as_ref()).unwrap();
+        let signed = digest(
+            "eth_chainId",
+            &json!([]),
+            &json!("0x1"),
+            cp.block_hash,
+            cp.state_root,
+        );
+        assert!(key.verify(signed.as_slice(), &signature).is_ok());
+        assert!(attestor.address().is_none());
+
+        assert!(Attestor::from_secret(Scheme::Ed25519, &[1u8; 31]).is_err());
+    }
+}
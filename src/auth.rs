@@ -0,0 +1,291 @@
+//! API key authentication with per-key rate limits and method allowlists.
+
+use axum::http::StatusCode;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::config::{method_matches, ApiKeyConfig, AuthConfig};
+use crate::types::RpcError;
+
+/// Why a request was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthError {
+    MissingKey,
+    InvalidKey,
+    MethodNotAllowed { key: String, method: String },
+    RateLimited { key: String },
+}
+
+impl AuthError {
+    /// HTTP status for the refusal.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AuthError::MissingKey | AuthError::InvalidKey => StatusCode::UNAUTHORIZED,
+            AuthError::MethodNotAllowed { .. } => StatusCode::FORBIDDEN,
+            AuthError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    /// JSON-RPC error for the refusal.
+    pub fn to_rpc_error(&self, id: serde_json::Value) -> RpcError {
+        match self {
+            AuthError::MissingKey => RpcError::new(id, -32001, "API key required"),
+            AuthError::InvalidKey => RpcError::new(id, -32001, "Invalid API key"),
+            AuthError::MethodNotAllowed { method, .. } => RpcError::new(
+                id,
+                -32004,
+                format!("Method {} not allowed for this API key", method),
+            ),
+            AuthError::RateLimited { .. } => RpcError::new(id, -32005, "Rate limit exceeded"),
+        }
+    }
+}
+
+/// Token bucket refilled continuously at `rate` tokens per second.
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(rate: f64, capacity: f64) -> Self {
+        Self {
+            rate,
+            capacity,
+            tokens: capacity,
+            last: Instant::now(),
+        }
+    }
+
+    pub(crate) fn try_take(&mut self, now: Instant) -> bool {
+        self.try_take_n(now, 1.0)
+    }
+
+    /// Take `n` tokens at once, or none if fewer are available.
+    pub(crate) fn try_take_n(&mut self, now: Instant, n: f64) -> bool {
+        self.refill(now);
+        if self.tokens >= n {
+            self.tokens -= n;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Whether the bucket has refilled completely, so forgetting it changes
+    /// nothing.
+    pub(crate) fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.capacity
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last = now;
+    }
+}
+
+struct ApiKey {
+    name: String,
+    allowed_methods: Option<Vec<String>>,
+    bucket: Option<Mutex<TokenBucket>>,
+}
+
+impl ApiKey {
+    fn new(config: &ApiKeyConfig) -> Self {
+        let bucket = config.requests_per_second.map(|rate| {
+            let capacity = config
+                .burst
+                .map(f64::from)
+                .unwrap_or_else(|| rate.ceil())
+                .max(1.0);
+            Mutex::new(TokenBucket::new(rate, capacity))
+        });
+
+        Self {
+            name: config.name.clone(),
+            allowed_methods: config.allowed_methods.clone(),
+            bucket,
+        }
+    }
+
+    fn allows(&self, method: &str) -> bool {
+        let Some(ref allowed) = self.allowed_methods else {
+            return !is_admin(method);
+        };
+        if is_admin(method) {
+            return allowed.iter().any(|m| m == method);
+        }
+        allowed
+            .iter()
+            .any(|pattern| method_matches(pattern, method))
+    }
+}
+
+/// Admin methods change the node, so they need a key listing them by name:
+/// keys without an allowlist and wildcard patterns do not grant them.
+fn is_admin(method: &str) -> bool {
+    method.starts_with("admin_")
+}
+
+/// Checks API keys against the `[auth]` config.
+pub struct Authenticator {
+    enabled: bool,
+    header: String,
+    keys: HashMap<String, ApiKey>,
+}
+
+impl Authenticator {
+    pub fn new(config: &AuthConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            header: config.header.clone(),
+            keys: config
+                .keys
+                .iter()
+                .map(|k| (k.key.clone(), ApiKey::new(k)))
+                .collect(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Header the API key is read from.
+    pub fn header(&self) -> &str {
+        &self.header
+    }
+
+    /// Authorize one request, returning the name of the key it was made with.
+    ///
+    /// Returns `Ok(None)` when authentication is disabled.
+    pub fn authorize(&self, key: Option<&str>, method: &str) -> Result<Option<String>, AuthError> {
+        if !self.enabled {
+            return match is_admin(method) {
+                true => Err(AuthError::MissingKey),
+                false => Ok(None),
+            };
+        }
+
+        let key = key.ok_or(AuthError::MissingKey)?;
+        let api_key = self.keys.get(key).ok_or(AuthError::InvalidKey)?;
+
+        if !api_key.allows(method) {
+            return Err(AuthError::MethodNotAllowed {
+                key: api_key.name.clone(),
+                method: method.to_string(),
+            });
+        }
+
+        if let Some(ref bucket) = api_key.bucket {
+            if !bucket.lock().unwrap().try_take(Instant::now()) {
+                return Err(AuthError::RateLimited {
+                    key: api_key.name.clone(),
+                });
+            }
+        }
+
+        Ok(Some(api_key.name.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn config(rate: Option<f64>, allowed: Option<Vec<&str>>) -> AuthConfig {
+        AuthConfig {
+            enabled: true,
+            keys: vec![ApiKeyConfig {
+                name: "team-a".to_string(),
+                key: "secret".to_string(),
+                requests_per_second: rate,
+                burst: Some(2),
+                allowed_methods: allowed.map(|m| m.into_iter().map(String::from).collect()),
+            }],
+            ..AuthConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_token_bucket_refills() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(1.0, 2.0);
+        assert!(bucket.try_take(start));
+        assert!(bucket.try_take(start));
+        assert!(!bucket.try_take(start));
+        assert!(bucket.try_take(start + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_authorize_keys_and_limits() {
+        let auth = Authenticator::new(&config(Some(0.001), None));
+
+        assert_eq!(
+            auth.authorize(None, "eth_getBalance"),
+            Err(AuthError::MissingKey)
+        );
+        assert_eq!(
+            auth.authorize(Some("wrong"), "eth_getBalance"),
+            Err(AuthError::InvalidKey)
+        );
+        assert_eq!(
+            auth.authorize(Some("secret"), "eth_getBalance"),
+            Ok(Some("team-a".to_string()))
+        );
+        assert!(auth.authorize(Some("secret"), "eth_getBalance").is_ok());
+        assert_eq!(
+            auth.authorize(Some("secret"), "eth_getBalance"),
+            Err(AuthError::RateLimited {
+                key: "team-a".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_method_allowlist() {
+        let auth = Authenticator::new(&config(
+            None,
+            Some(vec!["eth_*", "dvrpc_getMultiAccountProof"]),
+        ));
+
+        assert!(auth.authorize(Some("secret"), "eth_getBalance").is_ok());
+        assert!(auth
+            .authorize(Some("secret"), "dvrpc_getMultiAccountProof")
+            .is_ok());
+        assert!(matches!(
+            auth.authorize(Some("secret"), "debug_traceTransaction"),
+            Err(AuthError::MethodNotAllowed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_disabled_allows_everything() {
+        let auth = Authenticator::new(&AuthConfig::default());
+        assert_eq!(auth.authorize(None, "anything"), Ok(None));
+        assert_eq!(
+            auth.authorize(None, "admin_reloadConfig"),
+            Err(AuthError::MissingKey)
+        );
+    }
+
+    #[test]
+    fn test_admin_methods_listed_by_name() {
+        for allowed in [None, Some(vec!["*"]), Some(vec!["admin_*"])] {
+            let auth = Authenticator::new(&config(None, allowed));
+            assert!(auth
+                .authorize(Some("secret"), "admin_reloadConfig")
+                .is_err());
+        }
+
+        let auth = Authenticator::new(&config(None, Some(vec!["admin_reloadConfig"])));
+        assert!(auth.authorize(Some("secret"), "admin_reloadConfig").is_ok());
+    }
+}
@@ -0,0 +1,370 @@
+//! Beacon chain parameters of the network being followed, and checks that
+//! the upstream and the consensus RPC are on that chain.
+
+use alloy_primitives::{b256, fixed_bytes, FixedBytes, B256};
+use eyre::Result;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::time::Duration;
+
+use crate::config::redact_url;
+use crate::upstream::UpstreamClient;
+
+/// Time allowed to connect to the consensus RPC.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Time allowed for each check, so an unresponsive endpoint cannot hold up
+/// startup or a reload.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Parameters the light client needs to follow a beacon chain, and to map
+/// execution blocks to slots.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub struct ChainSpec {
+    /// Execution chain ID.
+    pub chain_id: u64,
+    /// Beacon chain genesis time (unix seconds).
+    pub genesis_time: u64,
+    pub genesis_validators_root: B256,
+    #[serde(default = "default_seconds_per_slot")]
+    pub seconds_per_slot: u64,
+    pub forks: Forks,
+    /// Checkpoint sync service used when no checkpoint is configured.
+    #[serde(default)]
+    pub checkpoint_fallback: Option<String>,
+}
+
+/// Fork schedule. Forks not yet scheduled are left out.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub struct Forks {
+    pub genesis: Fork,
+    pub altair: Fork,
+    pub bellatrix: Fork,
+    pub capella: Fork,
+    pub deneb: Fork,
+    #[serde(default)]
+    pub electra: Option<Fork>,
+    #[serde(default)]
+    pub fulu: Option<Fork>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct Fork {
+    pub epoch: u64,
+    pub version: FixedBytes<4>,
+}
+
+impl Forks {
+    /// Forks in activation order, leaving out unscheduled ones.
+    pub fn scheduled(&self) -> impl Iterator<Item = &Fork> {
+        [
+            Some(&self.genesis),
+            Some(&self.altair),
+            Some(&self.bellatrix),
+            Some(&self.capella),
+            Some(&self.deneb),
+            self.electra.as_ref(),
+            self.fulu.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+fn default_seconds_per_slot() -> u64 {
+    12
+}
+
+fn fork(epoch: u64, version: FixedBytes<4>) -> Fork {
+    Fork { epoch, version }
+}
+
+impl ChainSpec {
+    pub fn mainnet() -> Self {
+        Self {
+            chain_id: 1,
+            genesis_time: 1606824023,
+            genesis_validators_root: b256!(
+                "4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95"
+            ),
+            seconds_per_slot: 12,
+            forks: Forks {
+                genesis: fork(0, fixed_bytes!("00000000")),
+                altair: fork(74240, fixed_bytes!("01000000")),
+                bellatrix: fork(144896, fixed_bytes!("02000000")),
+                capella: fork(194048, fixed_bytes!("03000000")),
+                deneb: fork(269568, fixed_bytes!("04000000")),
+                electra: Some(fork(364032, fixed_bytes!("05000000"))),
+                fulu: Some(fork(411392, fixed_bytes!("06000000"))),
+            },
+            checkpoint_fallback: Some("https://sync-mainnet.beaconcha.in".to_string()),
+        }
+    }
+
+    pub fn sepolia() -> Self {
+        Self {
+            chain_id: 11155111,
+            genesis_time: 1655733600,
+            genesis_validators_root: b256!(
+                "d8ea171f3c94aea21ebc42a1ed61052acf3f9209c00e4efbaaddac09ed9b8078"
+            ),
+            seconds_per_slot: 12,
+            forks: Forks {
+                genesis: fork(0, fixed_bytes!("90000069")),
+                altair: fork(50, fixed_bytes!("90000070")),
+                bellatrix: fork(100, fixed_bytes!("90000071")),
+                capella: fork(56832, fixed_bytes!("90000072")),
+                deneb: fork(132608, fixed_bytes!("90000073")),
+                electra: Some(fork(222464, fixed_bytes!("90000074"))),
+                fulu: Some(fork(272640, fixed_bytes!("90000075"))),
+            },
+            checkpoint_fallback: Some("https://sync-sepolia.beaconcha.in".to_string()),
+        }
+    }
+
+    pub fn holesky() -> Self {
+        Self {
+            chain_id: 17000,
+            genesis_time: 1695902400,
+            genesis_validators_root: b256!(
+                "9143aa7c615a7f7115e2b6aac319c03529df8242ae705fba9df39b79c59fa8b1"
+            ),
+            seconds_per_slot: 12,
+            forks: Forks {
+                genesis: fork(0, fixed_bytes!("01017000")),
+                altair: fork(0, fixed_bytes!("02017000")),
+                bellatrix: fork(0, fixed_bytes!("03017000")),
+                capella: fork(256, fixed_bytes!("04017000")),
+                deneb: fork(29696, fixed_bytes!("05017000")),
+                electra: Some(fork(115968, fixed_bytes!("06017000"))),
+                fulu: Some(fork(165120, fixed_bytes!("07017000"))),
+            },
+            checkpoint_fallback: Some("https://sync-holesky.beaconcha.in".to_string()),
+        }
+    }
+
+    pub fn hoodi() -> Self {
+        Self {
+            chain_id: 560048,
+            genesis_time: 1742213400,
+            genesis_validators_root: b256!(
+                "212f13fc4df078b6cb7db228f1c8307566dcecf900867401a92023d7ba99cb5f"
+            ),
+            seconds_per_slot: 12,
+            forks: Forks {
+                genesis: fork(0, fixed_bytes!("10000910")),
+                altair: fork(0, fixed_bytes!("20000910")),
+                bellatrix: fork(0, fixed_bytes!("30000910")),
+                capella: fork(0, fixed_bytes!("40000910")),
+                deneb: fork(0, fixed_bytes!("50000910")),
+                electra: Some(fork(2048, fixed_bytes!("60000910"))),
+                fulu: Some(fork(50688, fixed_bytes!("70000910"))),
+            },
+            checkpoint_fallback: Some("https://checkpoint-sync.hoodi.ethpandaops.io".to_string()),
+        }
+    }
+
+    /// Beacon slot containing the block with the given timestamp.
+    pub fn slot_at(&self, timestamp: u64) -> u64 {
+        timestamp.saturating_sub(self.genesis_time) / self.seconds_per_slot
+    }
+}
+
+/// An RPC endpoint is on a different chain than the one configured.
+#[derive(Debug)]
+pub struct ChainMismatch(pub String);
+
+impl std::fmt::Display for ChainMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Chain mismatch: {}", self.0)
+    }
+}
+
+impl std::error::Error for ChainMismatch {}
+
+fn mismatch(message: String) -> eyre::Report {
+    ChainMismatch(message).into()
+}
+
+/// Check the upstream serves `chain_id`. Fails with [`ChainMismatch`] if it
+/// serves another chain, or with the request's error if it cannot be asked.
+pub async fn check_upstream(upstream: &UpstreamClient, chain_id: u64) -> Result<()> {
+    let upstream_id = tokio::time::timeout(CHECK_TIMEOUT, upstream.eth_chain_id())
+        .await
+        .map_err(|_| eyre::eyre!("upstream did not answer eth_chainId in time"))??;
+    if upstream_id != chain_id {
+        return Err(mismatch(format!(
+            "upstream is on chain {}, configured chain is {}",
+            upstream_id, chain_id
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct BeaconResponse<T> {
+    data: T,
+}
+
+#[derive(Debug, Deserialize)]
+struct BeaconGenesis {
+    #[serde(with = "decimal")]
+    genesis_time: u64,
+    genesis_validators_root: B256,
+    genesis_fork_version: FixedBytes<4>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BeaconFork {
+    current_version: FixedBytes<4>,
+    #[serde(with = "decimal")]
+    epoch: u64,
+}
+
+/// Beacon API numbers are decimal strings.
+mod decimal {
+    use serde::{de::Error, Deserialize, Deserializer};
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+/// GET a beacon API path, keeping the consensus RPC's path and query, which
+/// may hold an API key, out of errors.
+async fn beacon_get<T: DeserializeOwned>(
+    client: &reqwest::Client,
+    base: &str,
+    path: &str,
+) -> Result<T> {
+    let get = async {
+        client
+            .get(format!("{}{}", base, path))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    };
+    get.await.map_err(|e: reqwest::Error| {
+        eyre::eyre!(
+            "GET {}{} failed: {}",
+            redact_url(base),
+            path,
+            e.without_url()
+        )
+    })
+}
+
+/// Check the beacon node at `consensus_rpc` follows the chain in `spec`: its
+/// genesis and every scheduled fork must match. Fails with
+/// [`ChainMismatch`] on a difference.
+pub async fn check_beacon(consensus_rpc: &str, spec: &ChainSpec) -> Result<()> {
+    let client = reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(CHECK_TIMEOUT)
+        .build()?;
+    let base = consensus_rpc.trim_end_matches('/');
+
+    let genesis: BeaconResponse<BeaconGenesis> =
+        beacon_get(&client, base, "/eth/v1/beacon/genesis").await?;
+    let genesis = genesis.data;
+
+    if genesis.genesis_validators_root != spec.genesis_validators_root {
+        return Err(mismatch(format!(
+            "consensus RPC genesis validators root is {}, expected {}",
+            genesis.genesis_validators_root, spec.genesis_validators_root
+        )));
+    }
+    if genesis.genesis_time != spec.genesis_time {
+        return Err(mismatch(format!(
+            "consensus RPC genesis time is {}, expected {}",
+            genesis.genesis_time, spec.genesis_time
+        )));
+    }
+    if genesis.genesis_fork_version != spec.forks.genesis.version {
+        return Err(mismatch(format!(
+            "consensus RPC genesis fork version is {}, expected {}",
+            genesis.genesis_fork_version, spec.forks.genesis.version
+        )));
+    }
+
+    let schedule: BeaconResponse<Vec<BeaconFork>> =
+        beacon_get(&client, base, "/eth/v1/config/fork_schedule").await?;
+
+    for fork in spec.forks.scheduled() {
+        let found = schedule
+            .data
+            .iter()
+            .any(|f| f.current_version == fork.version && f.epoch == fork.epoch);
+        if !found {
+            return Err(mismatch(format!(
+                "consensus RPC has no fork {} at epoch {}",
+                fork.version, fork.epoch
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use crate::upstream::FixtureSource;
+    use axum::{routing::get, Json, Router};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    async fn beacon(genesis_validators_root: B256) -> String {
+        let spec = ChainSpec::sepolia();
+        let schedule: Vec<_> = spec
+            .forks
+            .scheduled()
+            .map(|f| json!({"previous_version": f.version, "current_version": f.version, "epoch": f.epoch.to_string()}))
+            .collect();
+        let app = Router::new()
+            .route(
+                "/eth/v1/beacon/genesis",
+                get(move || async move {
+                    Json(json!({"data": {
+                        "genesis_time": spec.genesis_time.to_string(),
+                        "genesis_validators_root": genesis_validators_root,
+                        "genesis_fork_version": spec.forks.genesis.version,
+                    }}))
+                }),
+            )
+            .route(
+                "/eth/v1/config/fork_schedule",
+                get(move || async move { Json(json!({ "data": schedule })) }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}/", addr)
+    }
+
+    #[tokio::test]
+    async fn test_chain_checks() {
+        let sepolia = ChainSpec::sepolia();
+        let url = beacon(sepolia.genesis_validators_root).await;
+        check_beacon(&url, &sepolia).await.unwrap();
+
+        let err = check_beacon(&url, &ChainSpec::mainnet()).await.unwrap_err();
+        assert!(err.downcast_ref::<ChainMismatch>().is_some());
+
+        let url = beacon(B256::ZERO).await;
+        let err = check_beacon(&url, &sepolia).await.unwrap_err();
+        assert!(err.downcast_ref::<ChainMismatch>().is_some());
+
+        let upstream = UpstreamClient::with_source(
+            FixtureSource::new(1).with_chain_id(11155111),
+            "fixture",
+            Arc::new(Metrics::new()),
+        );
+        check_upstream(&upstream, 11155111).await.unwrap();
+        let err = check_upstream(&upstream, 1).await.unwrap_err();
+        assert!(err.downcast_ref::<ChainMismatch>().is_some());
+    }
+}
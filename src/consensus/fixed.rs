@@ -0,0 +1,48 @@
+//! Consensus source serving a fixed set of headers, for tests.
+
+use alloy_eips::BlockNumberOrTag;
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use std::collections::BTreeMap;
+
+use super::{ConsensusSource, VerifiedHeader};
+
+/// Headers taken as verified. The highest is both the head and the finalized
+/// block.
+#[derive(Default)]
+pub struct FixedChain {
+    headers: BTreeMap<u64, VerifiedHeader>,
+}
+
+impl FixedChain {
+    pub fn new(headers: impl IntoIterator<Item = VerifiedHeader>) -> Self {
+        Self {
+            headers: headers.into_iter().map(|h| (h.number, h)).collect(),
+        }
+    }
+
+    fn head(&self) -> Option<&VerifiedHeader> {
+        self.headers.values().next_back()
+    }
+}
+
+#[async_trait]
+impl ConsensusSource for FixedChain {
+    async fn wait_synced(&self) -> Result<()> {
+        self.head().map(|_| ()).ok_or_else(|| eyre!("No headers"))
+    }
+
+    async fn block_number(&self) -> Result<u64> {
+        self.head()
+            .map(|h| h.number)
+            .ok_or_else(|| eyre!("No headers"))
+    }
+
+    async fn header(&self, block: BlockNumberOrTag) -> Result<Option<VerifiedHeader>> {
+        Ok(match block {
+            BlockNumberOrTag::Number(number) => self.headers.get(&number).cloned(),
+            BlockNumberOrTag::Earliest => self.headers.values().next().cloned(),
+            _ => self.head().cloned(),
+        })
+    }
+}
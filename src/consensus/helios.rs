@@ -0,0 +1,156 @@
+//! Helios light client as the consensus source.
+
+use alloy_eips::{BlockId, BlockNumberOrTag};
+use alloy_primitives::B256;
+use async_trait::async_trait;
+use eyre::Result;
+use helios_ethereum::{
+    config::{
+        networks::Network as HeliosNetwork,
+        types::{ChainConfig, Fork as HeliosFork, Forks as HeliosForks},
+        Config as HeliosConfig,
+    },
+    database::ConfigDB,
+    EthereumClient, EthereumClientBuilder,
+};
+use tokio::sync::RwLock;
+use tracing::info;
+
+use super::{ConsensusSource, VerifiedHeader};
+use crate::chain::{ChainSpec, Fork};
+use crate::config::{Config, Network};
+
+pub struct HeliosSource {
+    client: RwLock<EthereumClient>,
+}
+
+impl HeliosSource {
+    pub fn new(config: &Config, spec: &ChainSpec) -> Result<Self> {
+        info!(network = %config.ethereum.network, "Initializing Helios light client");
+
+        let builder: EthereumClientBuilder<ConfigDB> = EthereumClientBuilder::new();
+        let builder = match config.ethereum.network {
+            Network::Mainnet => builder.network(HeliosNetwork::Mainnet),
+            Network::Sepolia => builder.network(HeliosNetwork::Sepolia),
+            Network::Holesky => builder.network(HeliosNetwork::Holesky),
+            // Networks without a Helios preset are passed as a custom config
+            Network::Hoodi | Network::Custom => builder.config(custom_config(spec)),
+        };
+
+        let builder = builder
+            .execution_rpc(&config.ethereum.execution_rpc)?
+            .consensus_rpc(&config.ethereum.consensus_rpc)?;
+
+        let builder = match &spec.checkpoint_fallback {
+            Some(url) => builder.fallback(url)?,
+            None => builder,
+        };
+
+        // The external fallback list only covers public networks
+        let builder = match config.ethereum.network {
+            Network::Custom => builder,
+            _ => builder.load_external_fallback(),
+        };
+
+        let builder = match &config.consensus.checkpoint {
+            Some(checkpoint) if !checkpoint.is_empty() => {
+                let checkpoint_bytes = hex::decode(checkpoint.trim_start_matches("0x"))?;
+                let checkpoint_hash = B256::from_slice(&checkpoint_bytes);
+                builder.checkpoint(checkpoint_hash)
+            }
+            _ => builder,
+        };
+
+        let client = builder.build()?;
+
+        info!("Helios client built");
+
+        Ok(Self {
+            client: RwLock::new(client),
+        })
+    }
+}
+
+#[async_trait]
+impl ConsensusSource for HeliosSource {
+    async fn wait_synced(&self) -> Result<()> {
+        let client = self.client.read().await;
+        client.wait_synced().await?;
+        Ok(())
+    }
+
+    async fn block_number(&self) -> Result<u64> {
+        let client = self.client.read().await;
+        Ok(client.get_block_number().await?.to::<u64>())
+    }
+
+    async fn header(&self, block: BlockNumberOrTag) -> Result<Option<VerifiedHeader>> {
+        let client = self.client.read().await;
+        let block = client.get_block(BlockId::Number(block), false).await?;
+
+        Ok(block.map(|block| VerifiedHeader {
+            number: block.header.number,
+            hash: block.header.hash,
+            state_root: block.header.state_root,
+            transactions_root: block.header.transactions_root,
+            receipts_root: block.header.receipts_root,
+            timestamp: block.header.timestamp,
+        }))
+    }
+}
+
+/// Helios config describing the chain in `spec`.
+fn custom_config(spec: &ChainSpec) -> HeliosConfig {
+    let fork = |fork: &Fork| HeliosFork {
+        epoch: fork.epoch,
+        fork_version: fork.version,
+    };
+    // An unscheduled fork never activates. Every fork is set, as a default
+    // one would activate at genesis.
+    let unscheduled = HeliosFork {
+        epoch: u64::MAX,
+        fork_version: spec.forks.deneb.version,
+    };
+
+    HeliosConfig {
+        chain: ChainConfig {
+            chain_id: spec.chain_id,
+            genesis_time: spec.genesis_time,
+            genesis_root: spec.genesis_validators_root,
+        },
+        forks: HeliosForks {
+            genesis: fork(&spec.forks.genesis),
+            altair: fork(&spec.forks.altair),
+            bellatrix: fork(&spec.forks.bellatrix),
+            capella: fork(&spec.forks.capella),
+            deneb: fork(&spec.forks.deneb),
+            electra: spec
+                .forks
+                .electra
+                .as_ref()
+                .map(fork)
+                .unwrap_or_else(|| unscheduled.clone()),
+            fulu: spec.forks.fulu.as_ref().map(fork).unwrap_or(unscheduled),
+        },
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::fixed_bytes;
+
+    #[test]
+    fn test_custom_config_forks() {
+        let config = custom_config(&ChainSpec::hoodi());
+        assert_eq!(config.forks.electra.epoch, 2048);
+        assert_eq!(config.forks.fulu.epoch, 50688);
+        assert_eq!(config.forks.fulu.fork_version, fixed_bytes!("70000910"));
+
+        // A spec without Fulu leaves it unscheduled
+        let mut spec = ChainSpec::hoodi();
+        spec.forks.fulu = None;
+        assert_eq!(custom_config(&spec).forks.fulu.epoch, u64::MAX);
+    }
+}
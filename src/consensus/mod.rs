@@ -1,119 +1,149 @@
-//! Consensus layer integration via Helios light client.
-
-use alloy_primitives::{Address, B256, U256};
+//! Consensus layer integration.
+//!
+//! Verified headers come from a [`ConsensusSource`]: the Helios light client
+//! in production, a recording being replayed, or a fixed chain in tests.
+
+use alloy_eips::BlockNumberOrTag;
+use alloy_primitives::B256;
+use async_trait::async_trait;
 use eyre::Result;
-use helios_ethereum::{
-    config::networks::Network as HeliosNetwork, database::ConfigDB, EthereumClient,
-    EthereumClientBuilder,
-};
-use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::{debug, info};
-
-use crate::config::{Config, Network};
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument};
+
+use crate::chain::ChainSpec;
+use crate::config::Config;
 use crate::types::ConsensusProof;
 
+mod fixed;
+mod helios;
+mod record;
+
+pub use fixed::FixedChain;
+pub use helios::HeliosSource;
+pub use record::{RecordingSource, ReplaySource};
+
+/// A source of block headers verified against the beacon chain.
+#[async_trait]
+pub trait ConsensusSource: Send + Sync {
+    /// Resolve once the source has a verified head.
+    async fn wait_synced(&self) -> Result<()>;
+
+    /// Number of the verified head.
+    async fn block_number(&self) -> Result<u64>;
+
+    /// Verified header of `block`, or `None` if the source does not have it.
+    async fn header(&self, block: BlockNumberOrTag) -> Result<Option<VerifiedHeader>>;
+}
+
 pub struct ConsensusClient {
-    client: Arc<RwLock<EthereumClient>>,
+    source: Box<dyn ConsensusSource>,
+    spec: ChainSpec,
+}
+
+/// Roots of a block header verified by the light client.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiedHeader {
+    pub number: u64,
+    pub hash: B256,
+    pub state_root: B256,
+    pub transactions_root: B256,
+    pub receipts_root: B256,
+    pub timestamp: u64,
+}
+
+/// Snapshot of the light client's view of the chain.
+#[derive(Debug, Clone)]
+pub struct ConsensusStatus {
+    pub head_block: u64,
+    pub head_slot: u64,
+    pub finalized_block: u64,
+    pub finalized_slot: u64,
+    pub finalized_timestamp: u64,
 }
 
 impl ConsensusClient {
+    /// Helios light client for the configured network, recorded if
+    /// `ethereum.record` is set. With `ethereum.replay` set, the recorded
+    /// answers are replayed instead.
     pub async fn new(config: &Config) -> Result<Self> {
-        info!(network = %config.ethereum.network, "Initializing Helios light client");
-
-        let network = match config.ethereum.network {
-            Network::Mainnet => HeliosNetwork::Mainnet,
-            Network::Sepolia => HeliosNetwork::Sepolia,
-            Network::Holesky => HeliosNetwork::Holesky,
-        };
-
-        let fallback_url = match config.ethereum.network {
-            Network::Mainnet => "https://sync-mainnet.beaconcha.in",
-            Network::Sepolia => "https://sync-sepolia.beaconcha.in",
-            Network::Holesky => "https://sync-holesky.beaconcha.in",
-        };
-
-        let builder: EthereumClientBuilder<ConfigDB> = EthereumClientBuilder::new()
-            .network(network)
-            .execution_rpc(&config.ethereum.execution_rpc)?
-            .consensus_rpc(&config.ethereum.consensus_rpc)?
-            .fallback(fallback_url)?
-            .load_external_fallback();
-
-        let builder = match &config.consensus.checkpoint {
-            Some(checkpoint) if !checkpoint.is_empty() => {
-                let checkpoint_bytes = hex::decode(checkpoint.trim_start_matches("0x"))?;
-                let checkpoint_hash = B256::from_slice(&checkpoint_bytes);
-                builder.checkpoint(checkpoint_hash)
-            }
-            _ => builder,
-        };
-
-        let client = builder.build()?;
-
-        info!("Helios client built");
-
-        Ok(Self {
-            client: Arc::new(RwLock::new(client)),
-        })
-    }
+        let spec = config.ethereum.spec()?;
+        if let Some(path) = &config.ethereum.replay {
+            return Ok(Self::with_source(ReplaySource::load(path)?, spec));
+        }
 
-    pub async fn wait_for_sync(&self) -> Result<()> {
-        info!("Waiting for Helios sync...");
-        let client = self.client.read().await;
-        client.wait_synced().await?;
-        info!("Helios client synced");
-        Ok(())
+        let helios = HeliosSource::new(config, &spec)?;
+        Ok(match &config.ethereum.record {
+            Some(path) => Self::with_source(RecordingSource::new(helios, path)?, spec),
+            None => Self::with_source(helios, spec),
+        })
     }
 
-    pub async fn get_block_number(&self) -> u64 {
-        let client = self.client.read().await;
-        match client.get_block_number().await {
-            Ok(num) => num.to::<u64>(),
-            Err(_) => 0,
+    /// Client backed by any source, with slots computed from `spec`.
+    pub fn with_source(source: impl ConsensusSource + 'static, spec: ChainSpec) -> Self {
+        Self {
+            source: Box::new(source),
+            spec,
         }
     }
 
-    #[allow(dead_code)]
-    pub async fn get_balance(&self, address: Address, block: Option<u64>) -> Result<U256> {
-        debug!(%address, ?block, "Getting balance via Helios");
-        let client = self.client.read().await;
-        let block_id = block.map(|b| b.into()).unwrap_or_default();
-        let balance = client.get_balance(address, block_id).await?;
-        Ok(balance)
+    pub async fn wait_for_sync(&self) -> Result<()> {
+        info!("Waiting for consensus sync...");
+        self.source.wait_synced().await?;
+        info!("Consensus client synced");
+        Ok(())
     }
 
-    #[allow(dead_code)]
-    pub async fn get_storage_at(
-        &self,
-        address: Address,
-        slot: U256,
-        block: Option<u64>,
-    ) -> Result<B256> {
-        debug!(%address, %slot, ?block, "Getting storage via Helios");
-        let client = self.client.read().await;
-        let block_id = block.map(|b| b.into()).unwrap_or_default();
-        let value = client.get_storage_at(address, slot, block_id).await?;
-        Ok(value)
+    pub async fn get_block_number(&self) -> u64 {
+        self.source.block_number().await.unwrap_or(0)
     }
 
+    #[instrument(skip(self))]
     pub async fn get_consensus_proof(&self) -> Result<ConsensusProof> {
-        let client = self.client.read().await;
-        let block_number = client.get_block_number().await?.to::<u64>();
+        let block_number = self.source.block_number().await?;
 
         // Get the block to extract state root
-        let block = client
-            .get_block(block_number.into(), false)
+        let header = self
+            .get_header(block_number)
             .await?
             .ok_or_else(|| eyre::eyre!("Block not found"))?;
 
         Ok(ConsensusProof {
-            state_root: block.header.state_root,
-            slot: 0,
+            state_root: header.state_root,
+            block_hash: header.hash,
+            slot: self.spec.slot_at(header.timestamp),
             block_number,
         })
     }
 
+    /// Current verified head and finalized checkpoint.
+    pub async fn status(&self) -> Result<ConsensusStatus> {
+        let head = self
+            .source
+            .header(BlockNumberOrTag::Latest)
+            .await?
+            .ok_or_else(|| eyre::eyre!("Head block not found"))?;
+        let finalized = self
+            .source
+            .header(BlockNumberOrTag::Finalized)
+            .await?
+            .ok_or_else(|| eyre::eyre!("Finalized block not found"))?;
+
+        Ok(ConsensusStatus {
+            head_block: head.number,
+            head_slot: self.spec.slot_at(head.timestamp),
+            finalized_block: finalized.number,
+            finalized_slot: self.spec.slot_at(finalized.timestamp),
+            finalized_timestamp: finalized.timestamp,
+        })
+    }
+
+    /// Verified header at `number`, or `None` if the light client does not
+    /// have that block (beyond the verified head or outside its window).
+    pub async fn get_header(&self, number: u64) -> Result<Option<VerifiedHeader>> {
+        self.source.header(BlockNumberOrTag::Number(number)).await
+    }
+
     #[allow(dead_code)]
     pub async fn get_state_root(&self) -> Result<B256> {
         let proof = self.get_consensus_proof().await?;
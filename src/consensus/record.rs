@@ -0,0 +1,159 @@
+//! Recording of the light client's answers alongside upstream traffic, and
+//! replay of them as the consensus source.
+//!
+//! The verified head moves while a node runs, so replaying only the upstream
+//! would check recorded proofs against whatever the chain is at now. Each
+//! head and header the node was given is recorded as an exchange of its own,
+//! and replayed in the same order.
+
+use alloy_eips::BlockNumberOrTag;
+use async_trait::async_trait;
+use eyre::Result;
+use serde_json::json;
+use std::path::Path;
+
+use super::{ConsensusSource, VerifiedHeader};
+use crate::upstream::{Recording, ReplayTransport, Transport};
+
+fn block_number_request() -> serde_json::Value {
+    json!({"method": "consensus_blockNumber", "params": []})
+}
+
+fn header_request(block: BlockNumberOrTag) -> serde_json::Value {
+    json!({"method": "consensus_header", "params": [block.to_string()]})
+}
+
+/// Answers from `S`, appending each one to a recording.
+pub struct RecordingSource<S> {
+    inner: S,
+    recording: Recording,
+}
+
+impl<S: ConsensusSource> RecordingSource<S> {
+    pub fn new(inner: S, path: &Path) -> Result<Self> {
+        Ok(Self {
+            inner,
+            recording: Recording::open(path)?,
+        })
+    }
+}
+
+#[async_trait]
+impl<S: ConsensusSource> ConsensusSource for RecordingSource<S> {
+    async fn wait_synced(&self) -> Result<()> {
+        self.inner.wait_synced().await
+    }
+
+    async fn block_number(&self) -> Result<u64> {
+        let result = self.inner.block_number().await;
+        let recorded = match &result {
+            Ok(number) => Ok(json!(number)),
+            Err(e) => Err(eyre::eyre!("{}", e)),
+        };
+        self.recording.append(block_number_request(), &recorded);
+        result
+    }
+
+    async fn header(&self, block: BlockNumberOrTag) -> Result<Option<VerifiedHeader>> {
+        let result = self.inner.header(block).await;
+        let recorded = match &result {
+            Ok(header) => Ok(serde_json::to_value(header)?),
+            Err(e) => Err(eyre::eyre!("{}", e)),
+        };
+        self.recording.append(header_request(block), &recorded);
+        result
+    }
+}
+
+/// The light client's answers from a recording. Like upstream requests, each
+/// query gets its recorded answers in order, the last one repeating.
+pub struct ReplaySource {
+    replay: ReplayTransport,
+}
+
+impl ReplaySource {
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self {
+            replay: ReplayTransport::load(path)?,
+        })
+    }
+}
+
+#[async_trait]
+impl ConsensusSource for ReplaySource {
+    async fn wait_synced(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn block_number(&self) -> Result<u64> {
+        let number = self.replay.send(block_number_request()).await?;
+        Ok(serde_json::from_value(number)?)
+    }
+
+    async fn header(&self, block: BlockNumberOrTag) -> Result<Option<VerifiedHeader>> {
+        let header = self.replay.send(header_request(block)).await?;
+        Ok(serde_json::from_value(header)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::FixedChain;
+    use alloy_primitives::B256;
+
+    fn header(number: u64) -> VerifiedHeader {
+        VerifiedHeader {
+            number,
+            hash: B256::with_last_byte(number as u8),
+            state_root: B256::repeat_byte(number as u8),
+            transactions_root: B256::ZERO,
+            receipts_root: B256::ZERO,
+            timestamp: 1_700_000_000 + number * 12,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_recorded_heads() {
+        let path =
+            std::env::temp_dir().join(format!("dvrpc-consensus-test-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // The head moves between the two recorded queries
+        for head in [10, 11] {
+            let chain = FixedChain::new([header(head - 1), header(head)]);
+            let recorder = RecordingSource::new(chain, &path).unwrap();
+            assert_eq!(recorder.block_number().await.unwrap(), head);
+            recorder
+                .header(BlockNumberOrTag::Latest)
+                .await
+                .unwrap()
+                .unwrap();
+
+            // Wait for the writer thread
+            let lines = 2 * (head as usize - 9);
+            for _ in 0..100 {
+                let written = std::fs::read_to_string(&path).unwrap_or_default();
+                if written.lines().count() >= lines {
+                    break;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        }
+
+        let replay = ReplaySource::load(&path).unwrap();
+        assert_eq!(replay.block_number().await.unwrap(), 10);
+        assert_eq!(replay.block_number().await.unwrap(), 11);
+        assert_eq!(
+            replay.header(BlockNumberOrTag::Latest).await.unwrap(),
+            Some(header(10))
+        );
+        assert_eq!(
+            replay.header(BlockNumberOrTag::Latest).await.unwrap(),
+            Some(header(11))
+        );
+        assert!(replay.header(BlockNumberOrTag::Number(9)).await.is_err());
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
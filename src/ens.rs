@@ -0,0 +1,391 @@
+//! ENS name hashing, registry and resolver storage layouts, and the ABI
+//! encoding needed for resolver calls and CCIP-read (EIP-3668).
+//!
+//! Everything here is pure; fetching and proving lives in `rpc::ens`.
+
+use alloy_primitives::{address, keccak256, Address, Bytes, B256, U256};
+use eyre::{bail, Result};
+
+/// ENS registry (with fallback), deployed at the same address on every network.
+pub const REGISTRY: Address = address!("00000000000C2E074eC69A0dFb2997BA6C7d2e1e");
+
+/// Registry the current one falls back to for names it has no record of.
+pub const OLD_REGISTRY: Address = address!("314159265dD8dbb310642f98f50C066173C1259b");
+
+/// Highest declared slot probed when detecting a resolver's record layout.
+pub const MAX_PROBE_SLOT: u64 = 10;
+
+/// Longest `bytes`/`string` record read from storage.
+pub const MAX_RECORD_LEN: usize = 1024;
+
+/// Coin type of Ethereum addresses in multicoin address records (ENSIP-9).
+const COIN_TYPE_ETH: u64 = 60;
+
+const ADDR_SELECTOR: [u8; 4] = [0x3b, 0x3b, 0x57, 0xde];
+const NAME_SELECTOR: [u8; 4] = [0x69, 0x1f, 0x34, 0x31];
+const RESOLVE_SELECTOR: [u8; 4] = [0x90, 0x61, 0xb9, 0x23];
+const OFFCHAIN_LOOKUP_SELECTOR: [u8; 4] = [0x55, 0x6f, 0x18, 0x30];
+
+/// EIP-137 namehash of a normalized name.
+pub fn namehash(name: &str) -> B256 {
+    if name.is_empty() {
+        return B256::ZERO;
+    }
+    name.rsplit('.').fold(B256::ZERO, |node, label| {
+        keccak256([node.as_slice(), keccak256(label.as_bytes()).as_slice()].concat())
+    })
+}
+
+/// DNS wire format of a name, as passed to `resolve(bytes,bytes)` (ENSIP-10).
+pub fn dns_encode(name: &str) -> Result<Bytes> {
+    let mut out = Vec::with_capacity(name.len() + 2);
+    for label in name.split('.').filter(|l| !l.is_empty()) {
+        if label.len() > 255 {
+            bail!("label longer than 255 bytes");
+        }
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+    Ok(out.into())
+}
+
+/// The name itself followed by each parent, e.g. `a.b.eth`, `b.eth`, `eth`.
+pub fn ancestors(name: &str) -> Vec<&str> {
+    let mut names = vec![name];
+    let mut rest = name;
+    while let Some((_, parent)) = rest.split_once('.') {
+        names.push(parent);
+        rest = parent;
+    }
+    names
+}
+
+/// Reverse record name of an address (ENSIP-3).
+pub fn reverse_name(address: Address) -> String {
+    format!("{}.addr.reverse", hex::encode(address.as_slice()))
+}
+
+/// Solidity storage key of `mapping[key]` at `slot`.
+pub fn mapping_key(key: B256, slot: B256) -> B256 {
+    keccak256([key.as_slice(), slot.as_slice()].concat())
+}
+
+fn slot(index: u64) -> B256 {
+    U256::from(index).into()
+}
+
+/// Storage keys of a registry record's `owner` and packed `resolver, ttl`
+/// words. Records are `mapping(bytes32 => Record)` at slot 0.
+pub fn registry_keys(node: B256) -> (B256, B256) {
+    let base = mapping_key(node, slot(0));
+    let resolver = U256::from_be_bytes(base.0) + U256::from(1);
+    (base, resolver.into())
+}
+
+/// Address held in the low 20 bytes of a storage word.
+pub fn word_address(word: U256) -> Address {
+    Address::from_word(word.into())
+}
+
+/// A record a resolver can hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecordKind {
+    /// `addr(node)`
+    Addr,
+    /// `name(node)`, used for reverse records.
+    Name,
+}
+
+/// Where a resolver keeps a record, as found by probing its storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordLayout {
+    /// `mapping(bytes32 => address)` or `mapping(bytes32 => string)`.
+    Plain { slot: u64 },
+    /// `mapping(bytes32 => mapping(uint256 => bytes))` keyed by coin type.
+    Multicoin { slot: u64 },
+    /// Versioned records (`mapping(uint64 => mapping(bytes32 => ...))`), with
+    /// the record version in `mapping(bytes32 => uint64)` at slot 0.
+    Versioned { slot: u64 },
+}
+
+impl RecordLayout {
+    /// Layouts that can be checked without knowing a record version.
+    pub fn unversioned(kind: RecordKind) -> Vec<RecordLayout> {
+        (0..=MAX_PROBE_SLOT)
+            .flat_map(|slot| match kind {
+                RecordKind::Addr => vec![
+                    RecordLayout::Plain { slot },
+                    RecordLayout::Multicoin { slot },
+                ],
+                RecordKind::Name => vec![RecordLayout::Plain { slot }],
+            })
+            .collect()
+    }
+
+    pub fn versioned() -> Vec<RecordLayout> {
+        (1..=MAX_PROBE_SLOT)
+            .map(|slot| RecordLayout::Versioned { slot })
+            .collect()
+    }
+
+    /// Storage key of the record version of `node` in versioned resolvers.
+    pub fn version_key(node: B256) -> B256 {
+        mapping_key(node, slot(0))
+    }
+
+    /// Storage key of the record's first word.
+    pub fn key(&self, kind: RecordKind, node: B256, version: u64) -> B256 {
+        let (base, multicoin) = match *self {
+            RecordLayout::Plain { slot: s } => (mapping_key(node, slot(s)), false),
+            RecordLayout::Multicoin { slot: s } => (mapping_key(node, slot(s)), true),
+            RecordLayout::Versioned { slot: s } => (
+                mapping_key(node, mapping_key(slot(version), slot(s))),
+                kind == RecordKind::Addr,
+            ),
+        };
+        if multicoin {
+            mapping_key(slot(COIN_TYPE_ETH), base)
+        } else {
+            base
+        }
+    }
+
+    /// Whether the record is stored as `bytes`/`string` rather than a word.
+    pub fn is_bytes(&self, kind: RecordKind) -> bool {
+        kind == RecordKind::Name || !matches!(self, RecordLayout::Plain { .. })
+    }
+}
+
+/// First storage word of a `bytes`/`string` value. Values up to 31 bytes are
+/// stored inline with `len * 2` in the low byte, longer ones as `len * 2 + 1`
+/// with the data at `keccak256(key)`.
+pub fn bytes_header(value: &[u8]) -> U256 {
+    if value.len() < 32 {
+        let mut word = [0u8; 32];
+        word[..value.len()].copy_from_slice(value);
+        word[31] = (value.len() * 2) as u8;
+        U256::from_be_bytes(word)
+    } else {
+        U256::from(value.len() * 2 + 1)
+    }
+}
+
+/// A decoded `bytes`/`string` header word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BytesHeader {
+    /// Value stored inline.
+    Short(Vec<u8>),
+    /// Length of a value stored at `keccak256(key)`.
+    Long(usize),
+}
+
+impl BytesHeader {
+    pub fn decode(word: U256) -> Option<Self> {
+        let bytes = word.to_be_bytes::<32>();
+        if bytes[31] & 1 == 0 {
+            let len = usize::from(bytes[31] / 2);
+            (len < 32).then(|| BytesHeader::Short(bytes[..len].to_vec()))
+        } else {
+            let len: usize = (word >> 1usize).try_into().ok()?;
+            Some(BytesHeader::Long(len))
+        }
+    }
+}
+
+/// Storage keys holding the data of a long `bytes`/`string` value.
+pub fn bytes_data_keys(key: B256, len: usize) -> Vec<B256> {
+    let start = U256::from_be_bytes(keccak256(key.as_slice()).0);
+    (0..len.div_ceil(32))
+        .map(|i| (start + U256::from(i)).into())
+        .collect()
+}
+
+/// Calldata for `addr(node)`.
+pub fn addr_call(node: B256) -> Bytes {
+    [&ADDR_SELECTOR[..], node.as_slice()].concat().into()
+}
+
+/// Calldata for `name(node)`.
+pub fn name_call(node: B256) -> Bytes {
+    [&NAME_SELECTOR[..], node.as_slice()].concat().into()
+}
+
+/// Calldata for `resolve(name, data)` on an extended resolver.
+pub fn resolve_call(dns_name: &[u8], data: &[u8]) -> Bytes {
+    [&RESOLVE_SELECTOR[..], &encode_bytes_args(&[dns_name, data])]
+        .concat()
+        .into()
+}
+
+/// Calldata for an EIP-3668 callback: `callback(response, extraData)`.
+pub fn callback_call(selector: [u8; 4], response: &[u8], extra_data: &[u8]) -> Bytes {
+    [&selector[..], &encode_bytes_args(&[response, extra_data])]
+        .concat()
+        .into()
+}
+
+/// ABI-encode a tuple of dynamic `bytes` arguments.
+fn encode_bytes_args(args: &[&[u8]]) -> Vec<u8> {
+    let mut head = Vec::new();
+    let mut tail = Vec::new();
+    for arg in args {
+        head.extend_from_slice(&B256::from(U256::from(args.len() * 32 + tail.len())).0);
+        tail.extend_from_slice(&B256::from(U256::from(arg.len())).0);
+        tail.extend_from_slice(arg);
+        tail.resize(tail.len().div_ceil(32) * 32, 0);
+    }
+    [head, tail].concat()
+}
+
+/// Word `index` of ABI data.
+fn word(data: &[u8], index: usize) -> Option<U256> {
+    let start = index.checked_mul(32)?;
+    let bytes = data.get(start..start.checked_add(32)?)?;
+    Some(U256::from_be_slice(bytes))
+}
+
+/// Dynamic `bytes` whose offset is in word `index`, relative to `data`.
+fn dynamic_bytes(data: &[u8], index: usize) -> Option<Vec<u8>> {
+    let offset: usize = word(data, index)?.try_into().ok()?;
+    let tail = data.get(offset..)?;
+    let len: usize = word(tail, 0)?.try_into().ok()?;
+    tail.get(32..32usize.checked_add(len)?).map(<[u8]>::to_vec)
+}
+
+/// Decode an ABI-encoded `address` return value.
+pub fn decode_address(data: &[u8]) -> Option<Address> {
+    (data.len() == 32).then(|| word_address(U256::from_be_slice(data)))
+}
+
+/// Decode an ABI-encoded `bytes` or `string` return value.
+pub fn decode_bytes(data: &[u8]) -> Option<Vec<u8>> {
+    dynamic_bytes(data, 0)
+}
+
+/// Parsed `OffchainLookup(address,string[],bytes,bytes4,bytes)` revert.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffchainLookup {
+    pub sender: Address,
+    pub urls: Vec<String>,
+    pub call_data: Vec<u8>,
+    pub callback: [u8; 4],
+    pub extra_data: Vec<u8>,
+}
+
+impl OffchainLookup {
+    /// Decode revert data, returning `None` if it is not an `OffchainLookup`.
+    pub fn decode(revert: &[u8]) -> Option<Self> {
+        let data = revert.strip_prefix(&OFFCHAIN_LOOKUP_SELECTOR[..])?;
+
+        let urls_offset: usize = word(data, 1)?.try_into().ok()?;
+        let urls_data = data.get(urls_offset..)?;
+        let count: usize = word(urls_data, 0)?.try_into().ok()?;
+        let items = urls_data.get(32..)?;
+        let urls = (0..count.min(16))
+            .map(|i| String::from_utf8(dynamic_bytes(items, i)?).ok())
+            .collect::<Option<Vec<_>>>()?;
+
+        let callback_word = word(data, 3)?.to_be_bytes::<32>();
+
+        Some(Self {
+            sender: word_address(word(data, 0)?),
+            urls,
+            call_data: dynamic_bytes(data, 2)?,
+            callback: callback_word[..4].try_into().ok()?,
+            extra_data: dynamic_bytes(data, 4)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::b256;
+
+    #[test]
+    fn test_namehash() {
+        assert_eq!(namehash(""), B256::ZERO);
+        assert_eq!(
+            namehash("eth"),
+            b256!("93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae")
+        );
+        assert_eq!(
+            namehash("foo.eth"),
+            b256!("de9b09fd7c5f901e23a3f19fecc54828e9c848539801e86591bd9801b019f84f")
+        );
+    }
+
+    #[test]
+    fn test_dns_encode_and_ancestors() {
+        assert_eq!(
+            dns_encode("a.bc.eth").unwrap().as_ref(),
+            b"\x01a\x02bc\x03eth\x00"
+        );
+        assert_eq!(ancestors("a.bc.eth"), vec!["a.bc.eth", "bc.eth", "eth"]);
+    }
+
+    #[test]
+    fn test_bytes_header() {
+        let address = Address::repeat_byte(0xab);
+        let header = bytes_header(address.as_slice()).to_be_bytes::<32>();
+        assert_eq!(&header[..20], address.as_slice());
+        assert_eq!(header[31], 40);
+        assert_eq!(bytes_header(&[0u8; 40]), U256::from(81));
+        assert_eq!(
+            BytesHeader::decode(bytes_header(address.as_slice())),
+            Some(BytesHeader::Short(address.to_vec()))
+        );
+        assert_eq!(
+            BytesHeader::decode(U256::from(81)),
+            Some(BytesHeader::Long(40))
+        );
+        assert_eq!(bytes_data_keys(B256::ZERO, 40).len(), 2);
+    }
+
+    #[test]
+    fn test_offchain_lookup_roundtrip() {
+        let sender = Address::repeat_byte(0x11);
+        let url = b"https://gateway.example/{sender}/{data}.json";
+        let call_data = [0xaa; 36];
+        let extra_data = [0xbb; 3];
+
+        // OffchainLookup(sender, [url], callData, callback, extraData)
+        let mut data = OFFCHAIN_LOOKUP_SELECTOR.to_vec();
+        let mut push = |word: U256| data.extend_from_slice(&B256::from(word).0);
+        push(U256::from_be_slice(sender.as_slice()));
+        push(U256::from(5 * 32));
+        push(U256::from(5 * 32 + 3 * 32 + 64));
+        push(U256::from_be_slice(&[0x12, 0x34, 0x56, 0x78]) << 224);
+        push(U256::from(5 * 32 + 3 * 32 + 64 + 32 + 64));
+        // urls: count, offset of the only string, then the string
+        push(U256::from(1));
+        push(U256::from(32));
+        push(U256::from(url.len()));
+        data.extend_from_slice(url);
+        data.resize(data.len() + (64 - url.len()), 0);
+        data.extend_from_slice(&encode_bytes_args(&[&call_data])[32..]);
+        data.extend_from_slice(&encode_bytes_args(&[&extra_data])[32..]);
+
+        let lookup = OffchainLookup::decode(&data).unwrap();
+        assert_eq!(lookup.sender, sender);
+        assert_eq!(lookup.urls, vec![String::from_utf8(url.to_vec()).unwrap()]);
+        assert_eq!(lookup.call_data, call_data);
+        assert_eq!(lookup.callback, [0x12, 0x34, 0x56, 0x78]);
+        assert_eq!(lookup.extra_data, extra_data);
+
+        assert!(OffchainLookup::decode(&data[..100]).is_none());
+    }
+
+    #[test]
+    fn test_decode_return_values() {
+        let address = Address::repeat_byte(0x42);
+        assert_eq!(
+            decode_address(&B256::left_padding_from(address.as_slice()).0),
+            Some(address)
+        );
+        let encoded = encode_bytes_args(&[b"vitalik.eth"]);
+        assert_eq!(decode_bytes(&encoded), Some(b"vitalik.eth".to_vec()));
+        assert_eq!(decode_bytes(&encoded[..40]), None);
+    }
+}
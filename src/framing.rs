@@ -0,0 +1,125 @@
+//! Splitting a byte stream of JSON values written back to back, as on an
+//! IPC socket, into one message per value.
+//!
+//! Bytes are scanned once as they arrive: the scanner keeps its place,
+//! nesting depth and string state between reads, so a large message split
+//! over many reads costs no more than one that arrives whole.
+
+use eyre::{bail, Result};
+
+/// Buffered bytes and how far they have been scanned.
+#[derive(Default)]
+pub struct JsonFrames {
+    buffer: Vec<u8>,
+    /// Bytes of `buffer` already scanned.
+    scanned: usize,
+    /// Nesting depth at `scanned`.
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl JsonFrames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Bytes buffered but not yet returned as a message.
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// The next complete top-level object or array, if one has arrived.
+    /// Anything else at the top level cannot be framed and is an error, as is
+    /// a byte that cannot appear in JSON outside a string, so garbage is
+    /// refused without waiting for a closing bracket that never comes.
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>> {
+        while self.scanned < self.buffer.len() {
+            let byte = self.buffer[self.scanned];
+            self.scanned += 1;
+
+            if self.in_string {
+                match byte {
+                    _ if self.escaped => self.escaped = false,
+                    b'\\' => self.escaped = true,
+                    b'"' => self.in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match byte {
+                b'{' | b'[' => self.depth += 1,
+                b'}' | b']' if self.depth > 0 => {
+                    self.depth -= 1;
+                    if self.depth == 0 {
+                        let frame: Vec<u8> = self.buffer.drain(..self.scanned).collect();
+                        self.scanned = 0;
+                        return Ok(Some(frame));
+                    }
+                }
+                b'"' if self.depth > 0 => self.in_string = true,
+                b' ' | b'\t' | b'\r' | b'\n' if self.depth == 0 => {
+                    // Drop whitespace between messages
+                    self.buffer.drain(..self.scanned);
+                    self.scanned = 0;
+                }
+                _ if self.depth == 0 => {
+                    bail!("expected a JSON object or array, found {:?}", byte as char)
+                }
+                // Numbers, literals and separators
+                b'0'..=b'9' | b'-' | b'+' | b'.' | b',' | b':' | b' ' | b'\t' | b'\r' | b'\n' => {}
+                b'a' | b'e' | b'E' | b'f' | b'l' | b'n' | b'r' | b's' | b't' | b'u' => {}
+                _ => bail!("unexpected {:?} in JSON", byte as char),
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_split_across_reads() {
+        let stream = br#" {"a":"}{\"[","b":[1,{}]}
+[{"id":1}]{}"#;
+        let mut frames = JsonFrames::new();
+        let mut found = Vec::new();
+        for chunk in stream.chunks(3) {
+            frames.extend(chunk);
+            while let Some(frame) = frames.next_frame().unwrap() {
+                found.push(String::from_utf8(frame).unwrap());
+            }
+        }
+        assert_eq!(
+            found,
+            [r#"{"a":"}{\"[","b":[1,{}]}"#, r#"[{"id":1}]"#, "{}"]
+        );
+        assert!(frames.is_empty());
+    }
+
+    #[test]
+    fn test_frames_reject_scalars() {
+        let mut frames = JsonFrames::new();
+        frames.extend(b"{}\n42");
+        assert!(frames.next_frame().unwrap().is_some());
+        assert!(frames.next_frame().is_err());
+    }
+
+    #[test]
+    fn test_frames_reject_garbage() {
+        let mut frames = JsonFrames::new();
+        frames.extend(br#"{"ok": [true, null, -1.5e3]}{not json"#);
+        assert!(frames.next_frame().unwrap().is_some());
+        assert!(frames.next_frame().is_err());
+    }
+}
@@ -0,0 +1,23 @@
+//! Decentralized Verified RPC node for Ethereum.
+//!
+//! Serves Ethereum JSON-RPC responses verified against a light client state
+//! root using EIP-1186 Merkle proofs.
+
+pub mod access;
+pub mod attestation;
+pub mod auth;
+pub mod chain;
+pub mod config;
+pub mod consensus;
+pub mod ens;
+pub mod framing;
+pub mod metrics;
+pub mod p2p;
+pub mod proof;
+pub mod rpc;
+pub mod telemetry;
+pub mod tls;
+pub mod token;
+pub mod tx;
+pub mod types;
+pub mod upstream;
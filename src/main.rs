@@ -1,18 +1,16 @@
 use clap::Parser;
 use eyre::Result;
+use std::fmt;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::sync::Arc;
 use tracing::{info, warn, Level};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
 use tracing_subscriber::{fmt, EnvFilter};
 
-mod config;
-mod consensus;
-mod proof;
-mod rpc;
-mod types;
-mod upstream;
-
-use config::Config;
+use dvrpc_node::config::Config;
+use dvrpc_node::{chain, consensus, proof, rpc, telemetry};
 
 #[derive(Parser, Debug)]
 #[command(name = "dvrpc-node")]
@@ -31,55 +29,32 @@ struct Args {
     #[arg(long)]
     port: Option<u16>,
 
-    /// Log level (trace, debug, info, warn, error)
-    #[arg(long, default_value = "info")]
-    log_level: Level,
+    /// Log level (trace, debug, info, warn, error), overriding logging.level
+    #[arg(long)]
+    log_level: Option<Level>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
 
-    // Initialize logging
-    let filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| EnvFilter::new(args.log_level.to_string()));
-
-    fmt()
-        .with_env_filter(filter)
-        .with_target(true)
-        .with_thread_ids(false)
-        .init();
+    // Logging is configured by the config file, so it is loaded first
+    let (config, source) = load_config(&args)?;
+    let tracer_provider = init_logging(&args, &config)?;
 
     info!("Starting DVRPC Node");
-
-    // Load configuration
-    let mut config = match &args.config {
-        Some(path) if path.exists() => {
-            info!(path = %path.display(), "Loading config from file with env overrides");
-            Config::load_with_env(path)?
-        }
-        Some(path) => {
-            return Err(eyre::eyre!("Config file not found: {}", path.display()));
-        }
-        None => {
-            info!("No config file specified, using environment variables");
-            Config::from_env()?
+    info!(%source, %config, "Configuration loaded");
+
+    // Refuse to follow a beacon node on another chain. A replay contacts none.
+    if config.consensus.enabled && config.ethereum.replay.is_none() {
+        let spec = config.ethereum.spec()?;
+        match chain::check_beacon(&config.ethereum.consensus_rpc, &spec).await {
+            Ok(()) => info!("Consensus RPC is on the configured chain"),
+            Err(e) if e.downcast_ref::<chain::ChainMismatch>().is_some() => return Err(e),
+            Err(e) => warn!("Failed to check consensus RPC chain: {}", e),
         }
-    };
-
-    // Apply CLI overrides
-    if let Some(host) = args.host {
-        config.server.host = host;
-    }
-    if let Some(port) = args.port {
-        config.server.port = port;
     }
 
-    info!(
-        network = %config.ethereum.network,
-        "Configuration loaded"
-    );
-
     // Initialize consensus client
     let consensus_client = if config.consensus.enabled {
         info!("Initializing consensus client");
@@ -119,7 +94,90 @@ async fn main() -> Result<()> {
 
     info!(%addr, "Starting RPC server");
 
-    rpc::serve(addr, config, consensus_client, proof_generator).await?;
+    let loader = Arc::new(move || load_config(&args).map(|(config, _)| config));
+    let served = rpc::serve(addr, config, consensus_client, proof_generator, loader).await;
+
+    // Flush spans still queued for export
+    if let Some(provider) = tracer_provider {
+        let _ = tokio::task::spawn_blocking(move || provider.shutdown()).await;
+    }
+
+    served
+}
+
+/// Install the global subscriber. `RUST_LOG` wins over `--log-level`, which
+/// wins over `logging.level`. Spans are exported at info level regardless,
+/// when an OTLP endpoint is configured.
+fn init_logging(
+    args: &Args,
+    config: &Config,
+) -> Result<Option<opentelemetry_sdk::trace::SdkTracerProvider>> {
+    let filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter,
+        Err(_) => match args.log_level {
+            Some(level) => EnvFilter::new(level.to_string()),
+            None => EnvFilter::try_new(&config.logging.level)
+                .map_err(|e| eyre::eyre!("Invalid logging.level: {}", e))?,
+        },
+    };
+
+    let output = fmt::layer().with_target(true).with_thread_ids(false);
+    let output = match config.logging.format.as_str() {
+        "json" => output.json().with_current_span(true).boxed(),
+        _ => output.boxed(),
+    };
+
+    let tracer_provider = telemetry::tracer_provider(&config.telemetry)?;
+    let export = tracer_provider
+        .as_ref()
+        .map(|provider| telemetry::layer(provider).with_filter(LevelFilter::INFO));
+
+    tracing_subscriber::registry()
+        .with(output.with_filter(filter))
+        .with(export)
+        .init();
+    Ok(tracer_provider)
+}
+
+/// Where the config was read from.
+enum ConfigSource {
+    /// A config file, with environment overrides.
+    File(PathBuf),
+    /// The environment alone, as no file was given.
+    Environment,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::File(path) => write!(f, "{} with env overrides", path.display()),
+            ConfigSource::Environment => write!(f, "environment variables"),
+        }
+    }
+}
+
+/// Load the config file, or the environment without one, and apply CLI
+/// overrides. Also used to reload the config. Nothing is logged here, as the
+/// subscriber is only installed once the first config is loaded.
+fn load_config(args: &Args) -> Result<(Config, ConfigSource)> {
+    let (mut config, source) = match &args.config {
+        Some(path) if path.exists() => (
+            Config::load_with_env(path)?,
+            ConfigSource::File(path.clone()),
+        ),
+        Some(path) => {
+            return Err(eyre::eyre!("Config file not found: {}", path.display()));
+        }
+        None => (Config::from_env()?, ConfigSource::Environment),
+    };
+
+    // Apply CLI overrides
+    if let Some(ref host) = args.host {
+        config.server.host = host.clone();
+    }
+    if let Some(port) = args.port {
+        config.server.port = port;
+    }
 
-    Ok(())
+    Ok((config, source))
 }
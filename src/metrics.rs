@@ -0,0 +1,292 @@
+//! Prometheus metrics for requests, verification and upstream health.
+//!
+//! Verification and upstream observations are also noted on the current
+//! request's access log entry.
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use std::time::Duration;
+
+use crate::access;
+
+/// Outcome of verifying a response against the light client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationOutcome {
+    /// Proof checked against a consensus-verified state root.
+    Verified,
+    /// Proof was invalid or could not be checked.
+    Failed,
+    /// No consensus state root was available, data served unverified.
+    Skipped,
+}
+
+impl VerificationOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerificationOutcome::Verified => "verified",
+            VerificationOutcome::Failed => "failed",
+            VerificationOutcome::Skipped => "skipped",
+        }
+    }
+}
+
+/// All node metrics, registered in a dedicated registry.
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+    verifications: IntCounterVec,
+    upstream_duration: HistogramVec,
+    upstream_errors: IntCounterVec,
+    verified_head: IntGauge,
+    upstream_head: IntGauge,
+    head_lag: IntGauge,
+    proof_cache_hits: IntCounter,
+    proof_cache_misses: IntCounter,
+    proof_size: HistogramVec,
+    api_key_requests: IntCounterVec,
+    rejected_requests: IntCounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry =
+            Registry::new_custom(Some("dvrpc".to_string()), None).expect("valid metrics prefix");
+
+        let requests = IntCounterVec::new(
+            Opts::new("requests_total", "JSON-RPC requests by method"),
+            &["method"],
+        )
+        .unwrap();
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "request_duration_seconds",
+                "JSON-RPC request latency by method",
+            ),
+            &["method"],
+        )
+        .unwrap();
+        let verifications = IntCounterVec::new(
+            Opts::new(
+                "verifications_total",
+                "Verification outcomes (verified, failed, skipped) by method",
+            ),
+            &["method", "outcome"],
+        )
+        .unwrap();
+        let upstream_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "upstream_request_duration_seconds",
+                "Upstream request latency by endpoint and method",
+            ),
+            &["endpoint", "method"],
+        )
+        .unwrap();
+        let upstream_errors = IntCounterVec::new(
+            Opts::new(
+                "upstream_errors_total",
+                "Failed upstream requests by endpoint and method",
+            ),
+            &["endpoint", "method"],
+        )
+        .unwrap();
+        let verified_head = IntGauge::new(
+            "verified_head_block",
+            "Latest block number verified by the light client",
+        )
+        .unwrap();
+        let upstream_head = IntGauge::new(
+            "upstream_head_block",
+            "Latest block number reported by the upstream",
+        )
+        .unwrap();
+        let head_lag = IntGauge::new(
+            "light_client_head_lag_blocks",
+            "Blocks between the upstream head and the verified head",
+        )
+        .unwrap();
+        let proof_cache_hits =
+            IntCounter::new("proof_cache_hits_total", "Proofs served from the cache").unwrap();
+        let proof_cache_misses = IntCounter::new(
+            "proof_cache_misses_total",
+            "Proof lookups that went to the upstream",
+        )
+        .unwrap();
+        let proof_size = HistogramVec::new(
+            HistogramOpts::new("proof_size_bytes", "Total size of fetched proof nodes")
+                .buckets(prometheus::exponential_buckets(512.0, 2.0, 10).unwrap()),
+            &["method"],
+        )
+        .unwrap();
+
+        registry.register(Box::new(requests.clone())).unwrap();
+        registry
+            .register(Box::new(request_duration.clone()))
+            .unwrap();
+        registry.register(Box::new(verifications.clone())).unwrap();
+        registry
+            .register(Box::new(upstream_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(upstream_errors.clone()))
+            .unwrap();
+        registry.register(Box::new(verified_head.clone())).unwrap();
+        registry.register(Box::new(upstream_head.clone())).unwrap();
+        registry.register(Box::new(head_lag.clone())).unwrap();
+        registry
+            .register(Box::new(proof_cache_hits.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(proof_cache_misses.clone()))
+            .unwrap();
+        let api_key_requests = IntCounterVec::new(
+            Opts::new(
+                "api_key_requests_total",
+                "Requests per API key by result (allowed, rate_limited, denied)",
+            ),
+            &["key", "result"],
+        )
+        .unwrap();
+
+        let rejected_requests = IntCounterVec::new(
+            Opts::new(
+                "rejected_requests_total",
+                "Requests rejected by server limits by reason (overloaded, timeout, body)",
+            ),
+            &["reason"],
+        )
+        .unwrap();
+
+        registry.register(Box::new(proof_size.clone())).unwrap();
+        registry
+            .register(Box::new(api_key_requests.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(rejected_requests.clone()))
+            .unwrap();
+
+        Self {
+            registry,
+            requests,
+            request_duration,
+            verifications,
+            upstream_duration,
+            upstream_errors,
+            verified_head,
+            upstream_head,
+            head_lag,
+            proof_cache_hits,
+            proof_cache_misses,
+            proof_size,
+            api_key_requests,
+            rejected_requests,
+        }
+    }
+
+    pub fn record_request(&self, method: &str, elapsed: Duration) {
+        self.requests.with_label_values(&[method]).inc();
+        self.request_duration
+            .with_label_values(&[method])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn record_verification(&self, method: &str, outcome: VerificationOutcome) {
+        access::record_verification(outcome);
+        self.verifications
+            .with_label_values(&[method, outcome.as_str()])
+            .inc();
+    }
+
+    pub fn record_upstream(&self, endpoint: &str, method: &str, elapsed: Duration, ok: bool) {
+        access::record_upstream(endpoint);
+        self.upstream_duration
+            .with_label_values(&[endpoint, method])
+            .observe(elapsed.as_secs_f64());
+        if !ok {
+            self.upstream_errors
+                .with_label_values(&[endpoint, method])
+                .inc();
+        }
+    }
+
+    pub fn record_heads(&self, verified: u64, upstream: u64) {
+        self.verified_head.set(verified as i64);
+        self.upstream_head.set(upstream as i64);
+        self.head_lag.set(upstream as i64 - verified as i64);
+    }
+
+    pub fn record_proof_cache(&self, hit: bool) {
+        if hit {
+            access::record_upstream("cache");
+            self.proof_cache_hits.inc();
+        } else {
+            self.proof_cache_misses.inc();
+        }
+    }
+
+    pub fn record_proof_size(&self, method: &str, bytes: usize) {
+        self.proof_size
+            .with_label_values(&[method])
+            .observe(bytes as f64);
+    }
+
+    /// Count a request made with the named API key.
+    pub fn record_api_key(&self, key: &str, result: &str) {
+        self.api_key_requests
+            .with_label_values(&[key, result])
+            .inc();
+    }
+
+    /// Count a request rejected by a server limit.
+    pub fn record_rejected(&self, reason: &str) {
+        self.rejected_requests.with_label_values(&[reason]).inc();
+    }
+
+    /// Render all metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("text encoding never fails");
+        String::from_utf8(buffer).expect("text encoding is utf-8")
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_includes_recorded_metrics() {
+        let metrics = Metrics::new();
+        metrics.record_request("eth_getBalance", Duration::from_millis(5));
+        metrics.record_verification("eth_getBalance", VerificationOutcome::Verified);
+        metrics.record_upstream(
+            "eth.example",
+            "eth_getProof",
+            Duration::from_millis(3),
+            false,
+        );
+        metrics.record_heads(100, 103);
+        metrics.record_proof_cache(true);
+
+        let output = metrics.render();
+        assert!(output.contains("dvrpc_requests_total{method=\"eth_getBalance\"} 1"));
+        assert!(output.contains(
+            "dvrpc_verifications_total{method=\"eth_getBalance\",outcome=\"verified\"} 1"
+        ));
+        assert!(output.contains(
+            "dvrpc_upstream_errors_total{endpoint=\"eth.example\",method=\"eth_getProof\"} 1"
+        ));
+        assert!(output.contains("dvrpc_light_client_head_lag_blocks 3"));
+        assert!(output.contains("dvrpc_proof_cache_hits_total 1"));
+    }
+}
@@ -0,0 +1,596 @@
+//! Gossip of verified headers and proofs between DVRPC nodes.
+//!
+//! Nodes share headers their light client verified and proofs they verified
+//! against those headers' state roots. Nothing received is trusted: messages
+//! are only forwarded once the receiving node has re-verified them and
+//! reported them as accepted (gossipsub's strict validation mode). Peers on the
+//! local network are found with mDNS; others can be listed as bootstrap peers.
+//!
+//! Nodes can also ask each other for proofs directly over a request/response
+//! protocol carrying JSON-RPC bodies, so a node can use its peers in place of
+//! an RPC provider.
+
+use alloy_primitives::B256;
+use eyre::{eyre, Result, WrapErr};
+use futures::StreamExt;
+use libp2p::gossipsub::{self, IdentTopic, MessageAcceptance, MessageId};
+use libp2p::request_response::{self, OutboundRequestId, ProtocolSupport, ResponseChannel};
+use libp2p::swarm::{behaviour::toggle::Toggle, NetworkBehaviour, SwarmEvent};
+use libp2p::{identity, mdns, noise, tcp, yamux, Multiaddr, PeerId, StreamProtocol, Swarm};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, info, warn};
+
+use crate::config::P2pConfig;
+use crate::types::ProofData;
+
+/// Largest gossip message accepted, enough for proofs of large contracts.
+const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+/// Messages queued for the swarm before new ones are dropped.
+const COMMAND_QUEUE: usize = 256;
+
+/// Request/response protocol for fetching proofs from peers.
+const PROOF_PROTOCOL: StreamProtocol = StreamProtocol::new("/dvrpc/proof/1");
+
+/// Time a peer has to answer a proof request.
+const PEER_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Peers tried for one request before giving up.
+const MAX_PEER_ATTEMPTS: usize = 3;
+
+/// Something a node verified and shares with its peers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum GossipMessage {
+    /// A header the sender's light client verified.
+    #[serde(rename_all = "camelCase")]
+    Header {
+        number: u64,
+        hash: B256,
+        state_root: B256,
+    },
+    /// A proof the sender verified against the state root of `block_number`.
+    #[serde(rename_all = "camelCase")]
+    Proof {
+        block_number: u64,
+        state_root: B256,
+        proof: ProofData,
+    },
+}
+
+impl GossipMessage {
+    fn topic(&self, topics: &Topics) -> IdentTopic {
+        match self {
+            GossipMessage::Header { .. } => topics.headers.clone(),
+            GossipMessage::Proof { .. } => topics.proofs.clone(),
+        }
+    }
+}
+
+/// A message received from a peer, waiting for validation.
+#[derive(Debug)]
+pub struct Inbound {
+    pub id: MessageId,
+    pub source: PeerId,
+    pub message: GossipMessage,
+}
+
+/// Answer to a proof request: the serving node's JSON-RPC response, or why it
+/// would not serve the request.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PeerResponse {
+    Result(serde_json::Value),
+    Error(String),
+}
+
+/// A JSON-RPC body a peer asked this node to answer.
+#[derive(Debug)]
+pub struct PeerQuery {
+    pub peer: PeerId,
+    pub body: serde_json::Value,
+    channel: ResponseChannel<PeerResponse>,
+}
+
+/// What the node receives from its peers.
+pub struct P2pEvents {
+    /// Gossip, each of which must be reported back through [`P2pHandle::report`].
+    pub gossip: mpsc::Receiver<Inbound>,
+    /// Proof requests, each of which must be answered through
+    /// [`P2pHandle::respond`].
+    pub queries: mpsc::Receiver<PeerQuery>,
+}
+
+enum Command {
+    Publish(GossipMessage),
+    Report(MessageId, PeerId, MessageAcceptance),
+    Request(
+        serde_json::Value,
+        usize,
+        oneshot::Sender<Result<PeerResponse>>,
+    ),
+    Respond(ResponseChannel<PeerResponse>, PeerResponse),
+}
+
+#[derive(Clone)]
+struct Topics {
+    headers: IdentTopic,
+    proofs: IdentTopic,
+}
+
+impl Topics {
+    /// Topics are per chain so nodes on different networks never mix.
+    fn new(chain_id: u64) -> Self {
+        Self {
+            headers: IdentTopic::new(format!("/dvrpc/{}/headers/1", chain_id)),
+            proofs: IdentTopic::new(format!("/dvrpc/{}/proofs/1", chain_id)),
+        }
+    }
+}
+
+#[derive(NetworkBehaviour)]
+struct Behaviour {
+    gossipsub: gossipsub::Behaviour,
+    mdns: Toggle<mdns::tokio::Behaviour>,
+    proofs: request_response::json::Behaviour<serde_json::Value, PeerResponse>,
+}
+
+/// Handle to the running P2P node.
+#[derive(Clone)]
+pub struct P2pHandle {
+    peer_id: PeerId,
+    commands: mpsc::Sender<Command>,
+    listen_addrs: Arc<Mutex<Vec<Multiaddr>>>,
+}
+
+impl P2pHandle {
+    pub fn peer_id(&self) -> PeerId {
+        self.peer_id
+    }
+
+    /// Addresses the node is listening on, once bound.
+    pub fn listen_addrs(&self) -> Vec<Multiaddr> {
+        self.listen_addrs.lock().unwrap().clone()
+    }
+
+    /// Share a verified header or proof. Dropped if the swarm is backed up.
+    pub fn publish(&self, message: GossipMessage) {
+        if self.commands.try_send(Command::Publish(message)).is_err() {
+            debug!("P2P command queue full, dropping gossip message");
+        }
+    }
+
+    /// Report the outcome of re-verifying a received message. Only accepted
+    /// messages are forwarded; rejected ones count against their sender.
+    pub fn report(&self, inbound: &Inbound, acceptance: MessageAcceptance) {
+        let command = Command::Report(inbound.id.clone(), inbound.source, acceptance);
+        if self.commands.try_send(command).is_err() {
+            debug!("P2P command queue full, dropping validation result");
+        }
+    }
+
+    /// Send a JSON-RPC body to a connected peer and return its JSON-RPC
+    /// response, trying other peers if one fails or declines. The response is
+    /// as unverified as one from any RPC provider.
+    pub async fn request(&self, body: serde_json::Value) -> Result<serde_json::Value> {
+        let mut last_error = eyre!("No P2P peers connected");
+        for attempt in 0..MAX_PEER_ATTEMPTS {
+            let (reply, response) = oneshot::channel();
+            self.commands
+                .send(Command::Request(body.clone(), attempt, reply))
+                .await
+                .map_err(|_| eyre!("P2P node stopped"))?;
+            match response.await.map_err(|_| eyre!("P2P node stopped"))? {
+                Ok(PeerResponse::Result(result)) => return Ok(result),
+                Ok(PeerResponse::Error(e)) => last_error = eyre!("Peer declined request: {}", e),
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Answer a peer's proof request.
+    pub fn respond(&self, query: PeerQuery, response: PeerResponse) {
+        if self
+            .commands
+            .try_send(Command::Respond(query.channel, response))
+            .is_err()
+        {
+            debug!("P2P command queue full, dropping peer response");
+        }
+    }
+}
+
+/// Start the P2P node, returning its handle and what it receives from peers.
+pub fn spawn(config: &P2pConfig, chain_id: u64) -> Result<(P2pHandle, P2pEvents)> {
+    let keypair = identity::Keypair::generate_ed25519();
+    let peer_id = keypair.public().to_peer_id();
+    let mdns_enabled = config.mdns;
+
+    let mut swarm = libp2p::SwarmBuilder::with_existing_identity(keypair)
+        .with_tokio()
+        .with_tcp(
+            tcp::Config::default(),
+            noise::Config::new,
+            yamux::Config::default,
+        )?
+        .with_behaviour(|key| {
+            let gossipsub_config = gossipsub::ConfigBuilder::default()
+                .validation_mode(gossipsub::ValidationMode::Strict)
+                .validate_messages()
+                .max_transmit_size(MAX_MESSAGE_SIZE)
+                .build()?;
+            let gossipsub = gossipsub::Behaviour::new(
+                gossipsub::MessageAuthenticity::Signed(key.clone()),
+                gossipsub_config,
+            )?;
+            let mdns = match mdns_enabled {
+                true => Some(mdns::tokio::Behaviour::new(
+                    mdns::Config::default(),
+                    key.public().to_peer_id(),
+                )?),
+                false => None,
+            };
+            let proofs = request_response::json::Behaviour::new(
+                [(PROOF_PROTOCOL, ProtocolSupport::Full)],
+                request_response::Config::default().with_request_timeout(PEER_REQUEST_TIMEOUT),
+            );
+            Ok(Behaviour {
+                gossipsub,
+                mdns: mdns.into(),
+                proofs,
+            })
+        })
+        .map_err(|e| eyre!("Failed to create P2P behaviour: {}", e))?
+        .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
+        .build();
+
+    let topics = Topics::new(chain_id);
+    swarm.behaviour_mut().gossipsub.subscribe(&topics.headers)?;
+    swarm.behaviour_mut().gossipsub.subscribe(&topics.proofs)?;
+
+    for addr in &config.listen {
+        let addr: Multiaddr = addr
+            .parse()
+            .wrap_err_with(|| format!("Invalid P2P listen address {}", addr))?;
+        swarm.listen_on(addr)?;
+    }
+    for addr in &config.bootstrap {
+        let addr: Multiaddr = addr
+            .parse()
+            .wrap_err_with(|| format!("Invalid P2P bootstrap address {}", addr))?;
+        swarm.dial(addr)?;
+    }
+
+    let (commands, pending) = mpsc::channel(COMMAND_QUEUE);
+    let (gossip, gossip_events) = mpsc::channel(COMMAND_QUEUE);
+    let (queries, query_events) = mpsc::channel(COMMAND_QUEUE);
+    let listen_addrs = Arc::new(Mutex::new(Vec::new()));
+
+    let node = Node {
+        swarm,
+        topics,
+        gossip,
+        queries,
+        listen_addrs: listen_addrs.clone(),
+        peers: Vec::new(),
+        requests: HashMap::new(),
+    };
+    info!(%peer_id, mdns = mdns_enabled, "P2P node started");
+    tokio::spawn(node.run(pending));
+
+    Ok((
+        P2pHandle {
+            peer_id,
+            commands,
+            listen_addrs,
+        },
+        P2pEvents {
+            gossip: gossip_events,
+            queries: query_events,
+        },
+    ))
+}
+
+/// The swarm and the bookkeeping of its event loop.
+struct Node {
+    swarm: Swarm<Behaviour>,
+    topics: Topics,
+    gossip: mpsc::Sender<Inbound>,
+    queries: mpsc::Sender<PeerQuery>,
+    listen_addrs: Arc<Mutex<Vec<Multiaddr>>>,
+    /// Connected peers, in the order proof requests are spread over them.
+    peers: Vec<PeerId>,
+    requests: HashMap<OutboundRequestId, oneshot::Sender<Result<PeerResponse>>>,
+}
+
+impl Node {
+    async fn run(mut self, mut commands: mpsc::Receiver<Command>) {
+        loop {
+            tokio::select! {
+                command = commands.recv() => match command {
+                    Some(command) => self.handle_command(command),
+                    // Every handle is gone
+                    None => return,
+                },
+                event = self.swarm.select_next_some() => self.handle_event(event),
+            }
+        }
+    }
+
+    fn handle_command(&mut self, command: Command) {
+        match command {
+            Command::Publish(message) => {
+                let topic = message.topic(&self.topics);
+                let data = serde_json::to_vec(&message).unwrap();
+                if let Err(e) = self.swarm.behaviour_mut().gossipsub.publish(topic, data) {
+                    debug!("Gossip not published: {}", e);
+                }
+            }
+            Command::Report(id, source, acceptance) => {
+                // Messages whose validation took too long have already expired
+                let _ = self
+                    .swarm
+                    .behaviour_mut()
+                    .gossipsub
+                    .report_message_validation_result(&id, &source, acceptance);
+            }
+            Command::Request(body, attempt, reply) => {
+                if self.peers.is_empty() {
+                    let _ = reply.send(Err(eyre!("No P2P peers connected")));
+                    return;
+                }
+                // Successive attempts go to successive peers
+                let peer = self.peers[attempt % self.peers.len()];
+                let id = self.swarm.behaviour_mut().proofs.send_request(&peer, body);
+                self.requests.insert(id, reply);
+            }
+            Command::Respond(channel, response) => {
+                // Fails only if the requesting peer went away
+                let _ = self
+                    .swarm
+                    .behaviour_mut()
+                    .proofs
+                    .send_response(channel, response);
+            }
+        }
+    }
+
+    fn handle_event(&mut self, event: SwarmEvent<BehaviourEvent>) {
+        match event {
+            SwarmEvent::NewListenAddr { address, .. } => {
+                info!(%address, "P2P listening");
+                self.listen_addrs.lock().unwrap().push(address);
+            }
+            SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+                debug!(%peer_id, "P2P peer connected");
+                if !self.peers.contains(&peer_id) {
+                    self.peers.push(peer_id);
+                }
+            }
+            SwarmEvent::ConnectionClosed {
+                peer_id,
+                num_established: 0,
+                ..
+            } => {
+                debug!(%peer_id, "P2P peer disconnected");
+                self.peers.retain(|p| *p != peer_id);
+            }
+            SwarmEvent::Behaviour(BehaviourEvent::Mdns(mdns::Event::Discovered(peers))) => {
+                for (peer_id, address) in peers {
+                    if !self.swarm.is_connected(&peer_id) {
+                        debug!(%peer_id, %address, "Discovered P2P peer");
+                        let _ = self.swarm.dial(address);
+                    }
+                }
+            }
+            SwarmEvent::Behaviour(BehaviourEvent::Gossipsub(gossipsub::Event::Message {
+                propagation_source,
+                message_id,
+                message,
+            })) => match serde_json::from_slice(&message.data) {
+                Ok(message) => {
+                    let received = Inbound {
+                        id: message_id,
+                        source: propagation_source,
+                        message,
+                    };
+                    if self.gossip.try_send(received).is_err() {
+                        debug!("P2P inbound queue full, dropping message");
+                    }
+                }
+                Err(e) => {
+                    warn!(peer = %propagation_source, "Malformed gossip message: {}", e);
+                    let _ = self
+                        .swarm
+                        .behaviour_mut()
+                        .gossipsub
+                        .report_message_validation_result(
+                            &message_id,
+                            &propagation_source,
+                            MessageAcceptance::Reject,
+                        );
+                }
+            },
+            SwarmEvent::Behaviour(BehaviourEvent::Proofs(event)) => self.handle_proofs(event),
+            _ => {}
+        }
+    }
+
+    fn handle_proofs(&mut self, event: request_response::Event<serde_json::Value, PeerResponse>) {
+        match event {
+            request_response::Event::Message {
+                peer,
+                message:
+                    request_response::Message::Request {
+                        request, channel, ..
+                    },
+            } => {
+                let query = PeerQuery {
+                    peer,
+                    body: request,
+                    channel,
+                };
+                if let Err(e) = self.queries.try_send(query) {
+                    debug!(%peer, "P2P query queue full, declining request");
+                    let query = e.into_inner();
+                    let _ = self
+                        .swarm
+                        .behaviour_mut()
+                        .proofs
+                        .send_response(query.channel, PeerResponse::Error("busy".to_string()));
+                }
+            }
+            request_response::Event::Message {
+                message:
+                    request_response::Message::Response {
+                        request_id,
+                        response,
+                    },
+                ..
+            } => {
+                if let Some(reply) = self.requests.remove(&request_id) {
+                    let _ = reply.send(Ok(response));
+                }
+            }
+            request_response::Event::OutboundFailure {
+                peer,
+                request_id,
+                error,
+            } => {
+                debug!(%peer, "Proof request failed: {}", error);
+                if let Some(reply) = self.requests.remove(&request_id) {
+                    let _ = reply.send(Err(eyre!("Request to peer {} failed: {}", peer, error)));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local_config(bootstrap: Vec<Multiaddr>) -> P2pConfig {
+        P2pConfig {
+            enabled: true,
+            listen: vec!["/ip4/127.0.0.1/tcp/0".to_string()],
+            bootstrap: bootstrap.iter().map(|a| a.to_string()).collect(),
+            mdns: false,
+            ..P2pConfig::default()
+        }
+    }
+
+    async fn bound_addr(handle: &P2pHandle) -> Multiaddr {
+        loop {
+            if let Some(addr) = handle.listen_addrs().first() {
+                return addr
+                    .clone()
+                    .with(libp2p::multiaddr::Protocol::P2p(handle.peer_id()));
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+
+    /// Three nodes in a line: a message published by the first only reaches
+    /// the third once the second has validated and accepted it.
+    #[tokio::test]
+    async fn test_cluster_forwards_only_accepted_messages() {
+        let (a, _a_events) = spawn(&local_config(vec![]), 1).unwrap();
+        let a_addr = bound_addr(&a).await;
+        let (b, b_events) = spawn(&local_config(vec![a_addr]), 1).unwrap();
+        let mut b_in = b_events.gossip;
+        let b_addr = bound_addr(&b).await;
+        let (_c, c_events) = spawn(&local_config(vec![b_addr]), 1).unwrap();
+        let mut c_in = c_events.gossip;
+
+        let header = |number| GossipMessage::Header {
+            number,
+            hash: B256::repeat_byte(1),
+            state_root: B256::repeat_byte(2),
+        };
+
+        let cluster = async {
+            // Republish until the mesh has formed and B sees a message
+            let first = loop {
+                a.publish(header(1));
+                if let Ok(Some(inbound)) =
+                    tokio::time::timeout(Duration::from_millis(500), b_in.recv()).await
+                {
+                    break inbound;
+                }
+            };
+
+            // B rejects: nothing reaches C
+            b.report(&first, MessageAcceptance::Reject);
+            assert!(tokio::time::timeout(Duration::from_secs(2), c_in.recv())
+                .await
+                .is_err());
+
+            // B accepts a later message: C gets it
+            loop {
+                a.publish(header(2));
+                let Ok(Some(inbound)) =
+                    tokio::time::timeout(Duration::from_millis(500), b_in.recv()).await
+                else {
+                    continue;
+                };
+                b.report(&inbound, MessageAcceptance::Accept);
+                if let Ok(Some(forwarded)) =
+                    tokio::time::timeout(Duration::from_millis(1500), c_in.recv()).await
+                {
+                    assert!(matches!(
+                        forwarded.message,
+                        GossipMessage::Header { number: 2, .. }
+                    ));
+                    assert_eq!(forwarded.source, b.peer_id());
+                    break;
+                }
+            }
+        };
+
+        tokio::time::timeout(Duration::from_secs(30), cluster)
+            .await
+            .expect("cluster did not propagate the message");
+    }
+
+    #[tokio::test]
+    async fn test_proof_request_answered_by_peer() {
+        let (server, mut events) = spawn(&local_config(vec![]), 1).unwrap();
+        let server_addr = bound_addr(&server).await;
+        let (client, _client_events) = spawn(&local_config(vec![server_addr]), 1).unwrap();
+
+        let responder = server.clone();
+        tokio::spawn(async move {
+            while let Some(query) = events.queries.recv().await {
+                let id = query.body["id"].clone();
+                responder.respond(
+                    query,
+                    PeerResponse::Result(
+                        serde_json::json!({"jsonrpc": "2.0", "id": id, "result": "0x1"}),
+                    ),
+                );
+            }
+        });
+
+        let body = serde_json::json!({"jsonrpc": "2.0", "id": 7, "method": "eth_blockNumber", "params": []});
+        let response = tokio::time::timeout(Duration::from_secs(30), async {
+            loop {
+                // Fails until the connection is up
+                match client.request(body.clone()).await {
+                    Ok(response) => break response,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+                }
+            }
+        })
+        .await
+        .expect("peer did not answer");
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["result"], "0x1");
+    }
+}
@@ -0,0 +1,263 @@
+//! Cache of verified proofs for recent blocks.
+
+use alloy_primitives::{Address, B256};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::types::ProofData;
+
+type ProofKey = (Address, Vec<B256>);
+
+/// Verified proofs keyed by block number, keeping the newest `blocks` blocks,
+/// at most `per_block` proofs in one block and at most `entries` proofs in
+/// total. When full, the least recently used proof is evicted.
+pub struct ProofCache {
+    blocks: usize,
+    entries: usize,
+    per_block: usize,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    blocks: BTreeMap<u64, HashMap<ProofKey, Entry>>,
+    /// Cached proofs by last use, oldest first
+    lru: BTreeMap<u64, (u64, ProofKey)>,
+    /// Proofs local requests looked up, by last lookup, so proofs from peers
+    /// are only cached when they are wanted
+    requested: HashMap<ProofKey, u64>,
+    requested_lru: BTreeMap<u64, ProofKey>,
+    tick: u64,
+}
+
+struct Entry {
+    proof: ProofData,
+    used: u64,
+}
+
+impl Inner {
+    fn touch(&mut self, block: u64, key: &ProofKey) -> Option<&ProofData> {
+        self.tick += 1;
+        let entry = self.blocks.get_mut(&block)?.get_mut(key)?;
+        self.lru.remove(&entry.used);
+        entry.used = self.tick;
+        self.lru.insert(self.tick, (block, key.clone()));
+        Some(&entry.proof)
+    }
+
+    fn remove(&mut self, block: u64, key: &ProofKey) {
+        if let Some(proofs) = self.blocks.get_mut(&block) {
+            if let Some(entry) = proofs.remove(key) {
+                self.lru.remove(&entry.used);
+            }
+            if proofs.is_empty() {
+                self.blocks.remove(&block);
+            }
+        }
+    }
+
+    fn evict_lru(&mut self) {
+        if let Some((_, (block, key))) = self.lru.first_key_value() {
+            let (block, key) = (*block, key.clone());
+            self.remove(block, &key);
+        }
+    }
+
+    /// Evict the least recently used proof of `block`.
+    fn evict_lru_in(&mut self, block: u64) {
+        let oldest = self.blocks.get(&block).and_then(|proofs| {
+            proofs
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(key, _)| key.clone())
+        });
+        if let Some(key) = oldest {
+            self.remove(block, &key);
+        }
+    }
+
+    fn evict_oldest_block(&mut self) {
+        if let Some((_, proofs)) = self.blocks.pop_first() {
+            for entry in proofs.values() {
+                self.lru.remove(&entry.used);
+            }
+        }
+    }
+
+    fn mark_requested(&mut self, key: ProofKey, capacity: usize) {
+        self.tick += 1;
+        if let Some(previous) = self.requested.insert(key.clone(), self.tick) {
+            self.requested_lru.remove(&previous);
+        }
+        self.requested_lru.insert(self.tick, key);
+        while self.requested_lru.len() > capacity {
+            if let Some((_, key)) = self.requested_lru.pop_first() {
+                self.requested.remove(&key);
+            }
+        }
+    }
+}
+
+impl ProofCache {
+    pub fn new(blocks: usize, entries: usize, per_block: usize) -> Self {
+        Self {
+            blocks,
+            entries,
+            per_block,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    pub fn get(&self, block: u64, address: Address, storage_keys: &[B256]) -> Option<ProofData> {
+        let key = (address, storage_keys.to_vec());
+        let mut inner = self.inner.lock().unwrap();
+        let proof = inner.touch(block, &key).cloned();
+        inner.mark_requested(key, self.entries);
+        proof
+    }
+
+    /// Insert a proof that has been verified against the state root of `block`.
+    /// Returns false if the proof was already cached.
+    pub fn insert(&self, block: u64, proof: ProofData) -> bool {
+        let key = proof_key(&proof);
+        let mut inner = self.inner.lock().unwrap();
+        self.insert_locked(&mut inner, block, key, proof)
+    }
+
+    /// Insert a verified proof received from a peer, but only if a local
+    /// request has looked up the same account and storage keys. Returns
+    /// whether it was cached.
+    pub fn insert_requested(&self, block: u64, proof: ProofData) -> bool {
+        let key = proof_key(&proof);
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.requested.contains_key(&key) {
+            return false;
+        }
+        self.insert_locked(&mut inner, block, key, proof)
+    }
+
+    fn insert_locked(
+        &self,
+        inner: &mut Inner,
+        block: u64,
+        key: ProofKey,
+        proof: ProofData,
+    ) -> bool {
+        if self.blocks == 0 || self.entries == 0 || self.per_block == 0 {
+            return false;
+        }
+        if inner.touch(block, &key).is_some() {
+            return false;
+        }
+
+        while inner.blocks.get(&block).map_or(0, HashMap::len) >= self.per_block {
+            inner.evict_lru_in(block);
+        }
+        let used = inner.tick;
+        inner.lru.insert(used, (block, key.clone()));
+        inner
+            .blocks
+            .entry(block)
+            .or_default()
+            .insert(key, Entry { proof, used });
+
+        while inner.blocks.len() > self.blocks {
+            inner.evict_oldest_block();
+        }
+        while inner.lru.len() > self.entries {
+            inner.evict_lru();
+        }
+        true
+    }
+}
+
+fn proof_key(proof: &ProofData) -> ProofKey {
+    let storage_keys = proof.storage_proof.iter().map(|sp| sp.key).collect();
+    (proof.address, storage_keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{U256, U64};
+
+    fn proof(address: Address) -> ProofData {
+        ProofData {
+            address,
+            balance: U256::from(1),
+            code_hash: B256::ZERO,
+            nonce: U64::ZERO,
+            storage_hash: B256::ZERO,
+            account_proof: vec![],
+            storage_proof: vec![],
+        }
+    }
+
+    #[test]
+    fn test_proof_cache_evicts_oldest_block() {
+        let cache = ProofCache::new(2, 16, 16);
+        let address = Address::repeat_byte(1);
+
+        cache.insert(10, proof(address));
+        cache.insert(11, proof(address));
+        cache.insert(12, proof(address));
+
+        assert!(cache.get(10, address, &[]).is_none());
+        assert!(cache.get(11, address, &[]).is_some());
+        assert!(cache.get(12, address, &[]).is_some());
+        assert!(cache.get(12, address, &[B256::ZERO]).is_none());
+    }
+
+    #[test]
+    fn test_proof_cache_evicts_least_recently_used() {
+        let cache = ProofCache::new(4, 2, 2);
+        let (a, b, c) = (
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+        );
+
+        cache.insert(10, proof(a));
+        cache.insert(10, proof(b));
+        assert!(cache.get(10, a, &[]).is_some());
+        cache.insert(11, proof(c));
+
+        assert!(cache.get(10, a, &[]).is_some());
+        assert!(cache.get(10, b, &[]).is_none());
+        assert!(cache.get(11, c, &[]).is_some());
+        assert!(!cache.insert(11, proof(c)));
+    }
+
+    #[test]
+    fn test_proof_cache_caps_entries_per_block() {
+        let cache = ProofCache::new(4, 16, 2);
+        let (a, b, c) = (
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+        );
+
+        cache.insert(10, proof(a));
+        cache.insert(10, proof(b));
+        cache.insert(11, proof(c));
+        cache.insert(10, proof(c));
+
+        assert!(cache.get(10, a, &[]).is_none());
+        assert!(cache.get(10, b, &[]).is_some());
+        assert!(cache.get(10, c, &[]).is_some());
+        assert!(cache.get(11, c, &[]).is_some());
+    }
+
+    #[test]
+    fn test_peer_proofs_only_cached_when_requested() {
+        let cache = ProofCache::new(4, 16, 16);
+        let (a, b) = (Address::repeat_byte(1), Address::repeat_byte(2));
+
+        assert!(cache.get(10, a, &[]).is_none());
+        assert!(cache.insert_requested(10, proof(a)));
+        assert!(!cache.insert_requested(10, proof(b)));
+        assert!(cache.get(10, b, &[]).is_none());
+        // Asked for now, so a later proof from a peer is kept
+        assert!(cache.insert_requested(11, proof(b)));
+    }
+}
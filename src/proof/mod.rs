@@ -3,7 +3,7 @@
 //! Implements Merkle Patricia Trie (MPT) verification for account and storage proofs.
 
 use alloy_primitives::{Bytes, B256, U256};
-use alloy_rlp::{Decodable, RlpDecodable};
+use alloy_rlp::{Decodable, RlpDecodable, RlpEncodable};
 use eyre::{bail, Result};
 use sha3::{Digest, Keccak256};
 use tracing::{debug, instrument, warn};
@@ -11,8 +11,51 @@ use tracing::{debug, instrument, warn};
 use crate::config::Config;
 use crate::types::{ProofData, StorageProofData};
 
+mod cache;
+mod multiproof;
+pub mod trie;
+
+pub use cache::ProofCache;
+pub use multiproof::MultiProof;
+
+/// Entry points for the fuzz targets in `fuzz/`.
+///
+/// These parse attacker-controlled upstream bytes and must never panic.
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing {
+    use super::*;
+
+    pub fn decode_rlp_list(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+        super::decode_rlp_list(data)
+    }
+
+    pub fn decode_rlp_length(data: &[u8]) -> Result<(&[u8], usize)> {
+        super::decode_rlp_length(data)
+    }
+
+    pub fn decode_rlp_item(data: &[u8]) -> Result<(Vec<u8>, usize)> {
+        super::decode_rlp_item(data)
+    }
+
+    pub fn decode_hp_path(encoded: &[u8]) -> Result<(Vec<u8>, bool)> {
+        super::decode_hp_path(encoded)
+    }
+
+    pub fn decode_storage_value(data: &[u8]) -> Result<U256> {
+        super::decode_storage_value(data)
+    }
+
+    pub fn verify_mpt_proof(root: B256, key: &B256, proof: &[Bytes]) -> Result<Option<Vec<u8>>> {
+        super::verify_mpt_proof(root, key, proof)
+    }
+
+    pub fn keccak256(data: &[u8]) -> B256 {
+        super::keccak256(data)
+    }
+}
+
 /// RLP-decoded account state.
-#[derive(Debug, RlpDecodable)]
+#[derive(Debug, RlpDecodable, RlpEncodable)]
 struct AccountState {
     nonce: u64,
     balance: U256,
@@ -49,73 +92,9 @@ impl ProofGenerator {
         let key = keccak256(proof.address.as_slice());
 
         // Verify the Merkle proof
-        let value = match verify_mpt_proof(state_root, &key, &proof.account_proof)? {
-            Some(v) => v,
-            None => {
-                // Account doesn't exist - verify it's truly empty
-                let nonce_u64: u64 = proof.nonce.to();
-                if proof.balance == U256::ZERO
-                    && nonce_u64 == 0
-                    && proof.code_hash == EMPTY_CODE_HASH
-                    && proof.storage_hash == EMPTY_ROOT_HASH
-                {
-                    debug!("Account verified as non-existent");
-                    return Ok(true);
-                }
-                warn!("Proof shows non-existent account but data is non-empty");
-                return Ok(false);
-            }
-        };
+        let value = verify_mpt_proof(state_root, &key, &proof.account_proof)?;
 
-        // Decode the RLP-encoded account state
-        let account = match AccountState::decode(&mut value.as_ref()) {
-            Ok(a) => a,
-            Err(e) => {
-                warn!("Failed to decode account RLP: {}", e);
-                return Ok(false);
-            }
-        };
-
-        // Verify account fields match
-        let proof_nonce: u64 = proof.nonce.to();
-        if account.nonce != proof_nonce {
-            warn!(
-                expected = proof_nonce,
-                got = account.nonce,
-                "Nonce mismatch"
-            );
-            return Ok(false);
-        }
-
-        if account.balance != proof.balance {
-            warn!(
-                expected = %proof.balance,
-                got = %account.balance,
-                "Balance mismatch"
-            );
-            return Ok(false);
-        }
-
-        if account.storage_root != proof.storage_hash {
-            warn!(
-                expected = %proof.storage_hash,
-                got = %account.storage_root,
-                "Storage hash mismatch"
-            );
-            return Ok(false);
-        }
-
-        if account.code_hash != proof.code_hash {
-            warn!(
-                expected = %proof.code_hash,
-                got = %account.code_hash,
-                "Code hash mismatch"
-            );
-            return Ok(false);
-        }
-
-        debug!("Account proof verified successfully");
-        Ok(true)
+        Ok(check_account(value, proof))
     }
 
     /// Verify a storage proof against a storage root.
@@ -140,33 +119,9 @@ impl ProofGenerator {
         let key = keccak256(proof.key.as_slice());
 
         // Verify the Merkle proof
-        let value = match verify_mpt_proof(storage_root, &key, &proof.proof)? {
-            Some(v) => v,
-            None => {
-                // Slot doesn't exist - verify value is zero
-                if proof.value == U256::ZERO {
-                    debug!("Storage slot verified as non-existent (zero value)");
-                    return Ok(true);
-                }
-                warn!("Proof shows non-existent slot but value is non-zero");
-                return Ok(false);
-            }
-        };
-
-        // Decode RLP-encoded storage value
-        let decoded_value = decode_storage_value(&value)?;
-
-        if decoded_value != proof.value {
-            warn!(
-                expected = %proof.value,
-                got = %decoded_value,
-                "Storage value mismatch"
-            );
-            return Ok(false);
-        }
+        let value = verify_mpt_proof(storage_root, &key, &proof.proof)?;
 
-        debug!("Storage proof verified successfully");
-        Ok(true)
+        check_storage(value, proof)
     }
 
     /// Verify a complete account proof including all storage proofs.
@@ -188,6 +143,145 @@ impl ProofGenerator {
         debug!("Complete proof verified successfully");
         Ok(true)
     }
+
+    /// Verify many account proofs, including their storage proofs, in one pass.
+    ///
+    /// All keys are walked through the same deduplicated node set, so trie
+    /// nodes shared between accounts are decoded once. Returns one result per
+    /// account, in order.
+    #[instrument(skip(self, multiproof, proofs), fields(accounts = proofs.len(), nodes = multiproof.len()))]
+    pub fn verify_multi_account_proof(
+        &self,
+        state_root: B256,
+        multiproof: &MultiProof,
+        proofs: &[ProofData],
+    ) -> Vec<Result<bool>> {
+        let mut verifier = multiproof.verifier();
+
+        proofs
+            .iter()
+            .map(|proof| {
+                let key = keccak256(proof.address.as_slice());
+                let value = verifier.get(state_root, &key)?;
+                if !check_account(value, proof) {
+                    return Ok(false);
+                }
+
+                for storage_proof in &proof.storage_proof {
+                    let key = keccak256(storage_proof.key.as_slice());
+                    let value = verifier.get(proof.storage_hash, &key)?;
+                    if !check_storage(value, storage_proof)? {
+                        warn!(key = %storage_proof.key, "Storage proof verification failed");
+                        return Ok(false);
+                    }
+                }
+
+                Ok(true)
+            })
+            .collect()
+    }
+}
+
+/// Check claimed account fields against the value proven at the account's key.
+fn check_account(value: Option<Vec<u8>>, proof: &ProofData) -> bool {
+    let value = match value {
+        Some(v) => v,
+        None => {
+            // Account doesn't exist - verify it's truly empty
+            let nonce_u64: u64 = proof.nonce.to();
+            if proof.balance == U256::ZERO
+                && nonce_u64 == 0
+                && proof.code_hash == EMPTY_CODE_HASH
+                && proof.storage_hash == EMPTY_ROOT_HASH
+            {
+                debug!("Account verified as non-existent");
+                return true;
+            }
+            warn!("Proof shows non-existent account but data is non-empty");
+            return false;
+        }
+    };
+
+    // Decode the RLP-encoded account state
+    let account = match AccountState::decode(&mut value.as_ref()) {
+        Ok(a) => a,
+        Err(e) => {
+            warn!("Failed to decode account RLP: {}", e);
+            return false;
+        }
+    };
+
+    // Verify account fields match
+    let proof_nonce: u64 = proof.nonce.to();
+    if account.nonce != proof_nonce {
+        warn!(
+            expected = proof_nonce,
+            got = account.nonce,
+            "Nonce mismatch"
+        );
+        return false;
+    }
+
+    if account.balance != proof.balance {
+        warn!(
+            expected = %proof.balance,
+            got = %account.balance,
+            "Balance mismatch"
+        );
+        return false;
+    }
+
+    if account.storage_root != proof.storage_hash {
+        warn!(
+            expected = %proof.storage_hash,
+            got = %account.storage_root,
+            "Storage hash mismatch"
+        );
+        return false;
+    }
+
+    if account.code_hash != proof.code_hash {
+        warn!(
+            expected = %proof.code_hash,
+            got = %account.code_hash,
+            "Code hash mismatch"
+        );
+        return false;
+    }
+
+    debug!("Account proof verified successfully");
+    true
+}
+
+/// Check a claimed storage value against the value proven at the slot's key.
+fn check_storage(value: Option<Vec<u8>>, proof: &StorageProofData) -> Result<bool> {
+    let value = match value {
+        Some(v) => v,
+        None => {
+            // Slot doesn't exist - verify value is zero
+            if proof.value == U256::ZERO {
+                debug!("Storage slot verified as non-existent (zero value)");
+                return Ok(true);
+            }
+            warn!("Proof shows non-existent slot but value is non-zero");
+            return Ok(false);
+        }
+    };
+
+    // Decode RLP-encoded storage value
+    let decoded_value = decode_storage_value(&value)?;
+
+    if decoded_value != proof.value {
+        warn!(
+            expected = %proof.value,
+            got = %decoded_value,
+            "Storage value mismatch"
+        );
+        return Ok(false);
+    }
+
+    debug!("Storage proof verified successfully");
+    Ok(true)
 }
 
 /// Empty account code hash (keccak256 of empty bytes)
@@ -210,6 +304,8 @@ fn keccak256(data: &[u8]) -> B256 {
 /// Verify a Merkle Patricia Trie proof.
 ///
 /// Returns the value at the key if it exists, or None for non-existence proofs.
+/// Nodes are looked up by hash starting from `root`, so every node on the path,
+/// including the root itself, must hash to the reference its parent holds.
 fn verify_mpt_proof(root: B256, key: &B256, proof: &[Bytes]) -> Result<Option<Vec<u8>>> {
     if proof.is_empty() {
         if root == EMPTY_ROOT_HASH {
@@ -218,141 +314,20 @@ fn verify_mpt_proof(root: B256, key: &B256, proof: &[Bytes]) -> Result<Option<Ve
         bail!("Empty proof for non-empty root");
     }
 
-    // Convert key to nibbles (4-bit values)
-    let key_nibbles = bytes_to_nibbles(key.as_slice());
-    let mut key_index = 0;
-
-    let mut expected_hash = root;
-
-    for (i, node) in proof.iter().enumerate() {
-        // Verify this node's hash matches expected
-        let node_hash = keccak256(node);
-        if node_hash != expected_hash && node.len() >= 32 {
-            bail!(
-                "Node hash mismatch at index {}: expected {}, got {}",
-                i,
-                expected_hash,
-                node_hash
-            );
-        }
-
-        // For nodes < 32 bytes, they can be embedded directly (no hash)
-        // This is only valid for the root node or when embedded in a branch
-        if node.len() < 32 && i > 0 && node_hash != expected_hash {
-            bail!("Invalid embedded node at index {}", i);
-        }
-
-        // Decode the RLP list
-        let items = decode_rlp_list(node)?;
-
-        match items.len() {
-            17 => {
-                // Branch node: 16 children + value
-                if key_index >= key_nibbles.len() {
-                    // We've consumed all key nibbles, return the value at index 16
-                    if items[16].is_empty() {
-                        return Ok(None);
-                    }
-                    return Ok(Some(items[16].clone()));
-                }
-
-                let nibble = key_nibbles[key_index] as usize;
-                key_index += 1;
-
-                if items[nibble].is_empty() {
-                    // Path doesn't exist
-                    return Ok(None);
-                }
-
-                // The child reference is either a 32-byte hash or an embedded node
-                if items[nibble].len() == 32 {
-                    expected_hash = B256::from_slice(&items[nibble]);
-                } else if items[nibble].len() < 32 {
-                    // Embedded node - should be the next item in proof
-                    if i + 1 < proof.len() {
-                        expected_hash = keccak256(&proof[i + 1]);
-                    } else {
-                        // This is an embedded leaf/extension
-                        let embedded_items = decode_rlp_list(&items[nibble])?;
-                        return process_leaf_extension(&embedded_items, &key_nibbles, key_index);
-                    }
-                }
-            }
-            2 => {
-                // Leaf or extension node
-                let (prefix_nibbles, is_leaf) = decode_hp_path(&items[0])?;
-
-                // Check that the remaining key matches the path
-                let remaining_key = &key_nibbles[key_index..];
-
-                if is_leaf {
-                    // Leaf node: path should match remaining key exactly
-                    if prefix_nibbles != remaining_key {
-                        return Ok(None); // Key not in trie
-                    }
-                    // Return the value
-                    return Ok(Some(items[1].clone()));
-                } else {
-                    // Extension node: path is a prefix of remaining key
-                    if remaining_key.len() < prefix_nibbles.len() {
-                        return Ok(None);
-                    }
-                    if &remaining_key[..prefix_nibbles.len()] != prefix_nibbles.as_slice() {
-                        return Ok(None);
-                    }
-
-                    key_index += prefix_nibbles.len();
-
-                    // Follow to next node
-                    if items[1].len() == 32 {
-                        expected_hash = B256::from_slice(&items[1]);
-                    } else {
-                        // Embedded node
-                        let embedded_items = decode_rlp_list(&items[1])?;
-                        return process_leaf_extension(&embedded_items, &key_nibbles, key_index);
-                    }
-                }
-            }
-            _ => bail!("Invalid node with {} items", items.len()),
-        }
-    }
-
-    // If we get here without returning, the proof is incomplete
-    bail!("Incomplete proof - didn't reach a leaf");
-}
-
-/// Process a leaf or extension node that might be embedded.
-fn process_leaf_extension(
-    items: &[Vec<u8>],
-    key_nibbles: &[u8],
-    key_index: usize,
-) -> Result<Option<Vec<u8>>> {
-    if items.len() != 2 {
-        bail!("Invalid embedded node");
-    }
-
-    let (prefix_nibbles, is_leaf) = decode_hp_path(&items[0])?;
-    let remaining_key = &key_nibbles[key_index..];
-
-    if is_leaf {
-        if prefix_nibbles == remaining_key {
-            return Ok(Some(items[1].clone()));
-        }
-        return Ok(None);
-    }
-
-    // Extension node pointing somewhere - shouldn't happen in a valid proof
-    bail!("Incomplete proof - extension node at end");
+    MultiProof::new(proof).verifier().get(root, key)
 }
 
 /// Decode hex-prefix encoded path.
 /// Returns (nibbles, is_leaf).
 fn decode_hp_path(encoded: &[u8]) -> Result<(Vec<u8>, bool)> {
     if encoded.is_empty() {
-        return Ok((vec![], false));
+        bail!("Empty hex-prefix path");
     }
 
     let first_nibble = encoded[0] >> 4;
+    if first_nibble > 3 {
+        bail!("Invalid hex-prefix flag {}", first_nibble);
+    }
     let is_leaf = first_nibble >= 2;
     let is_odd = first_nibble % 2 == 1;
 
@@ -361,6 +336,8 @@ fn decode_hp_path(encoded: &[u8]) -> Result<(Vec<u8>, bool)> {
     if is_odd {
         // Odd length: first nibble is part of path
         nibbles.push(encoded[0] & 0x0f);
+    } else if encoded[0] & 0x0f != 0 {
+        bail!("Invalid hex-prefix padding");
     }
 
     // Rest of the bytes
@@ -383,12 +360,21 @@ fn bytes_to_nibbles(bytes: &[u8]) -> Vec<u8> {
 }
 
 /// Decode an RLP list into its items.
+///
+/// The whole input must be exactly one list.
 fn decode_rlp_list(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     if data.is_empty() {
         return Ok(vec![]);
     }
 
-    let (list_data, _) = decode_rlp_length(data)?;
+    if data[0] < 0xc0 {
+        bail!("RLP data is not a list");
+    }
+
+    let (list_data, total_len) = decode_rlp_length(data)?;
+    if total_len != data.len() {
+        bail!("Trailing bytes after RLP list");
+    }
 
     let mut items = Vec::new();
     let mut offset = 0;
@@ -402,112 +388,68 @@ fn decode_rlp_list(data: &[u8]) -> Result<Vec<Vec<u8>>> {
     Ok(items)
 }
 
-/// Decode RLP length prefix, return (content, total_length).
-fn decode_rlp_length(data: &[u8]) -> Result<(&[u8], usize)> {
+/// Split an RLP prefix into (header_length, payload_length), checking that the
+/// payload fits in `data`.
+fn decode_rlp_header(data: &[u8]) -> Result<(usize, usize)> {
     if data.is_empty() {
         bail!("Empty RLP data");
     }
 
     let prefix = data[0];
 
-    if prefix <= 0x7f {
-        // Single byte
-        Ok((&data[0..1], 1))
+    let (header_len, payload_len) = if prefix <= 0x7f {
+        // Single byte, its own payload
+        (0, 1)
     } else if prefix <= 0xb7 {
         // Short string (0-55 bytes)
-        let len = (prefix - 0x80) as usize;
-        if data.len() < 1 + len {
-            bail!("RLP string truncated");
-        }
-        Ok((&data[1..1 + len], 1 + len))
+        (1, (prefix - 0x80) as usize)
     } else if prefix <= 0xbf {
         // Long string
         let len_bytes = (prefix - 0xb7) as usize;
         if data.len() < 1 + len_bytes {
             bail!("RLP length truncated");
         }
-        let len = bytes_to_usize(&data[1..1 + len_bytes])?;
-        if data.len() < 1 + len_bytes + len {
-            bail!("RLP string truncated");
-        }
-        Ok((
-            &data[1 + len_bytes..1 + len_bytes + len],
-            1 + len_bytes + len,
-        ))
+        (1 + len_bytes, bytes_to_usize(&data[1..1 + len_bytes])?)
     } else if prefix <= 0xf7 {
         // Short list (0-55 bytes)
-        let len = (prefix - 0xc0) as usize;
-        if data.len() < 1 + len {
-            bail!("RLP list truncated");
-        }
-        Ok((&data[1..1 + len], 1 + len))
+        (1, (prefix - 0xc0) as usize)
     } else {
         // Long list
         let len_bytes = (prefix - 0xf7) as usize;
         if data.len() < 1 + len_bytes {
             bail!("RLP length truncated");
         }
-        let len = bytes_to_usize(&data[1..1 + len_bytes])?;
-        if data.len() < 1 + len_bytes + len {
-            bail!("RLP list truncated");
-        }
-        Ok((
-            &data[1 + len_bytes..1 + len_bytes + len],
-            1 + len_bytes + len,
-        ))
+        (1 + len_bytes, bytes_to_usize(&data[1..1 + len_bytes])?)
+    };
+
+    let total_len = header_len
+        .checked_add(payload_len)
+        .ok_or_else(|| eyre::eyre!("RLP length overflow"))?;
+    if data.len() < total_len {
+        bail!("RLP data truncated");
     }
+
+    Ok((header_len, payload_len))
+}
+
+/// Decode RLP length prefix, return (content, total_length).
+fn decode_rlp_length(data: &[u8]) -> Result<(&[u8], usize)> {
+    let (header_len, payload_len) = decode_rlp_header(data)?;
+    let total_len = header_len + payload_len;
+    Ok((&data[header_len..total_len], total_len))
 }
 
 /// Decode a single RLP item, return (content, total_bytes_consumed).
+///
+/// Strings are returned as their content, lists as their whole encoding.
 fn decode_rlp_item(data: &[u8]) -> Result<(Vec<u8>, usize)> {
-    if data.is_empty() {
-        bail!("Empty RLP item");
-    }
-
-    let prefix = data[0];
+    let (header_len, payload_len) = decode_rlp_header(data)?;
+    let total_len = header_len + payload_len;
 
-    if prefix <= 0x7f {
-        // Single byte value
-        Ok((vec![prefix], 1))
-    } else if prefix <= 0xb7 {
-        // Short string
-        let len = (prefix - 0x80) as usize;
-        if data.len() < 1 + len {
-            bail!("RLP item truncated");
-        }
-        Ok((data[1..1 + len].to_vec(), 1 + len))
-    } else if prefix <= 0xbf {
-        // Long string
-        let len_bytes = (prefix - 0xb7) as usize;
-        if data.len() < 1 + len_bytes {
-            bail!("RLP length truncated");
-        }
-        let len = bytes_to_usize(&data[1..1 + len_bytes])?;
-        if data.len() < 1 + len_bytes + len {
-            bail!("RLP item truncated");
-        }
-        Ok((
-            data[1 + len_bytes..1 + len_bytes + len].to_vec(),
-            1 + len_bytes + len,
-        ))
-    } else if prefix <= 0xf7 {
-        // Short list - return the whole encoded list
-        let len = (prefix - 0xc0) as usize;
-        if data.len() < 1 + len {
-            bail!("RLP list truncated");
-        }
-        Ok((data[0..1 + len].to_vec(), 1 + len))
+    if data[0] >= 0xc0 {
+        Ok((data[..total_len].to_vec(), total_len))
     } else {
-        // Long list - return the whole encoded list
-        let len_bytes = (prefix - 0xf7) as usize;
-        if data.len() < 1 + len_bytes {
-            bail!("RLP length truncated");
-        }
-        let len = bytes_to_usize(&data[1..1 + len_bytes])?;
-        if data.len() < 1 + len_bytes + len {
-            bail!("RLP list truncated");
-        }
-        Ok((data[0..1 + len_bytes + len].to_vec(), 1 + len_bytes + len))
+        Ok((data[header_len..total_len].to_vec(), total_len))
     }
 }
 
@@ -548,11 +490,12 @@ fn decode_storage_value(data: &[u8]) -> Result<U256> {
         if data.len() < 1 + len {
             bail!("Truncated storage value");
         }
-        return Ok(U256::from_be_slice(&data[1..1 + len]));
+        return U256::try_from_be_slice(&data[1..1 + len])
+            .ok_or_else(|| eyre::eyre!("Storage value exceeds 32 bytes"));
     }
 
     // Try to interpret as raw bytes (not RLP)
-    Ok(U256::from_be_slice(data))
+    U256::try_from_be_slice(data).ok_or_else(|| eyre::eyre!("Storage value exceeds 32 bytes"))
 }
 
 #[cfg(test)]
@@ -607,4 +550,57 @@ mod tests {
         let hash = keccak256(&[]);
         assert_eq!(hash, EMPTY_CODE_HASH);
     }
+
+    #[test]
+    fn test_verify_mpt_proof_roundtrip() {
+        let mut trie = trie::MemoryTrie::new();
+        for i in 0..20u8 {
+            trie.insert(keccak256(&[i]), vec![i + 1; 33]);
+        }
+
+        let key = keccak256(&[7]);
+        let value = verify_mpt_proof(trie.root(), &key, &trie.proof(key)).unwrap();
+        assert_eq!(value, Some(vec![8; 33]));
+
+        let absent = keccak256(&[99]);
+        let value = verify_mpt_proof(trie.root(), &absent, &trie.proof(absent)).unwrap();
+        assert_eq!(value, None);
+    }
+
+    #[test]
+    fn test_verify_mpt_proof_rejects_unhashed_short_root() {
+        // An empty branch is under 32 bytes and used to skip the root hash
+        // check, "proving" any key absent under any root
+        let mut empty_branch = vec![0xd1];
+        empty_branch.extend([0x80; 17]);
+        let proof = vec![Bytes::from(empty_branch)];
+
+        let root = B256::repeat_byte(0xaa);
+        let key = keccak256(&[1]);
+        assert!(verify_mpt_proof(root, &key, &proof).is_err());
+    }
+
+    #[test]
+    fn test_decode_rlp_length_overflow() {
+        let data = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        assert!(decode_rlp_length(&data).is_err());
+        assert!(decode_rlp_item(&data).is_err());
+        assert!(decode_rlp_list(&data).is_err());
+    }
+
+    #[test]
+    fn test_decode_rlp_list_rejects_string() {
+        assert!(decode_rlp_list(&[0x82, 0xc1, 0x01]).is_err());
+    }
+
+    #[test]
+    fn test_decode_hp_path_invalid_flag() {
+        assert!(decode_hp_path(&[0x40, 0xab]).is_err());
+        assert!(decode_hp_path(&[0x2a, 0xbc]).is_err());
+    }
+
+    #[test]
+    fn test_decode_storage_value_too_long() {
+        assert!(decode_storage_value(&[0xa1; 34]).is_err());
+    }
 }
@@ -0,0 +1,226 @@
+//! Multiproof verification over a deduplicated node set.
+//!
+//! Proofs for many keys under the same root share their upper trie nodes. A
+//! `MultiProof` keeps every node once, keyed by its hash, and walks each key
+//! from the root, decoding each node at most once.
+
+use alloy_primitives::{Bytes, B256};
+use eyre::{bail, eyre, Result};
+use std::collections::HashMap;
+
+use super::{bytes_to_nibbles, decode_hp_path, decode_rlp_list, keccak256, EMPTY_ROOT_HASH};
+use crate::types::ProofData;
+
+/// Deduplicated set of trie nodes covering many keys.
+#[derive(Debug, Default, Clone)]
+pub struct MultiProof {
+    nodes: HashMap<B256, Bytes>,
+}
+
+impl MultiProof {
+    /// Build a node set from raw proof nodes.
+    pub fn new<'a>(nodes: impl IntoIterator<Item = &'a Bytes>) -> Self {
+        let mut multiproof = Self::default();
+        multiproof.extend(nodes);
+        multiproof
+    }
+
+    /// Build a node set from the account and storage proofs of many accounts.
+    pub fn from_proofs(proofs: &[ProofData]) -> Self {
+        let mut multiproof = Self::default();
+        for proof in proofs {
+            multiproof.extend(&proof.account_proof);
+            for storage_proof in &proof.storage_proof {
+                multiproof.extend(&storage_proof.proof);
+            }
+        }
+        multiproof
+    }
+
+    fn extend<'a>(&mut self, nodes: impl IntoIterator<Item = &'a Bytes>) {
+        for node in nodes {
+            self.nodes
+                .entry(keccak256(node))
+                .or_insert_with(|| node.clone());
+        }
+    }
+
+    /// Number of distinct nodes in the set.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The distinct nodes, for returning to clients.
+    pub fn nodes(&self) -> Vec<Bytes> {
+        self.nodes.values().cloned().collect()
+    }
+
+    /// Start a verification pass that caches decoded nodes across keys.
+    pub fn verifier(&self) -> MultiProofVerifier<'_> {
+        MultiProofVerifier {
+            nodes: &self.nodes,
+            decoded: HashMap::new(),
+        }
+    }
+}
+
+/// Walks keys through a `MultiProof`, decoding each node once.
+pub struct MultiProofVerifier<'a> {
+    nodes: &'a HashMap<B256, Bytes>,
+    decoded: HashMap<B256, Vec<Vec<u8>>>,
+}
+
+impl MultiProofVerifier<'_> {
+    fn decode(&mut self, hash: B256) -> Result<&[Vec<u8>]> {
+        if !self.decoded.contains_key(&hash) {
+            let node = self
+                .nodes
+                .get(&hash)
+                .ok_or_else(|| eyre!("Missing trie node {}", hash))?;
+            let items = decode_rlp_list(node)?;
+            self.decoded.insert(hash, items);
+        }
+        Ok(&self.decoded[&hash])
+    }
+
+    /// Look up a hashed key under `root`.
+    ///
+    /// Returns the value at the key if it exists, or None if the node set proves
+    /// its absence. Fails if a node needed for the walk is missing or malformed.
+    pub fn get(&mut self, root: B256, key: &B256) -> Result<Option<Vec<u8>>> {
+        if root == EMPTY_ROOT_HASH {
+            return Ok(None);
+        }
+
+        let key_nibbles = bytes_to_nibbles(key.as_slice());
+        let mut key_index = 0;
+        let mut hash = root;
+        let mut embedded: Option<Vec<Vec<u8>>> = None;
+
+        loop {
+            let items = match &embedded {
+                Some(items) => items.as_slice(),
+                None => self.decode(hash)?,
+            };
+
+            let child = match items.len() {
+                17 => {
+                    let Some(&nibble) = key_nibbles.get(key_index) else {
+                        // Key ends at this branch - its value lives at index 16
+                        if items[16].is_empty() {
+                            return Ok(None);
+                        }
+                        return Ok(Some(items[16].clone()));
+                    };
+                    key_index += 1;
+                    items[nibble as usize].clone()
+                }
+                2 => {
+                    let (path, is_leaf) = decode_hp_path(&items[0])?;
+                    let remaining_key = &key_nibbles[key_index..];
+
+                    if is_leaf {
+                        if path != remaining_key {
+                            return Ok(None);
+                        }
+                        return Ok(Some(items[1].clone()));
+                    }
+
+                    if path.is_empty() {
+                        bail!("Extension node with empty path");
+                    }
+                    if !remaining_key.starts_with(&path) {
+                        return Ok(None);
+                    }
+                    key_index += path.len();
+                    items[1].clone()
+                }
+                n => bail!("Invalid node with {} items", n),
+            };
+
+            if child.is_empty() {
+                return Ok(None);
+            }
+
+            // A child is either a 32-byte hash or an embedded node (an RLP list < 32 bytes)
+            if child.len() == 32 {
+                hash = B256::from_slice(&child);
+                embedded = None;
+            } else if child[0] >= 0xc0 {
+                embedded = Some(decode_rlp_list(&child)?);
+            } else {
+                bail!("Invalid child reference of {} bytes", child.len());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proof::trie::MemoryTrie;
+
+    fn key(i: u8) -> B256 {
+        keccak256(&[i])
+    }
+
+    fn build_trie(count: u8) -> MemoryTrie {
+        let mut trie = MemoryTrie::new();
+        for i in 0..count {
+            trie.insert(key(i), vec![0x80 + i; 40]);
+        }
+        trie
+    }
+
+    #[test]
+    fn test_multiproof_verifies_many_keys() {
+        let trie = build_trie(50);
+        let proofs: Vec<Bytes> = (0..50).flat_map(|i| trie.proof(key(i))).collect();
+        let multiproof = MultiProof::new(&proofs);
+
+        // Shared upper nodes are deduplicated
+        assert!(multiproof.len() < proofs.len());
+
+        let mut verifier = multiproof.verifier();
+        for i in 0..50 {
+            let value = verifier.get(trie.root(), &key(i)).unwrap();
+            assert_eq!(value, Some(vec![0x80 + i; 40]));
+        }
+    }
+
+    #[test]
+    fn test_multiproof_proves_absence() {
+        let trie = build_trie(10);
+        let absent = key(200);
+        let multiproof = MultiProof::new(&trie.proof(absent));
+
+        let value = multiproof.verifier().get(trie.root(), &absent).unwrap();
+        assert_eq!(value, None);
+    }
+
+    #[test]
+    fn test_multiproof_missing_node_fails() {
+        let trie = build_trie(10);
+        let mut proof = trie.proof(key(3));
+        proof.pop();
+        let multiproof = MultiProof::new(&proof);
+
+        assert!(multiproof.verifier().get(trie.root(), &key(3)).is_err());
+    }
+
+    #[test]
+    fn test_multiproof_rejects_wrong_root() {
+        let trie = build_trie(10);
+        let multiproof = MultiProof::new(&trie.proof(key(1)));
+
+        assert!(multiproof
+            .verifier()
+            .get(B256::repeat_byte(0x11), &key(1))
+            .is_err());
+    }
+}
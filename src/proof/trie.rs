@@ -0,0 +1,193 @@
+//! Minimal in-memory Merkle Patricia Trie.
+//!
+//! Used to rebuild transaction and receipt tries from block contents, and to
+//! build proofs in tests and fuzz targets.
+
+use alloy_primitives::{Bytes, B256};
+use alloy_rlp::{Encodable, Header};
+use std::collections::BTreeMap;
+
+use super::keccak256;
+
+/// In-memory trie. Keys must be prefix-free, as hashed keys and RLP-encoded
+/// indices are.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryTrie {
+    entries: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl MemoryTrie {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a value under an already-hashed key.
+    pub fn insert(&mut self, key: B256, value: Vec<u8>) {
+        self.entries.insert(nibbles(key.as_slice()), value);
+    }
+
+    /// Insert a value under an unhashed key.
+    pub fn insert_raw(&mut self, key: &[u8], value: Vec<u8>) {
+        self.entries.insert(nibbles(key), value);
+    }
+
+    /// Root hash of the trie.
+    pub fn root(&self) -> B256 {
+        if self.entries.is_empty() {
+            return super::EMPTY_ROOT_HASH;
+        }
+        let entries: Vec<_> = self.entries.iter().collect();
+        keccak256(&encode_node(&entries, 0))
+    }
+
+    /// Proof nodes from the root to the key, as returned by `eth_getProof`.
+    ///
+    /// Embedded nodes (< 32 bytes) are part of their parent and not listed separately.
+    pub fn proof(&self, key: B256) -> Vec<Bytes> {
+        let mut proof = Vec::new();
+        if self.entries.is_empty() {
+            return proof;
+        }
+        let key = nibbles(key.as_slice());
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        let mut depth = 0;
+
+        loop {
+            let node = encode_node(&entries, depth);
+            if proof.is_empty() || node.len() >= 32 {
+                proof.push(Bytes::from(node));
+            }
+
+            if entries.len() == 1 {
+                return proof;
+            }
+
+            let prefix = common_prefix(&entries, depth);
+            if prefix > 0 {
+                if key.get(depth..depth + prefix) != Some(&entries[0].0[depth..depth + prefix]) {
+                    return proof;
+                }
+                depth += prefix;
+                continue;
+            }
+
+            let Some(&nibble) = key.get(depth) else {
+                return proof;
+            };
+            entries.retain(|(k, _)| k[depth] == nibble);
+            if entries.is_empty() {
+                return proof;
+            }
+            depth += 1;
+        }
+    }
+}
+
+/// Root of a trie keyed by the RLP-encoded index of each value, as used for
+/// a block's transactions and receipts.
+pub fn ordered_trie_root(values: &[Vec<u8>]) -> B256 {
+    let mut trie = MemoryTrie::new();
+    for (index, value) in values.iter().enumerate() {
+        trie.insert_raw(&encode_index(index), value.clone());
+    }
+    trie.root()
+}
+
+fn encode_index(index: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    index.encode(&mut out);
+    out
+}
+
+fn nibbles(bytes: &[u8]) -> Vec<u8> {
+    super::bytes_to_nibbles(bytes)
+}
+
+fn common_prefix(entries: &[(&Vec<u8>, &Vec<u8>)], depth: usize) -> usize {
+    let first = &entries[0].0[depth..];
+    entries[1..].iter().fold(first.len(), |len, (key, _)| {
+        first[..len]
+            .iter()
+            .zip(&key[depth..])
+            .take_while(|(a, b)| a == b)
+            .count()
+    })
+}
+
+fn encode_node(entries: &[(&Vec<u8>, &Vec<u8>)], depth: usize) -> Vec<u8> {
+    if entries.len() == 1 {
+        let (key, value) = entries[0];
+        return encode_list(&[
+            encode_bytes(&hex_prefix(&key[depth..], true)),
+            encode_bytes(value),
+        ]);
+    }
+
+    let prefix = common_prefix(entries, depth);
+    if prefix > 0 {
+        let path = &entries[0].0[depth..depth + prefix];
+        let child = encode_node(entries, depth + prefix);
+        return encode_list(&[encode_bytes(&hex_prefix(path, false)), child_ref(child)]);
+    }
+
+    let mut items = Vec::with_capacity(17);
+    for nibble in 0..16u8 {
+        let children: Vec<_> = entries
+            .iter()
+            .filter(|(k, _)| k[depth] == nibble)
+            .copied()
+            .collect();
+        if children.is_empty() {
+            items.push(encode_bytes(&[]));
+        } else {
+            items.push(child_ref(encode_node(&children, depth + 1)));
+        }
+    }
+    items.push(encode_bytes(&[]));
+    encode_list(&items)
+}
+
+/// Nodes shorter than 32 bytes are embedded, everything else is referenced by hash.
+fn child_ref(node: Vec<u8>) -> Vec<u8> {
+    if node.len() < 32 {
+        node
+    } else {
+        encode_bytes(keccak256(&node).as_slice())
+    }
+}
+
+fn hex_prefix(path: &[u8], is_leaf: bool) -> Vec<u8> {
+    let flag = if is_leaf { 2 } else { 0 };
+    let mut out = Vec::with_capacity(path.len() / 2 + 1);
+    let rest = if path.len() % 2 == 1 {
+        out.push(((flag + 1) << 4) | path[0]);
+        &path[1..]
+    } else {
+        out.push(flag << 4);
+        path
+    };
+    for pair in rest.chunks(2) {
+        out.push((pair[0] << 4) | pair[1]);
+    }
+    out
+}
+
+fn encode_bytes(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    bytes.encode(&mut out);
+    out
+}
+
+fn encode_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload_length = items.iter().map(Vec::len).sum();
+    let mut out = Vec::new();
+    Header {
+        list: true,
+        payload_length,
+    }
+    .encode(&mut out);
+    for item in items {
+        out.extend_from_slice(item);
+    }
+    out
+}
@@ -0,0 +1,631 @@
+//! ENS resolution proven from registry and resolver storage.
+//!
+//! The resolver of a name is read from registry storage. Records are read from
+//! resolver storage in the layout found by matching the resolver's own,
+//! unverified, answer against proven candidate slots, as for token balances.
+//! The layout is found again for every request, so a lying upstream cannot pin
+//! a resolver to the wrong one. Wildcard (ENSIP-10) and CCIP-read answers
+//! cannot be proven and are flagged as such.
+
+use alloy_primitives::{Address, Bytes, B256, U256};
+use eyre::{bail, eyre, Result};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde::Deserialize;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use super::handlers::{fetch_proof, proven};
+use super::AppState;
+use crate::ens::{
+    addr_call, ancestors, bytes_data_keys, bytes_header, callback_call, decode_address,
+    decode_bytes, dns_encode, name_call, namehash, registry_keys, resolve_call, reverse_name,
+    word_address, BytesHeader, OffchainLookup, RecordKind, RecordLayout, MAX_RECORD_LEN,
+    OLD_REGISTRY, REGISTRY,
+};
+use crate::metrics::VerificationOutcome;
+use crate::types::{ConsensusProof, EnsResolution, ProofData};
+use crate::upstream::CallOutput;
+
+/// Chained `OffchainLookup`s followed for one answer (EIP-3668 suggests 4).
+const MAX_OFFCHAIN_LOOKUPS: usize = 4;
+
+/// Time allowed for each gateway request.
+const GATEWAY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Largest gateway response body read.
+const MAX_GATEWAY_RESPONSE: usize = 256 * 1024;
+
+/// One resolution, pinned to a single block.
+pub struct Resolver<'a> {
+    state: &'a AppState,
+    method: &'static str,
+    consensus_proof: Option<ConsensusProof>,
+    block: String,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(
+        state: &'a AppState,
+        method: &'static str,
+        consensus_proof: Option<ConsensusProof>,
+        block: String,
+    ) -> Self {
+        Self {
+            state,
+            method,
+            consensus_proof,
+            block,
+        }
+    }
+
+    /// Verified block the resolution is pinned to, if any.
+    pub fn consensus_proof(&self) -> Option<&ConsensusProof> {
+        self.consensus_proof.as_ref()
+    }
+
+    /// Whether proofs are checked against a verified state root.
+    fn verified(&self) -> bool {
+        self.consensus_proof.is_some()
+    }
+
+    /// Resolve a name to an address.
+    pub async fn resolve_name(&self, name: &str) -> Result<EnsResolution> {
+        let node = namehash(name);
+        let mut resolution = EnsResolution {
+            name: Some(name.to_string()),
+            address: None,
+            node,
+            resolver: None,
+            resolver_proven: self.verified(),
+            record_proven: false,
+            offchain: false,
+        };
+
+        let Some((depth, resolver)) = self.find_resolver(name).await? else {
+            // No resolver is itself proven: the name has no address
+            resolution.record_proven = self.verified();
+            return Ok(resolution);
+        };
+        resolution.resolver = Some(resolver);
+
+        if depth == 0 {
+            match self.call(resolver, addr_call(node)).await? {
+                CallOutput::Success(output) => {
+                    let expected = decode_address(&output).ok_or_else(|| {
+                        eyre!("Resolver {} returned a malformed address", resolver)
+                    })?;
+                    let expected = address_record(expected);
+                    let (value, proven) = self
+                        .prove_record(resolver, RecordKind::Addr, node, &expected)
+                        .await?;
+                    resolution.address = record_address(&value);
+                    resolution.record_proven = proven && self.verified();
+                    return Ok(resolution);
+                }
+                CallOutput::Revert(revert) => {
+                    let lookup = OffchainLookup::decode(&revert)
+                        .ok_or_else(|| eyre!("Resolver {} reverted", resolver))?;
+                    let output = self.ccip_read(resolver, lookup).await?;
+                    resolution.address =
+                        decode_address(&output).filter(|address| !address.is_zero());
+                    resolution.offchain = true;
+                    return Ok(resolution);
+                }
+            }
+        }
+
+        // The resolver is an ancestor's: ask it through `resolve(name, data)`
+        let call = resolve_call(&dns_encode(name)?, &addr_call(node));
+        let output = match self.call(resolver, call).await? {
+            CallOutput::Success(output) => output,
+            CallOutput::Revert(revert) => {
+                let Some(lookup) = OffchainLookup::decode(&revert) else {
+                    debug!(name, %resolver, "Wildcard resolver does not resolve the name");
+                    return Ok(resolution);
+                };
+                resolution.offchain = true;
+                self.ccip_read(resolver, lookup).await?
+            }
+        };
+        resolution.address = decode_bytes(&output)
+            .and_then(|output| decode_address(&output))
+            .filter(|address| !address.is_zero());
+
+        Ok(resolution)
+    }
+
+    /// Find the primary name of an address. The name is only returned if it
+    /// resolves back to the address.
+    pub async fn lookup_address(&self, address: Address) -> Result<EnsResolution> {
+        let reverse = reverse_name(address);
+        let node = namehash(&reverse);
+        let mut resolution = EnsResolution {
+            name: None,
+            address: Some(address),
+            node,
+            resolver: None,
+            resolver_proven: self.verified(),
+            record_proven: self.verified(),
+            offchain: false,
+        };
+
+        // Reverse records are never resolved through a wildcard
+        let resolver = match self.find_resolver(&reverse).await? {
+            Some((0, resolver)) => resolver,
+            _ => return Ok(resolution),
+        };
+        resolution.resolver = Some(resolver);
+
+        let (name, proven) = match self.call(resolver, name_call(node)).await? {
+            CallOutput::Success(output) => {
+                let expected = decode_bytes(&output)
+                    .ok_or_else(|| eyre!("Resolver {} returned a malformed name", resolver))?;
+                self.prove_record(resolver, RecordKind::Name, node, &expected)
+                    .await?
+            }
+            CallOutput::Revert(revert) => {
+                let lookup = OffchainLookup::decode(&revert)
+                    .ok_or_else(|| eyre!("Resolver {} reverted", resolver))?;
+                let output = self.ccip_read(resolver, lookup).await?;
+                resolution.offchain = true;
+                let name = decode_bytes(&output)
+                    .ok_or_else(|| eyre!("Gateway returned a malformed name"))?;
+                (name, false)
+            }
+        };
+        resolution.record_proven &= proven;
+
+        let Ok(name) = String::from_utf8(name) else {
+            bail!("Reverse record of {} is not valid UTF-8", address);
+        };
+        if name.is_empty() {
+            return Ok(resolution);
+        }
+
+        // The reverse record is only a claim until the name points back
+        let forward = self.resolve_name(&name).await?;
+        resolution.record_proven &= forward.resolver_proven && forward.record_proven;
+        resolution.offchain |= forward.offchain;
+        if forward.address == Some(address) {
+            resolution.name = Some(name);
+        } else {
+            debug!(%address, name, "Primary name does not resolve back to the address");
+        }
+
+        Ok(resolution)
+    }
+
+    /// Resolver of the name or its closest ancestor that has one, with how
+    /// many labels up it was found.
+    async fn find_resolver(&self, name: &str) -> Result<Option<(usize, Address)>> {
+        let nodes: Vec<B256> = ancestors(name).into_iter().map(namehash).collect();
+        let keys = |nodes: &[B256]| -> Vec<B256> {
+            nodes
+                .iter()
+                .flat_map(|node| {
+                    let (owner, resolver) = registry_keys(*node);
+                    [owner, resolver]
+                })
+                .collect()
+        };
+
+        let current = self.prove(REGISTRY, keys(&nodes)).await?;
+
+        // The registry defers to the old one for nodes it has no owner for
+        let mut missing = Vec::new();
+        for node in &nodes {
+            if slot_value(&current, registry_keys(*node).0)?.is_zero() {
+                missing.push(*node);
+            }
+        }
+        let old = match missing.is_empty() {
+            true => None,
+            false => Some(self.prove(OLD_REGISTRY, keys(&missing)).await?),
+        };
+
+        for (depth, node) in nodes.iter().enumerate() {
+            let resolver = registry_keys(*node).1;
+            let proof = match &old {
+                Some(old) if missing.contains(node) => old,
+                _ => &current,
+            };
+            let resolver = word_address(slot_value(proof, resolver)?);
+            if !resolver.is_zero() {
+                return Ok(Some((depth, resolver)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Read a record from resolver storage, returning it and whether it was
+    /// proven. `expected` (the resolver's own answer) picks the layout; if no
+    /// candidate layout holds it, `expected` is returned unproven.
+    async fn prove_record(
+        &self,
+        resolver: Address,
+        kind: RecordKind,
+        node: B256,
+        expected: &[u8],
+    ) -> Result<(Vec<u8>, bool)> {
+        // An empty record looks the same in every layout
+        if expected.is_empty() {
+            return Ok((Vec::new(), false));
+        }
+
+        let version_key = RecordLayout::version_key(node);
+        let unversioned = RecordLayout::unversioned(kind);
+        let mut keys: Vec<B256> = unversioned
+            .iter()
+            .map(|layout| layout.key(kind, node, 0))
+            .collect();
+        keys.push(version_key);
+        let proof = self.prove(resolver, keys).await?;
+
+        if let Some(layout) = self
+            .match_layout(resolver, kind, node, 0, &unversioned, &proof, expected)
+            .await?
+        {
+            return Ok(self.detected(resolver, kind, layout, expected));
+        }
+
+        let version: u64 = slot_value(&proof, version_key)?.saturating_to();
+        let versioned = RecordLayout::versioned();
+        let keys = versioned
+            .iter()
+            .map(|layout| layout.key(kind, node, version))
+            .collect();
+        let proof = self.prove(resolver, keys).await?;
+
+        if let Some(layout) = self
+            .match_layout(resolver, kind, node, version, &versioned, &proof, expected)
+            .await?
+        {
+            return Ok(self.detected(resolver, kind, layout, expected));
+        }
+
+        debug!(%resolver, ?kind, "No known record layout matches the resolver's answer");
+        Ok((expected.to_vec(), false))
+    }
+
+    fn detected(
+        &self,
+        resolver: Address,
+        kind: RecordKind,
+        layout: RecordLayout,
+        expected: &[u8],
+    ) -> (Vec<u8>, bool) {
+        debug!(%resolver, ?kind, ?layout, "Detected resolver record layout");
+        (expected.to_vec(), true)
+    }
+
+    /// First candidate layout whose proven storage holds `expected`.
+    #[allow(clippy::too_many_arguments)]
+    async fn match_layout(
+        &self,
+        resolver: Address,
+        kind: RecordKind,
+        node: B256,
+        version: u64,
+        candidates: &[RecordLayout],
+        proof: &ProofData,
+        expected: &[u8],
+    ) -> Result<Option<RecordLayout>> {
+        for layout in candidates {
+            let word = slot_value(proof, layout.key(kind, node, version))?;
+            if !layout.is_bytes(kind) {
+                if expected.len() == 20 && word == U256::from_be_slice(expected) {
+                    return Ok(Some(*layout));
+                }
+                continue;
+            }
+            if word != bytes_header(expected) {
+                continue;
+            }
+            // A long value's header only holds its length; compare the data too
+            if expected.len() < 32
+                || self
+                    .read_record(resolver, *layout, kind, node, version)
+                    .await?
+                    == expected
+            {
+                return Ok(Some(*layout));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read a record in a known layout from proven storage.
+    async fn read_record(
+        &self,
+        resolver: Address,
+        layout: RecordLayout,
+        kind: RecordKind,
+        node: B256,
+        version: u64,
+    ) -> Result<Vec<u8>> {
+        let key = layout.key(kind, node, version);
+        let proof = self.prove(resolver, vec![key]).await?;
+        let word = slot_value(&proof, key)?;
+
+        if !layout.is_bytes(kind) {
+            return Ok(address_record(word_address(word)));
+        }
+
+        match BytesHeader::decode(word) {
+            Some(BytesHeader::Short(value)) => Ok(value),
+            Some(BytesHeader::Long(len)) if len <= MAX_RECORD_LEN => {
+                let keys = bytes_data_keys(key, len);
+                let proof = self.prove(resolver, keys.clone()).await?;
+                let mut value = Vec::with_capacity(keys.len() * 32);
+                for key in keys {
+                    value.extend_from_slice(&slot_value(&proof, key)?.to_be_bytes::<32>());
+                }
+                value.truncate(len);
+                Ok(value)
+            }
+            _ => bail!(
+                "Record of {} in {} is malformed or too long",
+                node,
+                resolver
+            ),
+        }
+    }
+
+    /// Fetch storage proofs and check them against the verified state root.
+    async fn prove(&self, address: Address, keys: Vec<B256>) -> Result<ProofData> {
+        let proof = fetch_proof(
+            self.state,
+            self.method,
+            address,
+            keys,
+            &self.block,
+            self.consensus_proof.as_ref(),
+        )
+        .await?;
+
+        let Some(ref cp) = self.consensus_proof else {
+            return Ok(proof);
+        };
+        let valid = self
+            .state
+            .proof_generator
+            .verify_complete_proof(cp.state_root, &proof)
+            .unwrap_or(false);
+        if !valid {
+            self.state
+                .metrics
+                .record_verification(self.method, VerificationOutcome::Failed);
+            bail!("Proof verification failed - data integrity check failed");
+        }
+        Ok(proof)
+    }
+
+    async fn call(&self, to: Address, data: Bytes) -> Result<CallOutput> {
+        self.state
+            .upstream()
+            .eth_try_call(to, data, &self.block)
+            .await
+    }
+
+    /// Follow an `OffchainLookup` to its gateway and back through the
+    /// resolver's callback, returning the callback's output.
+    async fn ccip_read(&self, resolver: Address, mut lookup: OffchainLookup) -> Result<Bytes> {
+        if !self.state.config().ens.ccip_read {
+            bail!("Name requires an offchain lookup, which is disabled");
+        }
+
+        for _ in 0..MAX_OFFCHAIN_LOOKUPS {
+            if lookup.sender != resolver {
+                bail!(
+                    "OffchainLookup sender {} is not the resolver",
+                    lookup.sender
+                );
+            }
+            let response = query_gateway(&lookup).await?;
+            let callback = callback_call(lookup.callback, &response, &lookup.extra_data);
+            match self.call(resolver, callback).await? {
+                CallOutput::Success(output) => return Ok(output),
+                CallOutput::Revert(revert) => {
+                    lookup = OffchainLookup::decode(&revert)
+                        .ok_or_else(|| eyre!("Resolver callback reverted"))?;
+                }
+            }
+        }
+
+        bail!("Too many chained offchain lookups")
+    }
+}
+
+#[derive(Deserialize)]
+struct GatewayResponse {
+    data: Bytes,
+}
+
+/// Client for gateway requests. Gateway URLs come from resolver revert data,
+/// which the upstream controls, so redirects are not followed and hosts must
+/// resolve to public addresses only.
+fn gateway_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(GATEWAY_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .dns_resolver(Arc::new(PublicResolver))
+            .build()
+            .expect("gateway client")
+    })
+}
+
+/// Resolver refusing names with any non-public address, so a gateway URL
+/// cannot reach the node's own network.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+            if let Some(addr) = addrs.iter().find(|addr| !is_public(addr.ip())) {
+                return Err(
+                    format!("{} resolves to non-public {}", name.as_str(), addr.ip()).into(),
+                );
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Whether `ip` is a globally routable unicast address.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+/// Check a gateway URL before contacting it. Hostnames are checked when the
+/// client resolves them.
+fn gateway_url_allowed(url: &str) -> Result<()> {
+    let url = reqwest::Url::parse(url)?;
+    if url.scheme() != "https" {
+        bail!("not HTTPS");
+    }
+    let Some(host) = url.host_str() else {
+        bail!("no host");
+    };
+    match host
+        .trim_matches(|c| c == '[' || c == ']')
+        .parse::<IpAddr>()
+    {
+        Ok(ip) if !is_public(ip) => bail!("non-public address"),
+        _ => Ok(()),
+    }
+}
+
+/// Read a gateway response body, refusing bodies over `MAX_GATEWAY_RESPONSE`.
+async fn read_limited(mut response: reqwest::Response) -> Result<Vec<u8>> {
+    if response.content_length().unwrap_or(0) > MAX_GATEWAY_RESPONSE as u64 {
+        bail!("response over {} bytes", MAX_GATEWAY_RESPONSE);
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > MAX_GATEWAY_RESPONSE {
+            bail!("response over {} bytes", MAX_GATEWAY_RESPONSE);
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// Ask each gateway in turn, as EIP-3668 describes: `GET` when the URL takes
+/// `{data}`, `POST` otherwise.
+async fn query_gateway(lookup: &OffchainLookup) -> Result<Bytes> {
+    let client = gateway_client();
+    let sender = format!("0x{}", hex::encode(lookup.sender));
+    let data = format!("0x{}", hex::encode(&lookup.call_data));
+
+    for url in &lookup.urls {
+        let url = url.replace("{sender}", &sender);
+        if let Err(e) = gateway_url_allowed(&url) {
+            debug!(url, "Skipping gateway: {}", e);
+            continue;
+        }
+
+        let request = if url.contains("{data}") {
+            client.get(url.replace("{data}", &data))
+        } else {
+            client
+                .post(&url)
+                .json(&serde_json::json!({ "sender": sender, "data": data }))
+        };
+
+        let response = match request.send().await.and_then(|r| r.error_for_status()) {
+            Ok(response) => response,
+            Err(e) => {
+                warn!(url, "Gateway request failed: {}", e);
+                continue;
+            }
+        };
+        let body = match read_limited(response).await {
+            Ok(body) => body,
+            Err(e) => {
+                warn!(url, "Gateway response rejected: {}", e);
+                continue;
+            }
+        };
+        match serde_json::from_slice::<GatewayResponse>(&body) {
+            Ok(response) => return Ok(response.data),
+            Err(e) => warn!(url, "Gateway returned an invalid response: {}", e),
+        }
+    }
+
+    bail!("No gateway answered the offchain lookup")
+}
+
+/// Proven value of a storage key, failing if the upstream left it out.
+fn slot_value(proof: &ProofData, key: B256) -> Result<U256> {
+    proven(proof, key).ok_or_else(|| eyre!("Upstream proof is missing a requested storage slot"))
+}
+
+/// Raw address record: the address bytes, or empty when unset.
+fn address_record(address: Address) -> Vec<u8> {
+    if address.is_zero() {
+        Vec::new()
+    } else {
+        address.to_vec()
+    }
+}
+
+fn record_address(value: &[u8]) -> Option<Address> {
+    (value.len() == 20).then(|| Address::from_slice(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gateway_urls() {
+        assert!(gateway_url_allowed("https://ccip.example.com/{sender}/{data}.json").is_ok());
+        assert!(gateway_url_allowed("https://8.8.8.8/lookup").is_ok());
+
+        for url in [
+            "http://ccip.example.com/{data}",
+            "https://127.0.0.1/",
+            "https://127.1/",
+            "https://10.0.0.5:8545/",
+            "https://169.254.169.254/latest/meta-data",
+            "https://100.64.0.1/",
+            "https://[::1]/",
+            "https://[fe80::1]/",
+            "https://[fd00::1]/",
+            "https://[::ffff:192.168.1.1]/",
+        ] {
+            assert!(gateway_url_allowed(url).is_err(), "{url} allowed");
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Re-verification of headers and proofs received from peers.
+//!
+//! A peer's word counts for nothing: headers must match the header our own
+//! light client verified at that height, and proofs must verify against its
+//! state root. Only then are they used, and only then forwarded.
+
+use libp2p::gossipsub::MessageAcceptance;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+use super::AppState;
+use crate::consensus::VerifiedHeader;
+use crate::p2p::{GossipMessage, Inbound, P2pHandle};
+use crate::proof::ProofGenerator;
+
+/// Validate messages from peers, caching proofs that verify and that local
+/// requests have asked for.
+pub async fn validate_gossip(
+    state: Arc<AppState>,
+    p2p: P2pHandle,
+    mut inbound: mpsc::Receiver<Inbound>,
+) {
+    while let Some(received) = inbound.recv().await {
+        let acceptance = validate(&state, &received).await;
+        if matches!(acceptance, MessageAcceptance::Reject) {
+            warn!(peer = %received.source, "Rejected gossip that failed verification");
+        }
+        p2p.report(&received, acceptance);
+    }
+}
+
+async fn validate(state: &AppState, received: &Inbound) -> MessageAcceptance {
+    let Some(ref consensus) = state.consensus else {
+        return MessageAcceptance::Ignore;
+    };
+    let number = match received.message {
+        GossipMessage::Header { number, .. } => number,
+        GossipMessage::Proof { block_number, .. } => block_number,
+    };
+    let header = match consensus.get_header(number).await {
+        Ok(header) => header,
+        Err(e) => {
+            debug!(number, "Cannot check gossip, header unavailable: {}", e);
+            return MessageAcceptance::Ignore;
+        }
+    };
+
+    let acceptance = check(&state.proof_generator, header.as_ref(), &received.message);
+    if let (MessageAcceptance::Accept, GossipMessage::Proof { proof, .. }) =
+        (&acceptance, &received.message)
+    {
+        if state.proof_cache.insert_requested(number, proof.clone()) {
+            debug!(address = %proof.address, block = number, "Cached proof from peer");
+        }
+    }
+    acceptance
+}
+
+/// Check a message against our own verified header at its height. Messages
+/// about blocks we have not verified are ignored rather than rejected, since
+/// peers may simply be ahead of or behind us.
+fn check(
+    proof_generator: &ProofGenerator,
+    header: Option<&VerifiedHeader>,
+    message: &GossipMessage,
+) -> MessageAcceptance {
+    let Some(header) = header else {
+        return MessageAcceptance::Ignore;
+    };
+
+    let valid = match message {
+        GossipMessage::Header {
+            hash, state_root, ..
+        } => *hash == header.hash && *state_root == header.state_root,
+        GossipMessage::Proof {
+            state_root, proof, ..
+        } => {
+            *state_root == header.state_root
+                && proof_generator
+                    .verify_complete_proof(header.state_root, proof)
+                    .unwrap_or(false)
+        }
+    };
+
+    match valid {
+        true => MessageAcceptance::Accept,
+        false => MessageAcceptance::Reject,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ProofData;
+    use alloy_primitives::{B256, U256, U64};
+
+    fn header() -> VerifiedHeader {
+        VerifiedHeader {
+            number: 10,
+            hash: B256::repeat_byte(0xaa),
+            state_root: B256::repeat_byte(0xbb),
+            transactions_root: B256::ZERO,
+            receipts_root: B256::ZERO,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn test_check_gossip_against_verified_header() {
+        let config = toml::from_str(
+            r#"
+            [server]
+            [ethereum]
+            network = "mainnet"
+            execution_rpc = "http://localhost:8545"
+            consensus_rpc = "http://localhost:5052"
+            [consensus]
+            [proof]
+            "#,
+        )
+        .unwrap();
+        let generator = ProofGenerator::new(&config);
+        let header = header();
+        let announced = |hash| GossipMessage::Header {
+            number: 10,
+            hash,
+            state_root: header.state_root,
+        };
+
+        assert!(matches!(
+            check(&generator, Some(&header), &announced(header.hash)),
+            MessageAcceptance::Accept
+        ));
+        assert!(matches!(
+            check(&generator, Some(&header), &announced(B256::ZERO)),
+            MessageAcceptance::Reject
+        ));
+        assert!(matches!(
+            check(&generator, None, &announced(header.hash)),
+            MessageAcceptance::Ignore
+        ));
+
+        // A proof that does not verify against our state root is rejected
+        let forged = GossipMessage::Proof {
+            block_number: 10,
+            state_root: header.state_root,
+            proof: ProofData {
+                address: Default::default(),
+                balance: U256::from(1_000_000),
+                code_hash: B256::ZERO,
+                nonce: U64::ZERO,
+                storage_hash: B256::ZERO,
+                account_proof: vec![],
+                storage_proof: vec![],
+            },
+        };
+        assert!(matches!(
+            check(&generator, Some(&header), &forged),
+            MessageAcceptance::Reject
+        ));
+    }
+}
@@ -1,10 +1,23 @@
 //! RPC method handlers.
 
-use alloy_primitives::{Address, B256, U256, U64};
-use tracing::{debug, error};
+use alloy_primitives::{Address, Bytes, B256, U256, U64};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, warn};
 
+use super::ens::Resolver;
+use super::inclusion::{InclusionWatch, POLL_INTERVAL};
 use super::AppState;
-use crate::types::{RpcError, RpcRequest, RpcResponse};
+use crate::access;
+use crate::metrics::VerificationOutcome;
+use crate::p2p::GossipMessage;
+use crate::proof::MultiProof;
+use crate::token::{allowance_call, balance_of_call, MappingSlot, TokenField, MAX_PROBE_SLOT};
+use crate::tx::decode_raw_transaction;
+use crate::types::{
+    AccountResult, AttestationKey, ConsensusProof, EnsResolution, InclusionState,
+    MultiAccountProof, NodeInfo, ProofData, RpcError, RpcRequest, RpcResponse, StorageValue,
+};
 
 /// Parse address and block tag from params.
 fn parse_address_block(params: &serde_json::Value) -> Result<(Address, String), String> {
@@ -28,6 +41,93 @@ fn parse_address_block(params: &serde_json::Value) -> Result<(Address, String),
     Ok((address, block))
 }
 
+/// Block number under which proofs for `query_block` may be cached: only proofs
+/// for the consensus-verified block itself are.
+fn cached_block(query_block: &str, consensus_proof: &ConsensusProof) -> Option<u64> {
+    (query_block == format!("0x{:x}", consensus_proof.block_number))
+        .then_some(consensus_proof.block_number)
+}
+
+/// Record a proof that verified against the consensus state root and cache it,
+/// sharing newly cached proofs with peers.
+fn record_verified(
+    state: &AppState,
+    method: &str,
+    query_block: &str,
+    consensus_proof: &ConsensusProof,
+    proof: &ProofData,
+) {
+    state
+        .metrics
+        .record_verification(method, VerificationOutcome::Verified);
+    if let Some(block) = cached_block(query_block, consensus_proof) {
+        if state.proof_cache.insert(block, proof.clone()) {
+            if let Some(ref p2p) = state.p2p {
+                p2p.publish(GossipMessage::Proof {
+                    block_number: block,
+                    state_root: consensus_proof.state_root,
+                    proof: proof.clone(),
+                });
+            }
+        }
+    }
+}
+
+/// Response for a result proven against `consensus_proof`, with the proof
+/// attached when requested and signed when the node has an attestation key.
+fn verified_response<T: Serialize>(
+    state: &AppState,
+    request: &RpcRequest,
+    result: T,
+    consensus_proof: Option<ConsensusProof>,
+    proof: Option<ProofData>,
+    include_proof: bool,
+) -> serde_json::Value {
+    let mut response = RpcResponse::new(request.id.clone(), result);
+    let Some(cp) = consensus_proof else {
+        return serde_json::to_value(response).unwrap();
+    };
+    access::record_block(cp.block_number);
+
+    if let Some(ref attestor) = state.attestor {
+        let result = serde_json::to_value(&response.result).unwrap();
+        let attestation = attestor.attest(&request.method, &request.params, &result, &cp);
+        response = response.with_attestation(attestation);
+    }
+    if include_proof {
+        response.proof = proof;
+        response = response.with_consensus(cp);
+    }
+    serde_json::to_value(response).unwrap()
+}
+
+/// Fetch a proof from the upstream, or from the proof cache when querying the
+/// consensus-verified block.
+pub(super) async fn fetch_proof(
+    state: &AppState,
+    method: &str,
+    address: Address,
+    storage_keys: Vec<B256>,
+    query_block: &str,
+    consensus_proof: Option<&ConsensusProof>,
+) -> eyre::Result<ProofData> {
+    if let Some(block) = consensus_proof.and_then(|cp| cached_block(query_block, cp)) {
+        let cached = state.proof_cache.get(block, address, &storage_keys);
+        state.metrics.record_proof_cache(cached.is_some());
+        if let Some(proof) = cached {
+            debug!(%address, block, "Proof served from cache");
+            return Ok(proof);
+        }
+    }
+
+    let proof = state
+        .upstream()
+        .eth_get_proof(address, storage_keys, query_block)
+        .await?;
+    state.metrics.record_proof_size(method, proof.proof_size());
+    Ok(proof)
+}
+
 /// eth_getBalance - Get account balance with optional proof.
 pub async fn eth_get_balance(
     state: &AppState,
@@ -68,10 +168,15 @@ pub async fn eth_get_balance(
     };
 
     // Fetch proof from upstream using consensus-verified block
-    let proof_data = match state
-        .upstream
-        .eth_get_proof(address, vec![], &query_block)
-        .await
+    let proof_data = match fetch_proof(
+        state,
+        "eth_getBalance",
+        address,
+        vec![],
+        &query_block,
+        consensus_proof.as_ref(),
+    )
+    .await
     {
         Ok(p) => p,
         Err(e) => {
@@ -91,9 +196,13 @@ pub async fn eth_get_balance(
             .verify_account_proof(cp.state_root, &proof_data)
         {
             Ok(true) => {
+                record_verified(state, "eth_getBalance", &query_block, cp, &proof_data);
                 debug!("Proof verified successfully against state root");
             }
             Ok(false) => {
+                state
+                    .metrics
+                    .record_verification("eth_getBalance", VerificationOutcome::Failed);
                 error!("Proof verification failed - data may be tampered");
                 return serde_json::to_value(RpcError::internal(
                     request.id.clone(),
@@ -102,6 +211,9 @@ pub async fn eth_get_balance(
                 .unwrap();
             }
             Err(e) => {
+                state
+                    .metrics
+                    .record_verification("eth_getBalance", VerificationOutcome::Failed);
                 error!("Proof verification error: {}", e);
                 return serde_json::to_value(RpcError::internal(
                     request.id.clone(),
@@ -110,20 +222,22 @@ pub async fn eth_get_balance(
                 .unwrap();
             }
         }
+    } else {
+        state
+            .metrics
+            .record_verification("eth_getBalance", VerificationOutcome::Skipped);
     }
 
     let balance = proof_data.balance;
 
-    if include_proof {
-        if let Some(cp) = consensus_proof {
-            let response = RpcResponse::new(request.id.clone(), balance).with_proof(proof_data, cp);
-            serde_json::to_value(response).unwrap()
-        } else {
-            serde_json::to_value(RpcResponse::new(request.id.clone(), balance)).unwrap()
-        }
-    } else {
-        serde_json::to_value(RpcResponse::new(request.id.clone(), balance)).unwrap()
-    }
+    verified_response(
+        state,
+        request,
+        balance,
+        consensus_proof,
+        Some(proof_data),
+        include_proof,
+    )
 }
 
 /// eth_getStorageAt - Get storage value with optional proof.
@@ -200,10 +314,15 @@ pub async fn eth_get_storage_at(
     };
 
     // Fetch proof with storage key using consensus-verified block
-    let proof_data = match state
-        .upstream
-        .eth_get_proof(address, vec![slot], &query_block)
-        .await
+    let proof_data = match fetch_proof(
+        state,
+        "eth_getStorageAt",
+        address,
+        vec![slot],
+        &query_block,
+        consensus_proof.as_ref(),
+    )
+    .await
     {
         Ok(p) => p,
         Err(e) => {
@@ -223,9 +342,13 @@ pub async fn eth_get_storage_at(
             .verify_complete_proof(cp.state_root, &proof_data)
         {
             Ok(true) => {
+                record_verified(state, "eth_getStorageAt", &query_block, cp, &proof_data);
                 debug!("Complete proof verified successfully");
             }
             Ok(false) => {
+                state
+                    .metrics
+                    .record_verification("eth_getStorageAt", VerificationOutcome::Failed);
                 error!("Proof verification failed - data may be tampered");
                 return serde_json::to_value(RpcError::internal(
                     request.id.clone(),
@@ -234,6 +357,9 @@ pub async fn eth_get_storage_at(
                 .unwrap();
             }
             Err(e) => {
+                state
+                    .metrics
+                    .record_verification("eth_getStorageAt", VerificationOutcome::Failed);
                 error!("Proof verification error: {}", e);
                 return serde_json::to_value(RpcError::internal(
                     request.id.clone(),
@@ -242,6 +368,10 @@ pub async fn eth_get_storage_at(
                 .unwrap();
             }
         }
+    } else {
+        state
+            .metrics
+            .record_verification("eth_getStorageAt", VerificationOutcome::Skipped);
     }
 
     // Extract storage value
@@ -254,17 +384,14 @@ pub async fn eth_get_storage_at(
     // Convert U256 to B256 for storage response
     let value_b256 = B256::from(value);
 
-    if include_proof {
-        if let Some(cp) = consensus_proof {
-            let response =
-                RpcResponse::new(request.id.clone(), value_b256).with_proof(proof_data, cp);
-            serde_json::to_value(response).unwrap()
-        } else {
-            serde_json::to_value(RpcResponse::new(request.id.clone(), value_b256)).unwrap()
-        }
-    } else {
-        serde_json::to_value(RpcResponse::new(request.id.clone(), value_b256)).unwrap()
-    }
+    verified_response(
+        state,
+        request,
+        value_b256,
+        consensus_proof,
+        Some(proof_data),
+        include_proof,
+    )
 }
 
 /// eth_getTransactionCount - Get account nonce with optional proof.
@@ -300,10 +427,15 @@ pub async fn eth_get_transaction_count(
         block.clone()
     };
 
-    let proof_data = match state
-        .upstream
-        .eth_get_proof(address, vec![], &query_block)
-        .await
+    let proof_data = match fetch_proof(
+        state,
+        "eth_getTransactionCount",
+        address,
+        vec![],
+        &query_block,
+        consensus_proof.as_ref(),
+    )
+    .await
     {
         Ok(p) => p,
         Err(e) => {
@@ -323,9 +455,19 @@ pub async fn eth_get_transaction_count(
             .verify_account_proof(cp.state_root, &proof_data)
         {
             Ok(true) => {
+                record_verified(
+                    state,
+                    "eth_getTransactionCount",
+                    &query_block,
+                    cp,
+                    &proof_data,
+                );
                 debug!("Proof verified successfully");
             }
             Ok(false) => {
+                state
+                    .metrics
+                    .record_verification("eth_getTransactionCount", VerificationOutcome::Failed);
                 error!("Proof verification failed - data may be tampered");
                 return serde_json::to_value(RpcError::internal(
                     request.id.clone(),
@@ -334,6 +476,9 @@ pub async fn eth_get_transaction_count(
                 .unwrap();
             }
             Err(e) => {
+                state
+                    .metrics
+                    .record_verification("eth_getTransactionCount", VerificationOutcome::Failed);
                 error!("Proof verification error: {}", e);
                 return serde_json::to_value(RpcError::internal(
                     request.id.clone(),
@@ -342,27 +487,29 @@ pub async fn eth_get_transaction_count(
                 .unwrap();
             }
         }
+    } else {
+        state
+            .metrics
+            .record_verification("eth_getTransactionCount", VerificationOutcome::Skipped);
     }
 
     let nonce = proof_data.nonce;
 
-    if include_proof {
-        if let Some(cp) = consensus_proof {
-            let response = RpcResponse::new(request.id.clone(), nonce).with_proof(proof_data, cp);
-            serde_json::to_value(response).unwrap()
-        } else {
-            serde_json::to_value(RpcResponse::new(request.id.clone(), nonce)).unwrap()
-        }
-    } else {
-        serde_json::to_value(RpcResponse::new(request.id.clone(), nonce)).unwrap()
-    }
+    verified_response(
+        state,
+        request,
+        nonce,
+        consensus_proof,
+        Some(proof_data),
+        include_proof,
+    )
 }
 
-/// eth_getCode - Get contract code with optional proof.
+/// eth_getCode - Get contract code, checked against the proven code hash.
 pub async fn eth_get_code(
-    _state: &AppState,
+    state: &AppState,
     request: &RpcRequest,
-    _include_proof: bool,
+    include_proof: bool,
 ) -> serde_json::Value {
     let (address, block) = match parse_address_block(&request.params) {
         Ok(v) => v,
@@ -371,15 +518,108 @@ pub async fn eth_get_code(
         }
     };
 
-    debug!(%address, %block, "eth_getCode");
+    debug!(%address, %block, include_proof, "eth_getCode");
 
-    // TODO: Implement code fetching with proof
-    // For now, return empty code
-    serde_json::to_value(RpcResponse::new(
-        request.id.clone(),
-        alloy_primitives::Bytes::new(),
-    ))
-    .unwrap()
+    // Get consensus proof first to determine which block to query
+    let consensus_proof = if let Some(ref consensus) = state.consensus {
+        consensus.get_consensus_proof().await.ok()
+    } else {
+        None
+    };
+
+    // Use consensus block number if available and user requested "latest"
+    let query_block = if block == "latest" {
+        if let Some(ref cp) = consensus_proof {
+            format!("0x{:x}", cp.block_number)
+        } else {
+            block.clone()
+        }
+    } else {
+        block.clone()
+    };
+
+    let proof_data = match fetch_proof(
+        state,
+        "eth_getCode",
+        address,
+        vec![],
+        &query_block,
+        consensus_proof.as_ref(),
+    )
+    .await
+    {
+        Ok(p) => p,
+        Err(e) => {
+            error!("Failed to fetch proof: {}", e);
+            return serde_json::to_value(RpcError::internal(
+                request.id.clone(),
+                format!("Failed to fetch proof: {}", e),
+            ))
+            .unwrap();
+        }
+    };
+
+    let code = match state.upstream().eth_get_code(address, &query_block).await {
+        Ok(code) => code,
+        Err(e) => {
+            error!("Failed to fetch code: {}", e);
+            return serde_json::to_value(RpcError::internal(
+                request.id.clone(),
+                format!("Failed to fetch code: {}", e),
+            ))
+            .unwrap();
+        }
+    };
+
+    // Verify the account against the consensus state root, then the code
+    // against the account's code hash
+    if let Some(ref cp) = consensus_proof {
+        let verified = state
+            .proof_generator
+            .verify_account_proof(cp.state_root, &proof_data)
+            .map(|valid| valid && alloy_primitives::keccak256(&code) == proof_data.code_hash);
+        match verified {
+            Ok(true) => {
+                record_verified(state, "eth_getCode", &query_block, cp, &proof_data);
+                debug!("Code verified against proven code hash");
+            }
+            Ok(false) => {
+                state
+                    .metrics
+                    .record_verification("eth_getCode", VerificationOutcome::Failed);
+                error!("Code verification failed - data may be tampered");
+                return serde_json::to_value(RpcError::internal(
+                    request.id.clone(),
+                    "Proof verification failed - data integrity check failed",
+                ))
+                .unwrap();
+            }
+            Err(e) => {
+                state
+                    .metrics
+                    .record_verification("eth_getCode", VerificationOutcome::Failed);
+                error!("Proof verification error: {}", e);
+                return serde_json::to_value(RpcError::internal(
+                    request.id.clone(),
+                    format!("Proof verification error: {}", e),
+                ))
+                .unwrap();
+            }
+        }
+    } else {
+        state
+            .metrics
+            .record_verification("eth_getCode", VerificationOutcome::Skipped);
+    }
+
+    verified_response(
+        state,
+        request,
+        code,
+        consensus_proof,
+        Some(proof_data),
+        include_proof,
+    )
 }
 
 /// eth_getProof - Standard EIP-1186 proof response.
@@ -434,7 +674,7 @@ pub async fn eth_get_proof(state: &AppState, request: &RpcRequest) -> serde_json
     debug!(%address, ?storage_keys, %block, "eth_getProof");
 
     let proof_data = match state
-        .upstream
+        .upstream()
         .eth_get_proof(address, storage_keys, &block)
         .await
     {
@@ -471,7 +711,1038 @@ pub async fn eth_block_number(state: &AppState, request: &RpcRequest) -> serde_j
 pub async fn eth_chain_id(state: &AppState, request: &RpcRequest) -> serde_json::Value {
     serde_json::to_value(RpcResponse::new(
         request.id.clone(),
-        U64::from(state.config.ethereum.chain_id),
+        U64::from(state.config().ethereum.chain_id),
+    ))
+    .unwrap()
+}
+
+/// dvrpc_nodeInfo - Node identity, including the key signing attestations.
+pub fn dvrpc_node_info(state: &AppState, request: &RpcRequest) -> serde_json::Value {
+    let attestation = state.attestor.as_ref().map(|attestor| AttestationKey {
+        scheme: attestor.scheme(),
+        public_key: attestor.public_key(),
+        address: attestor.address(),
+    });
+
+    serde_json::to_value(RpcResponse::new(
+        request.id.clone(),
+        NodeInfo {
+            version: env!("CARGO_PKG_VERSION"),
+            network: state.config().ethereum.network.to_string(),
+            chain_id: state.config().ethereum.chain_id,
+            attestation,
+        },
     ))
     .unwrap()
 }
+
+/// admin_reloadConfig - Reload the config file, as on SIGHUP.
+pub async fn admin_reload_config(state: &AppState, request: &RpcRequest) -> serde_json::Value {
+    match state.reload().await {
+        Ok(()) => serde_json::to_value(RpcResponse::new(request.id.clone(), true)).unwrap(),
+        Err(e) => {
+            error!("Config reload failed: {}", e);
+            serde_json::to_value(RpcError::new(request.id.clone(), -32000, e.to_string())).unwrap()
+        }
+    }
+}
+
+/// Account entry in `dvrpc_getMultiAccountProof` params: a bare address or an
+/// address with storage keys.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AccountQuery {
+    Address(Address),
+    WithStorage {
+        address: Address,
+        #[serde(default, rename = "storageKeys")]
+        storage_keys: Vec<B256>,
+    },
+}
+
+impl From<AccountQuery> for (Address, Vec<B256>) {
+    fn from(query: AccountQuery) -> Self {
+        match query {
+            AccountQuery::Address(address) => (address, vec![]),
+            AccountQuery::WithStorage {
+                address,
+                storage_keys,
+            } => (address, storage_keys),
+        }
+    }
+}
+
+/// Most accounts one `dvrpc_getMultiAccountProof` request may ask for.
+const MAX_MULTIPROOF_ACCOUNTS: usize = 256;
+
+/// Most storage keys, across all accounts, one request may ask for.
+const MAX_MULTIPROOF_STORAGE_KEYS: usize = 1024;
+
+/// Check that the upstream answered for exactly the accounts and storage keys
+/// requested, in order.
+fn check_requested_proofs(
+    accounts: &[(Address, Vec<B256>)],
+    proofs: &[ProofData],
+) -> Result<(), String> {
+    if proofs.len() != accounts.len() {
+        return Err(format!(
+            "expected {} proofs, got {}",
+            accounts.len(),
+            proofs.len()
+        ));
+    }
+    for ((address, keys), proof) in accounts.iter().zip(proofs) {
+        if proof.address != *address {
+            return Err(format!(
+                "proof for {} returned for {}",
+                proof.address, address
+            ));
+        }
+        if !proof
+            .storage_proof
+            .iter()
+            .map(|sp| sp.key)
+            .eq(keys.iter().copied())
+        {
+            return Err(format!("storage keys of {} do not match", address));
+        }
+    }
+    Ok(())
+}
+
+/// dvrpc_getMultiAccountProof - Verify many accounts and slots against one state root.
+pub async fn dvrpc_get_multi_account_proof(
+    state: &AppState,
+    request: &RpcRequest,
+    include_proof: bool,
+) -> serde_json::Value {
+    let params = match request.params.as_array() {
+        Some(p) => p,
+        None => {
+            return serde_json::to_value(RpcError::invalid_params(
+                request.id.clone(),
+                "params must be an array",
+            ))
+            .unwrap()
+        }
+    };
+
+    if params.is_empty() {
+        return serde_json::to_value(RpcError::invalid_params(
+            request.id.clone(),
+            "missing accounts parameter",
+        ))
+        .unwrap();
+    }
+
+    let accounts: Vec<(Address, Vec<B256>)> =
+        match serde_json::from_value::<Vec<AccountQuery>>(params[0].clone()) {
+            Ok(a) => a.into_iter().map(Into::into).collect(),
+            Err(e) => {
+                return serde_json::to_value(RpcError::invalid_params(
+                    request.id.clone(),
+                    format!("invalid accounts: {}", e),
+                ))
+                .unwrap()
+            }
+        };
+
+    let storage_keys: usize = accounts.iter().map(|(_, keys)| keys.len()).sum();
+    if accounts.len() > MAX_MULTIPROOF_ACCOUNTS || storage_keys > MAX_MULTIPROOF_STORAGE_KEYS {
+        return serde_json::to_value(RpcError::invalid_params(
+            request.id.clone(),
+            format!(
+                "at most {} accounts and {} storage keys per request",
+                MAX_MULTIPROOF_ACCOUNTS, MAX_MULTIPROOF_STORAGE_KEYS
+            ),
+        ))
+        .unwrap();
+    }
+
+    let block = params
+        .get(1)
+        .and_then(|v| v.as_str())
+        .unwrap_or("latest")
+        .to_string();
+
+    debug!(accounts = accounts.len(), %block, include_proof, "dvrpc_getMultiAccountProof");
+
+    // Get consensus proof first to determine which block to query
+    let consensus_proof = if let Some(ref consensus) = state.consensus {
+        match consensus.get_consensus_proof().await {
+            Ok(cp) => Some(cp),
+            Err(e) => {
+                error!("Failed to get consensus proof: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // Use consensus block number if available and user requested "latest"
+    let query_block = if block == "latest" {
+        if let Some(ref cp) = consensus_proof {
+            format!("0x{:x}", cp.block_number)
+        } else {
+            block.clone()
+        }
+    } else {
+        block.clone()
+    };
+
+    let proofs = match state
+        .upstream()
+        .eth_get_proofs(&accounts, &query_block)
+        .await
+    {
+        Ok(p) => p,
+        Err(e) => {
+            error!("Failed to fetch proofs: {}", e);
+            return serde_json::to_value(RpcError::internal(
+                request.id.clone(),
+                format!("Failed to fetch proofs: {}", e),
+            ))
+            .unwrap();
+        }
+    };
+
+    // A proof for another account would verify, but answer the wrong question
+    if let Err(e) = check_requested_proofs(&accounts, &proofs) {
+        error!("Upstream proofs do not match the request: {}", e);
+        state
+            .metrics
+            .record_verification("dvrpc_getMultiAccountProof", VerificationOutcome::Failed);
+        return serde_json::to_value(RpcError::internal(
+            request.id.clone(),
+            format!("Upstream proofs do not match the request: {}", e),
+        ))
+        .unwrap();
+    }
+
+    for proof in &proofs {
+        state
+            .metrics
+            .record_proof_size("dvrpc_getMultiAccountProof", proof.proof_size());
+    }
+
+    let multiproof = MultiProof::from_proofs(&proofs);
+
+    // Verify every account against the consensus state root in one pass
+    let outcomes: Vec<Result<bool, String>> = match consensus_proof {
+        Some(ref cp) => state
+            .proof_generator
+            .verify_multi_account_proof(cp.state_root, &multiproof, &proofs)
+            .into_iter()
+            .map(|r| r.map_err(|e| e.to_string()))
+            .collect(),
+        None => proofs.iter().map(|_| Ok(false)).collect(),
+    };
+
+    let results = proofs
+        .into_iter()
+        .zip(outcomes)
+        .map(|(proof, outcome)| {
+            let recorded = match outcome {
+                Ok(true) => VerificationOutcome::Verified,
+                Ok(false) if consensus_proof.is_none() => VerificationOutcome::Skipped,
+                _ => VerificationOutcome::Failed,
+            };
+            state
+                .metrics
+                .record_verification("dvrpc_getMultiAccountProof", recorded);
+            (proof, outcome)
+        })
+        .map(|(proof, outcome)| match outcome {
+            Ok(false) if consensus_proof.is_some() => {
+                error!(address = %proof.address, "Proof verification failed - data may be tampered");
+                AccountResult::failed(
+                    proof.address,
+                    "Proof verification failed - data integrity check failed",
+                )
+            }
+            Ok(verified) => AccountResult {
+                address: proof.address,
+                verified,
+                balance: Some(proof.balance),
+                nonce: Some(proof.nonce),
+                code_hash: Some(proof.code_hash),
+                storage_hash: Some(proof.storage_hash),
+                storage: proof
+                    .storage_proof
+                    .iter()
+                    .map(|sp| StorageValue {
+                        key: sp.key,
+                        value: sp.value,
+                    })
+                    .collect(),
+                error: None,
+            },
+            Err(e) => {
+                error!(address = %proof.address, "Proof verification error: {}", e);
+                AccountResult::failed(proof.address, format!("Proof verification error: {}", e))
+            }
+        })
+        .collect();
+
+    let result = MultiAccountProof {
+        accounts: results,
+        nodes: include_proof.then(|| multiproof.nodes()),
+    };
+
+    verified_response(state, request, result, consensus_proof, None, include_proof)
+}
+
+/// Error code for transactions rejected by pre-validation, matching the code
+/// execution clients use for the same failures.
+const TX_REJECTED: i32 = -32000;
+
+/// eth_sendRawTransaction - Validate a signed transaction against the proven
+/// sender account before broadcasting it to the upstream. Broadcasting is
+/// forwarding, so it needs `[proxy]` to allow the method.
+pub async fn eth_send_raw_transaction(state: &AppState, request: &RpcRequest) -> serde_json::Value {
+    if !state.config().proxy.allows("eth_sendRawTransaction") {
+        return serde_json::to_value(RpcError::new(
+            request.id.clone(),
+            -32601,
+            "eth_sendRawTransaction is disabled: enable [proxy] to broadcast transactions",
+        ))
+        .unwrap();
+    }
+
+    let raw = match request
+        .params
+        .get(0)
+        .cloned()
+        .map(serde_json::from_value::<Bytes>)
+    {
+        Some(Ok(raw)) => raw,
+        Some(Err(e)) => {
+            return serde_json::to_value(RpcError::invalid_params(
+                request.id.clone(),
+                format!("invalid transaction data: {}", e),
+            ))
+            .unwrap()
+        }
+        None => {
+            return serde_json::to_value(RpcError::invalid_params(
+                request.id.clone(),
+                "missing transaction data",
+            ))
+            .unwrap()
+        }
+    };
+
+    let tx = match decode_raw_transaction(&raw) {
+        Ok(tx) => tx,
+        Err(e) => {
+            return serde_json::to_value(RpcError::invalid_params(
+                request.id.clone(),
+                format!("invalid transaction: {}", e),
+            ))
+            .unwrap()
+        }
+    };
+
+    debug!(hash = %tx.hash, sender = %tx.sender, nonce = tx.nonce, "eth_sendRawTransaction");
+
+    let chain_id = state.config().ethereum.chain_id;
+    match tx.chain_id {
+        Some(id) if id == chain_id => {}
+        Some(id) => {
+            return serde_json::to_value(RpcError::new(
+                request.id.clone(),
+                TX_REJECTED,
+                format!(
+                    "invalid chain id: transaction is for chain {}, node serves chain {}",
+                    id, chain_id
+                ),
+            ))
+            .unwrap()
+        }
+        None => {
+            return serde_json::to_value(RpcError::new(
+                request.id.clone(),
+                TX_REJECTED,
+                "only replay-protected (EIP-155) transactions allowed",
+            ))
+            .unwrap()
+        }
+    }
+
+    let consensus_proof = if let Some(ref consensus) = state.consensus {
+        match consensus.get_consensus_proof().await {
+            Ok(cp) => Some(cp),
+            Err(e) => {
+                error!("Failed to get consensus proof: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let query_block = consensus_proof
+        .as_ref()
+        .map(|cp| format!("0x{:x}", cp.block_number))
+        .unwrap_or_else(|| "latest".to_string());
+
+    let account = match fetch_proof(
+        state,
+        "eth_sendRawTransaction",
+        tx.sender,
+        vec![],
+        &query_block,
+        consensus_proof.as_ref(),
+    )
+    .await
+    {
+        Ok(p) => p,
+        Err(e) => {
+            error!("Failed to fetch proof: {}", e);
+            return serde_json::to_value(RpcError::internal(
+                request.id.clone(),
+                format!("Failed to fetch proof: {}", e),
+            ))
+            .unwrap();
+        }
+    };
+
+    if let Some(ref cp) = consensus_proof {
+        match state
+            .proof_generator
+            .verify_account_proof(cp.state_root, &account)
+        {
+            Ok(true) => {
+                record_verified(state, "eth_sendRawTransaction", &query_block, cp, &account);
+            }
+            Ok(false) => {
+                state
+                    .metrics
+                    .record_verification("eth_sendRawTransaction", VerificationOutcome::Failed);
+                error!("Proof verification failed - data may be tampered");
+                return serde_json::to_value(RpcError::internal(
+                    request.id.clone(),
+                    "Proof verification failed - data integrity check failed",
+                ))
+                .unwrap();
+            }
+            Err(e) => {
+                state
+                    .metrics
+                    .record_verification("eth_sendRawTransaction", VerificationOutcome::Failed);
+                error!("Proof verification error: {}", e);
+                return serde_json::to_value(RpcError::internal(
+                    request.id.clone(),
+                    format!("Proof verification error: {}", e),
+                ))
+                .unwrap();
+            }
+        }
+    } else {
+        state
+            .metrics
+            .record_verification("eth_sendRawTransaction", VerificationOutcome::Skipped);
+    }
+
+    let nonce: u64 = account.nonce.to();
+    if tx.nonce < nonce {
+        return serde_json::to_value(RpcError::new(
+            request.id.clone(),
+            TX_REJECTED,
+            format!(
+                "nonce too low: address {}, tx: {} state: {}",
+                tx.sender, tx.nonce, nonce
+            ),
+        ))
+        .unwrap();
+    }
+
+    let cost = tx.max_cost();
+    if account.balance < cost {
+        return serde_json::to_value(RpcError::new(
+            request.id.clone(),
+            TX_REJECTED,
+            format!(
+                "insufficient funds for gas * price + value: address {} have {} want {}",
+                tx.sender, account.balance, cost
+            ),
+        ))
+        .unwrap();
+    }
+
+    let response = match state
+        .upstream()
+        .forward("eth_sendRawTransaction", &request.params)
+        .await
+    {
+        Ok(r) => r,
+        Err(e) => {
+            error!("Failed to broadcast transaction: {}", e);
+            return serde_json::to_value(RpcError::internal(
+                request.id.clone(),
+                format!("Failed to broadcast transaction: {}", e),
+            ))
+            .unwrap();
+        }
+    };
+
+    if let Some(error) = response.get("error") {
+        return serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": request.id,
+            "error": error,
+        });
+    }
+
+    // The hash is ours; an upstream reporting another one is misbehaving
+    let upstream_hash = response
+        .get("result")
+        .and_then(|r| serde_json::from_value::<B256>(r.clone()).ok());
+    if upstream_hash != Some(tx.hash) {
+        warn!(hash = %tx.hash, ?upstream_hash, "Upstream returned a different transaction hash");
+    }
+
+    serde_json::to_value(RpcResponse::new(request.id.clone(), tx.hash)).unwrap()
+}
+
+/// dvrpc_getTokenBalance - Prove an ERC-20 balance from token storage.
+pub async fn dvrpc_get_token_balance(
+    state: &AppState,
+    request: &RpcRequest,
+    include_proof: bool,
+) -> serde_json::Value {
+    let (addresses, block) = match parse_addresses_block(&request.params, 2) {
+        Ok(v) => v,
+        Err(e) => {
+            return serde_json::to_value(RpcError::invalid_params(request.id.clone(), e)).unwrap()
+        }
+    };
+    let (token, owner) = (addresses[0], addresses[1]);
+
+    debug!(%token, %owner, %block, include_proof, "dvrpc_getTokenBalance");
+
+    prove_token_value(
+        state,
+        request,
+        include_proof,
+        TokenQuery {
+            method: "dvrpc_getTokenBalance",
+            token,
+            field: TokenField::Balance,
+            owner,
+            spender: None,
+        },
+        &block,
+    )
+    .await
+}
+
+/// dvrpc_getTokenAllowance - Prove an ERC-20 allowance from token storage.
+pub async fn dvrpc_get_token_allowance(
+    state: &AppState,
+    request: &RpcRequest,
+    include_proof: bool,
+) -> serde_json::Value {
+    let (addresses, block) = match parse_addresses_block(&request.params, 3) {
+        Ok(v) => v,
+        Err(e) => {
+            return serde_json::to_value(RpcError::invalid_params(request.id.clone(), e)).unwrap()
+        }
+    };
+    let (token, owner, spender) = (addresses[0], addresses[1], addresses[2]);
+
+    debug!(%token, %owner, %spender, %block, include_proof, "dvrpc_getTokenAllowance");
+
+    prove_token_value(
+        state,
+        request,
+        include_proof,
+        TokenQuery {
+            method: "dvrpc_getTokenAllowance",
+            token,
+            field: TokenField::Allowance,
+            owner,
+            spender: Some(spender),
+        },
+        &block,
+    )
+    .await
+}
+
+/// Parse `count` leading addresses followed by an optional block tag.
+fn parse_addresses_block(
+    params: &serde_json::Value,
+    count: usize,
+) -> Result<(Vec<Address>, String), String> {
+    let params = params
+        .as_array()
+        .ok_or_else(|| "params must be an array".to_string())?;
+
+    if params.len() < count {
+        return Err("missing parameters".to_string());
+    }
+
+    let addresses = params[..count]
+        .iter()
+        .map(|v| serde_json::from_value(v.clone()).map_err(|e| format!("invalid address: {}", e)))
+        .collect::<Result<Vec<Address>, String>>()?;
+
+    let block = params
+        .get(count)
+        .and_then(|v| v.as_str())
+        .unwrap_or("latest")
+        .to_string();
+
+    Ok((addresses, block))
+}
+
+/// A token mapping entry to prove.
+struct TokenQuery {
+    method: &'static str,
+    token: Address,
+    field: TokenField,
+    owner: Address,
+    /// Set for allowances.
+    spender: Option<Address>,
+}
+
+impl TokenQuery {
+    /// Storage key of the entry for a given mapping slot.
+    fn key(&self, slot: MappingSlot) -> B256 {
+        match self.spender {
+            Some(spender) => slot.nested_key(self.owner, spender),
+            None => slot.key(self.owner),
+        }
+    }
+
+    /// Getter call returning the same value, used to detect the mapping slot.
+    fn calldata(&self) -> Bytes {
+        match self.spender {
+            Some(spender) => allowance_call(self.owner, spender),
+            None => balance_of_call(self.owner),
+        }
+    }
+}
+
+/// Prove a token mapping entry, detecting the token's mapping slot on first use.
+async fn prove_token_value(
+    state: &AppState,
+    request: &RpcRequest,
+    include_proof: bool,
+    query: TokenQuery,
+    block: &str,
+) -> serde_json::Value {
+    // Get consensus proof first to determine which block to query
+    let consensus_proof = if let Some(ref consensus) = state.consensus {
+        match consensus.get_consensus_proof().await {
+            Ok(cp) => Some(cp),
+            Err(e) => {
+                error!("Failed to get consensus proof: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // Use consensus block number if available and user requested "latest"
+    let query_block = match consensus_proof {
+        Some(ref cp) if block == "latest" => format!("0x{:x}", cp.block_number),
+        _ => block.to_string(),
+    };
+
+    let known = state.token_slots.get(query.token, query.field);
+    let candidates = match known {
+        Some(slot) => vec![slot],
+        None => MappingSlot::candidates(),
+    };
+    let keys: Vec<B256> = candidates.iter().map(|slot| query.key(*slot)).collect();
+
+    let proof_data = match fetch_proof(
+        state,
+        query.method,
+        query.token,
+        keys.clone(),
+        &query_block,
+        consensus_proof.as_ref(),
+    )
+    .await
+    {
+        Ok(p) => p,
+        Err(e) => {
+            error!("Failed to fetch proof: {}", e);
+            return serde_json::to_value(RpcError::internal(
+                request.id.clone(),
+                format!("Failed to fetch proof: {}", e),
+            ))
+            .unwrap();
+        }
+    };
+
+    // Verify the account and every probed slot against the consensus state root
+    if let Some(ref cp) = consensus_proof {
+        match state
+            .proof_generator
+            .verify_complete_proof(cp.state_root, &proof_data)
+        {
+            Ok(true) => {
+                state
+                    .metrics
+                    .record_verification(query.method, VerificationOutcome::Verified);
+            }
+            Ok(false) => {
+                state
+                    .metrics
+                    .record_verification(query.method, VerificationOutcome::Failed);
+                error!("Proof verification failed - data may be tampered");
+                return serde_json::to_value(RpcError::internal(
+                    request.id.clone(),
+                    "Proof verification failed - data integrity check failed",
+                ))
+                .unwrap();
+            }
+            Err(e) => {
+                state
+                    .metrics
+                    .record_verification(query.method, VerificationOutcome::Failed);
+                error!("Proof verification error: {}", e);
+                return serde_json::to_value(RpcError::internal(
+                    request.id.clone(),
+                    format!("Proof verification error: {}", e),
+                ))
+                .unwrap();
+            }
+        }
+    } else {
+        state
+            .metrics
+            .record_verification(query.method, VerificationOutcome::Skipped);
+    }
+
+    let value = match known {
+        Some(_) => proven(&proof_data, keys[0]),
+        None => match detect_slot(state, &query, &query_block, &candidates, &proof_data).await {
+            Ok(slot) => {
+                debug!(
+                    token = %query.token,
+                    field = query.field.as_str(),
+                    ?slot,
+                    "Detected token mapping slot"
+                );
+                state.token_slots.insert(query.token, query.field, slot);
+                proven(&proof_data, query.key(slot))
+            }
+            Err(e) => {
+                return serde_json::to_value(RpcError::new(request.id.clone(), -32000, e)).unwrap()
+            }
+        },
+    };
+
+    let Some(value) = value else {
+        return serde_json::to_value(RpcError::internal(
+            request.id.clone(),
+            "Upstream proof is missing the requested storage slot",
+        ))
+        .unwrap();
+    };
+
+    verified_response(
+        state,
+        request,
+        value,
+        consensus_proof,
+        Some(proof_data),
+        include_proof,
+    )
+}
+
+/// Proven value of a storage key in `proof`.
+pub(super) fn proven(proof: &ProofData, key: B256) -> Option<U256> {
+    proof
+        .storage_proof
+        .iter()
+        .find(|sp| sp.key == key)
+        .map(|sp| sp.value)
+}
+
+/// Find the candidate slot whose proven value matches what the token's own
+/// getter returns. The getter result is unverified and only picks the slot;
+/// the value served is always the proven one. A zero value, or one held in
+/// more than one candidate, could pick the wrong slot for every later
+/// request, so neither is used to detect it.
+async fn detect_slot(
+    state: &AppState,
+    query: &TokenQuery,
+    query_block: &str,
+    candidates: &[MappingSlot],
+    proof_data: &ProofData,
+) -> Result<MappingSlot, String> {
+    let output = state
+        .upstream()
+        .eth_call(query.token, query.calldata(), query_block)
+        .await
+        .map_err(|e| format!("Failed to call token: {}", e))?;
+    if output.len() != 32 {
+        return Err(format!(
+            "{} is not an ERC-20 token: unexpected {} getter output",
+            query.token,
+            query.field.as_str()
+        ));
+    }
+    let expected = U256::from_be_slice(&output);
+
+    if expected.is_zero() {
+        return Err(format!(
+            "Cannot detect the {} slot of {} from a zero value; configure it under [[tokens]]",
+            query.field.as_str(),
+            query.token
+        ));
+    }
+
+    // A value held in more than one candidate cannot pick the slot
+    let mut matches = candidates
+        .iter()
+        .filter(|slot| proven(proof_data, query.key(**slot)) == Some(expected));
+    match (matches.next(), matches.next()) {
+        (Some(slot), None) => Ok(*slot),
+        (Some(_), Some(_)) => Err(format!(
+            "The {} slot of {} is ambiguous; configure it under [[tokens]]",
+            query.field.as_str(),
+            query.token
+        )),
+        (None, _) => Err(format!(
+            "No {} mapping found in the first {} slots of {}; configure it under [[tokens]]",
+            query.field.as_str(),
+            MAX_PROBE_SLOT + 1,
+            query.token
+        )),
+    }
+}
+
+/// dvrpc_resolveName - Resolve an ENS name from proven registry and resolver storage.
+pub async fn dvrpc_resolve_name(state: &AppState, request: &RpcRequest) -> serde_json::Value {
+    let params = request.params.as_array();
+    let name = match params.and_then(|p| p.first()).and_then(|v| v.as_str()) {
+        Some(name) => match normalize_name(name) {
+            Ok(name) => name,
+            Err(e) => {
+                return serde_json::to_value(RpcError::invalid_params(request.id.clone(), e))
+                    .unwrap()
+            }
+        },
+        None => {
+            return serde_json::to_value(RpcError::invalid_params(
+                request.id.clone(),
+                "missing name parameter",
+            ))
+            .unwrap()
+        }
+    };
+    let block = params
+        .and_then(|p| p.get(1))
+        .and_then(|v| v.as_str())
+        .unwrap_or("latest");
+
+    debug!(%name, %block, "dvrpc_resolveName");
+
+    let resolver = ens_resolver(state, "dvrpc_resolveName", block).await;
+    let result = resolver.resolve_name(&name).await;
+    ens_response(state, request, &resolver, result)
+}
+
+/// dvrpc_lookupAddress - Primary ENS name of an address, checked to resolve back.
+pub async fn dvrpc_lookup_address(state: &AppState, request: &RpcRequest) -> serde_json::Value {
+    let (address, block) = match parse_address_block(&request.params) {
+        Ok(v) => v,
+        Err(e) => {
+            return serde_json::to_value(RpcError::invalid_params(request.id.clone(), e)).unwrap()
+        }
+    };
+
+    debug!(%address, %block, "dvrpc_lookupAddress");
+
+    let resolver = ens_resolver(state, "dvrpc_lookupAddress", &block).await;
+    let result = resolver.lookup_address(address).await;
+    ens_response(state, request, &resolver, result)
+}
+
+/// Lowercase a name and drop a trailing dot. Full UTS-46 normalization is left
+/// to the client.
+fn normalize_name(name: &str) -> Result<String, String> {
+    let name = name.trim().trim_end_matches('.').to_lowercase();
+    if name.is_empty() || name.split('.').any(str::is_empty) {
+        return Err(format!("invalid ENS name: {:?}", name));
+    }
+    Ok(name)
+}
+
+/// A resolver pinned to the consensus-verified block when querying "latest".
+async fn ens_resolver<'a>(state: &'a AppState, method: &'static str, block: &str) -> Resolver<'a> {
+    let consensus_proof = if let Some(ref consensus) = state.consensus {
+        match consensus.get_consensus_proof().await {
+            Ok(cp) => Some(cp),
+            Err(e) => {
+                error!("Failed to get consensus proof: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let query_block = match consensus_proof {
+        Some(ref cp) if block == "latest" => format!("0x{:x}", cp.block_number),
+        _ => block.to_string(),
+    };
+
+    Resolver::new(state, method, consensus_proof, query_block)
+}
+
+fn ens_response(
+    state: &AppState,
+    request: &RpcRequest,
+    resolver: &Resolver<'_>,
+    result: eyre::Result<EnsResolution>,
+) -> serde_json::Value {
+    let method = request.method.as_str();
+    match result {
+        Ok(resolution) => {
+            // Only a fully proven answer is attested
+            let proven =
+                resolution.resolver_proven && resolution.record_proven && !resolution.offchain;
+            let outcome = if proven {
+                VerificationOutcome::Verified
+            } else {
+                VerificationOutcome::Skipped
+            };
+            state.metrics.record_verification(method, outcome);
+            let consensus_proof = resolver.consensus_proof().filter(|_| proven).cloned();
+            verified_response(state, request, resolution, consensus_proof, None, false)
+        }
+        Err(e) => {
+            error!("{} failed: {}", method, e);
+            serde_json::to_value(RpcError::internal(request.id.clone(), e.to_string())).unwrap()
+        }
+    }
+}
+
+/// Options of `dvrpc_waitForTransaction`.
+#[derive(Deserialize, Default)]
+struct WaitOptions {
+    /// Wait for the inclusion block to be finalized, not just verified.
+    #[serde(default)]
+    finalized: bool,
+    /// Seconds to wait, capped by the method's request timeout.
+    timeout: Option<u64>,
+}
+
+/// dvrpc_waitForTransaction - Wait until a transaction is proven in a verified
+/// block, returning its current inclusion status when the wait runs out.
+pub async fn dvrpc_wait_for_transaction(
+    state: &AppState,
+    request: &RpcRequest,
+) -> serde_json::Value {
+    let hash = match request
+        .params
+        .get(0)
+        .cloned()
+        .map(serde_json::from_value::<B256>)
+    {
+        Some(Ok(hash)) => hash,
+        Some(Err(e)) => {
+            return serde_json::to_value(RpcError::invalid_params(
+                request.id.clone(),
+                format!("invalid transaction hash: {}", e),
+            ))
+            .unwrap()
+        }
+        None => {
+            return serde_json::to_value(RpcError::invalid_params(
+                request.id.clone(),
+                "missing transaction hash",
+            ))
+            .unwrap()
+        }
+    };
+
+    let options = match request.params.get(1).cloned() {
+        Some(value) => match serde_json::from_value::<WaitOptions>(value) {
+            Ok(options) => options,
+            Err(e) => {
+                return serde_json::to_value(RpcError::invalid_params(
+                    request.id.clone(),
+                    format!("invalid options: {}", e),
+                ))
+                .unwrap()
+            }
+        },
+        None => WaitOptions::default(),
+    };
+
+    // Return before the request timeout would turn the wait into an error
+    let limit = state
+        .config()
+        .server
+        .timeout_for("dvrpc_waitForTransaction")
+        .saturating_sub(Duration::from_secs(1));
+    let wait = options
+        .timeout
+        .map(|secs| Duration::from_secs(secs).min(limit))
+        .unwrap_or(limit);
+    let deadline = Instant::now() + wait;
+
+    debug!(%hash, finalized = options.finalized, ?wait, "dvrpc_waitForTransaction");
+
+    let mut watch = InclusionWatch::new(hash);
+    loop {
+        let status = match watch.poll(state).await {
+            Ok(status) => status,
+            Err(e) => {
+                error!(%hash, "Inclusion check failed: {}", e);
+                return serde_json::to_value(RpcError::internal(
+                    request.id.clone(),
+                    format!("Inclusion check failed: {}", e),
+                ))
+                .unwrap();
+            }
+        };
+
+        let done = match status.state {
+            InclusionState::Pending => false,
+            InclusionState::Included => !options.finalized,
+            InclusionState::Finalized => true,
+        };
+        if done || Instant::now() + POLL_INTERVAL > deadline {
+            return serde_json::to_value(RpcResponse::new(request.id.clone(), status)).unwrap();
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Forward an unsupported method to the upstream. The response is returned
+/// as-is with `"verified": false`, since nothing in it can be checked.
+pub async fn passthrough(state: &AppState, request: &RpcRequest) -> serde_json::Value {
+    match state
+        .upstream()
+        .forward(&request.method, &request.params)
+        .await
+    {
+        Ok(mut response) => {
+            if let Some(object) = response.as_object_mut() {
+                object.insert("id".to_string(), request.id.clone());
+                object.insert("verified".to_string(), serde_json::Value::Bool(false));
+            }
+            response
+        }
+        Err(e) => {
+            error!(method = %request.method, "Passthrough failed: {}", e);
+            serde_json::to_value(RpcError::internal(
+                request.id.clone(),
+                format!("Upstream request failed: {}", e),
+            ))
+            .unwrap()
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Transaction inclusion proven against light-client-verified headers.
+//!
+//! The upstream only tells us where to look. A transaction counts as included
+//! once its block's transactions and receipts, as served by the upstream,
+//! rebuild to the roots of the header the light client verified at that height.
+
+use alloy_primitives::{keccak256, B256, U64};
+use alloy_rlp::{Encodable, Header};
+use eyre::{bail, Result};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use super::AppState;
+use crate::consensus::{ConsensusClient, VerifiedHeader};
+use crate::metrics::VerificationOutcome;
+use crate::proof::trie::ordered_trie_root;
+use crate::types::{InclusionState, InclusionStatus, ReceiptData};
+
+/// How often verified heads are re-checked while watching a transaction.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(4);
+
+/// Follows one transaction across verified heads, noticing when the block it
+/// was proven in is replaced.
+pub struct InclusionWatch {
+    hash: B256,
+    included: Option<(VerifiedHeader, ReceiptData)>,
+    reorgs: u32,
+}
+
+impl InclusionWatch {
+    pub fn new(hash: B256) -> Self {
+        Self {
+            hash,
+            included: None,
+            reorgs: 0,
+        }
+    }
+
+    /// Re-check the transaction against the current verified chain.
+    ///
+    /// Fails if the upstream serves block contents that do not match the
+    /// verified roots.
+    pub async fn poll(&mut self, state: &AppState) -> Result<InclusionStatus> {
+        let Some(ref consensus) = state.consensus else {
+            bail!("consensus verification is disabled");
+        };
+
+        // A block already proven only needs its header to still be canonical
+        if let Some((ref header, _)) = self.included {
+            if consensus.get_header(header.number).await?.as_ref() != Some(header) {
+                warn!(hash = %self.hash, block = header.number, "Inclusion block reorged out");
+                self.included = None;
+                self.reorgs += 1;
+            }
+        }
+
+        if self.included.is_none() {
+            self.included = find_inclusion(state, consensus, self.hash).await?;
+        }
+
+        let Some((ref header, ref receipt)) = self.included else {
+            return Ok(self.status(InclusionState::Pending));
+        };
+
+        let status = consensus.status().await?;
+        let inclusion = if header.number <= status.finalized_block {
+            InclusionState::Finalized
+        } else {
+            InclusionState::Included
+        };
+
+        Ok(InclusionStatus {
+            block_number: Some(U64::from(header.number)),
+            block_hash: Some(header.hash),
+            confirmations: Some(status.head_block.saturating_sub(header.number) + 1),
+            receipt: Some(receipt.clone()),
+            ..self.status(inclusion)
+        })
+    }
+
+    fn status(&self, state: InclusionState) -> InclusionStatus {
+        InclusionStatus {
+            transaction_hash: self.hash,
+            state,
+            block_number: None,
+            block_hash: None,
+            confirmations: None,
+            receipt: None,
+            reorgs: self.reorgs,
+        }
+    }
+}
+
+/// Locate the transaction via the upstream and prove it, returning `None`
+/// while it is not in a verified block.
+async fn find_inclusion(
+    state: &AppState,
+    consensus: &ConsensusClient,
+    hash: B256,
+) -> Result<Option<(VerifiedHeader, ReceiptData)>> {
+    let Some(receipt) = state.upstream().eth_get_transaction_receipt(hash).await? else {
+        return Ok(None);
+    };
+
+    let number = receipt.block_number.to::<u64>();
+    let Some(header) = consensus.get_header(number).await? else {
+        debug!(%hash, number, "Receipt block not verified yet");
+        return Ok(None);
+    };
+    if header.hash != receipt.block_hash {
+        debug!(%hash, number, "Upstream receipt is for a non-canonical block");
+        return Ok(None);
+    }
+
+    let receipts = state.upstream().eth_get_block_receipts(header.hash).await?;
+    let transactions = state
+        .upstream()
+        .eth_get_raw_transactions(header.hash, receipts.len())
+        .await?;
+
+    let index = receipt.transaction_index.to::<usize>();
+    let result = verify_block_contents(&header, &transactions, &receipts, index, hash);
+    let outcome = match result {
+        Ok(()) => VerificationOutcome::Verified,
+        Err(_) => VerificationOutcome::Failed,
+    };
+    state
+        .metrics
+        .record_verification("dvrpc_waitForTransaction", outcome);
+    result?;
+
+    Ok(Some((header, receipts[index].clone())))
+}
+
+/// Check block contents against a verified header and that the transaction
+/// at `index` is `hash`.
+fn verify_block_contents(
+    header: &VerifiedHeader,
+    transactions: &[alloy_primitives::Bytes],
+    receipts: &[ReceiptData],
+    index: usize,
+    hash: B256,
+) -> Result<()> {
+    let encoded: Vec<Vec<u8>> = transactions.iter().map(|tx| tx.to_vec()).collect();
+    if ordered_trie_root(&encoded) != header.transactions_root {
+        bail!("transactions do not match the verified transactions root");
+    }
+
+    let encoded: Vec<Vec<u8>> = receipts.iter().map(encode_receipt).collect();
+    if ordered_trie_root(&encoded) != header.receipts_root {
+        bail!("receipts do not match the verified receipts root");
+    }
+
+    match transactions.get(index) {
+        Some(tx) if keccak256(tx) == hash => {}
+        _ => bail!("transaction not at the index reported by the upstream"),
+    }
+    if receipts[index].transaction_hash != hash {
+        bail!("receipt does not belong to the transaction");
+    }
+
+    Ok(())
+}
+
+/// EIP-2718 encoding of a receipt, as stored in the receipts trie.
+pub fn encode_receipt(receipt: &ReceiptData) -> Vec<u8> {
+    let status = receipt.status.to::<u64>() != 0;
+    let cumulative_gas_used = receipt.cumulative_gas_used.to::<u64>();
+    let logs: Vec<Vec<u8>> = receipt
+        .logs
+        .iter()
+        .map(|log| {
+            let mut payload = Vec::new();
+            log.address.encode(&mut payload);
+            log.topics.encode(&mut payload);
+            log.data.encode(&mut payload);
+            list(payload)
+        })
+        .collect();
+
+    let mut payload = Vec::new();
+    status.encode(&mut payload);
+    cumulative_gas_used.encode(&mut payload);
+    receipt.logs_bloom.encode(&mut payload);
+    payload.extend(list(logs.concat()));
+
+    let tx_type = receipt.tx_type.to::<u8>();
+    let mut out = if tx_type == 0 { vec![] } else { vec![tx_type] };
+    out.extend(list(payload));
+    out
+}
+
+fn list(payload: Vec<u8>) -> Vec<u8> {
+    let mut out = Vec::new();
+    Header {
+        list: true,
+        payload_length: payload.len(),
+    }
+    .encode(&mut out);
+    out.extend(payload);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::LogData;
+    use alloy_primitives::{Address, Bytes};
+
+    fn receipt(hash: B256, index: u64) -> ReceiptData {
+        ReceiptData {
+            tx_type: U64::from(2),
+            transaction_hash: hash,
+            transaction_index: U64::from(index),
+            block_hash: B256::ZERO,
+            block_number: U64::from(1),
+            status: U64::from(1),
+            cumulative_gas_used: U64::from(21_000 * (index + 1)),
+            gas_used: U64::from(21_000),
+            logs_bloom: Bytes::from(vec![0u8; 256]),
+            logs: vec![LogData {
+                address: Address::repeat_byte(0xaa),
+                topics: vec![B256::repeat_byte(0x01)],
+                data: Bytes::from_static(&[1, 2, 3]),
+            }],
+            contract_address: None,
+        }
+    }
+
+    fn block(count: u8) -> (VerifiedHeader, Vec<Bytes>, Vec<ReceiptData>) {
+        let transactions: Vec<Bytes> = (0..count)
+            .map(|i| Bytes::from(vec![0x02, 0xc1, i]))
+            .collect();
+        let receipts: Vec<ReceiptData> = transactions
+            .iter()
+            .enumerate()
+            .map(|(i, tx)| receipt(keccak256(tx), i as u64))
+            .collect();
+        let header = VerifiedHeader {
+            number: 1,
+            hash: B256::repeat_byte(0xbb),
+            state_root: B256::repeat_byte(0xcc),
+            transactions_root: ordered_trie_root(
+                &transactions.iter().map(|t| t.to_vec()).collect::<Vec<_>>(),
+            ),
+            receipts_root: ordered_trie_root(
+                &receipts.iter().map(encode_receipt).collect::<Vec<_>>(),
+            ),
+            timestamp: 0,
+        };
+        (header, transactions, receipts)
+    }
+
+    #[test]
+    fn test_verify_block_contents() {
+        let (header, transactions, receipts) = block(20);
+        let hash = keccak256(&transactions[7]);
+        assert!(verify_block_contents(&header, &transactions, &receipts, 7, hash).is_ok());
+
+        // Wrong index for the transaction
+        assert!(verify_block_contents(&header, &transactions, &receipts, 8, hash).is_err());
+
+        // A receipt with a flipped status no longer matches the receipts root
+        let mut tampered = receipts.clone();
+        tampered[7].status = U64::ZERO;
+        assert!(verify_block_contents(&header, &transactions, &tampered, 7, hash).is_err());
+
+        // A transaction missing from the block
+        let mut dropped = transactions.clone();
+        dropped.pop();
+        assert!(verify_block_contents(&header, &dropped, &receipts, 7, hash).is_err());
+    }
+
+    #[test]
+    fn test_encode_legacy_receipt_has_no_type_prefix() {
+        let mut legacy = receipt(B256::ZERO, 0);
+        legacy.tx_type = U64::ZERO;
+        let encoded = encode_receipt(&legacy);
+        assert!(encoded[0] >= 0xc0);
+        assert_eq!(&encode_receipt(&receipt(B256::ZERO, 0))[1..], &encoded[..]);
+    }
+}
@@ -0,0 +1,117 @@
+//! JSON-RPC over a Unix domain socket for co-located clients.
+//!
+//! Messages are JSON objects or arrays written back to back, as with geth's
+//! IPC endpoint. Each connection is served like a WebSocket connection, so
+//! subscriptions work the same way; responses and notifications are written
+//! as one JSON value per line.
+
+use eyre::{Context, Result};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{UnixListener, UnixStream};
+use tracing::{debug, warn};
+
+use super::ws::Connection;
+use super::{key_named, AppState};
+use crate::framing::JsonFrames;
+use crate::types::RpcError;
+
+/// Bind the socket at `path` and set its file mode. A socket left behind by
+/// a node that is no longer running is replaced.
+pub fn bind_ipc(path: &Path, permissions: u32) -> Result<UnixListener> {
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            eyre::bail!("{} exists and is not a socket", path.display());
+        }
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            eyre::bail!("IPC socket {} is already in use", path.display());
+        }
+        std::fs::remove_file(path)
+            .wrap_err_with(|| format!("Failed to remove stale IPC socket {}", path.display()))?;
+    }
+
+    let listener = UnixListener::bind(path)
+        .wrap_err_with(|| format!("Failed to bind IPC socket {}", path.display()))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(permissions))
+        .wrap_err_with(|| format!("Failed to set permissions of {}", path.display()))?;
+    Ok(listener)
+}
+
+/// Accept IPC connections until the task is dropped.
+pub async fn serve_ipc(state: Arc<AppState>, listener: UnixListener) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(serve_connection(state.clone(), stream));
+            }
+            Err(e) => warn!("Failed to accept IPC connection: {}", e),
+        }
+    }
+}
+
+async fn serve_connection(state: Arc<AppState>, stream: UnixStream) {
+    let config = state.config();
+    let api_key = config
+        .server
+        .ipc_identity
+        .as_deref()
+        .and_then(|name| key_named(&config, name));
+    let max_message_size = config.server.max_body_size;
+    let (mut connection, mut pending) = Connection::new(state, api_key, false);
+
+    let (mut reader, mut writer) = stream.into_split();
+    let mut frames = JsonFrames::new();
+    let mut chunk = [0u8; 8192];
+
+    'connection: loop {
+        // Answer every complete message before reading more
+        loop {
+            let outgoing = match frames.next_frame() {
+                Ok(Some(message)) => connection.handle(&String::from_utf8_lossy(&message)).await,
+                Ok(None) => break,
+                Err(e) => {
+                    // The stream cannot be resynchronized after malformed JSON
+                    let error = RpcError::parse_error(e.to_string());
+                    let _ = write(&mut writer, serde_json::to_value(error).unwrap()).await;
+                    break 'connection;
+                }
+            };
+            if write(&mut writer, outgoing).await.is_err() {
+                break 'connection;
+            }
+        }
+
+        if frames.len() > max_message_size {
+            warn!(
+                size = frames.len(),
+                "IPC message too large, closing connection"
+            );
+            break;
+        }
+
+        tokio::select! {
+            read = reader.read(&mut chunk) => match read {
+                Ok(0) | Err(_) => break,
+                Ok(read) => frames.extend(&chunk[..read]),
+            },
+            Some(notification) = pending.recv() => {
+                if write(&mut writer, notification).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    debug!("IPC connection closed");
+}
+
+async fn write(
+    writer: &mut tokio::net::unix::OwnedWriteHalf,
+    value: serde_json::Value,
+) -> std::io::Result<()> {
+    let mut line = serde_json::to_vec(&value)?;
+    line.push(b'\n');
+    writer.write_all(&line).await
+}
@@ -1,34 +1,101 @@
 //! JSON-RPC server with optional proof responses.
 
 use axum::{
-    extract::State,
-    http::{HeaderMap, StatusCode},
-    response::IntoResponse,
+    body::Body,
+    extract::{rejection::JsonRejection, DefaultBodyLimit, Path, Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::post,
-    Json, Router,
+    Extension, Json, Router,
 };
-use eyre::Result;
+use eyre::{Context, Result};
+use http_body_util::Limited;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use hyper_util::server::graceful::GracefulShutdown;
+use hyper_util::service::TowerToHyperService;
+use std::hash::{BuildHasher, RandomState};
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+use tower::util::Either;
+use tower_http::add_extension::AddExtension;
 use tower_http::cors::{Any, CorsLayer};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, info_span, warn, Instrument};
 
+use crate::access;
+use crate::attestation::Attestor;
+use crate::auth::{AuthError, Authenticator};
+use crate::chain::{self, ChainMismatch};
 use crate::config::Config;
 use crate::consensus::ConsensusClient;
-use crate::proof::ProofGenerator;
-use crate::types::{RpcError, RpcRequest};
+use crate::metrics::Metrics;
+use crate::p2p::{self, GossipMessage, P2pHandle};
+use crate::proof::{ProofCache, ProofGenerator};
+use crate::telemetry;
+use crate::tls::{self, ClientCertificate, TlsTerminator};
+use crate::token::TokenSlots;
+use crate::types::{ConsensusStatusInfo, NodeStatus, RpcError, RpcRequest, UpstreamStatusInfo};
 use crate::upstream::UpstreamClient;
 
+mod ens;
+mod gossip;
 mod handlers;
+mod inclusion;
+#[cfg(unix)]
+mod ipc;
+mod peer;
+mod ws;
+
+#[cfg(unix)]
+pub use ipc::{bind_ipc, serve_ipc};
+
+/// How often the verified and upstream heads are compared.
+const HEAD_TRACKING_INTERVAL: Duration = Duration::from_secs(12);
+
+/// How often the upstream and consensus RPC chains are checked again.
+const CHAIN_CHECK_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Header set to "false" on responses passed through without verification.
+const VERIFIED_HEADER: HeaderName = HeaderName::from_static("x-dvrpc-verified");
+
+/// Header carrying the request ID, taken from the client or generated.
+const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// How long a client has to complete the TLS handshake.
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest client-supplied request ID that is kept.
+const MAX_REQUEST_ID_LEN: usize = 64;
 
 /// Shared application state.
 pub struct AppState {
-    pub config: Config,
+    /// Current config, replaced on reload.
+    config: RwLock<Arc<Config>>,
     pub consensus: Option<ConsensusClient>,
     pub proof_generator: ProofGenerator,
-    pub upstream: UpstreamClient,
+    pub proof_cache: ProofCache,
+    upstream: RwLock<Arc<UpstreamClient>>,
+    pub metrics: Arc<Metrics>,
+    auth: RwLock<Arc<Authenticator>>,
+    pub token_slots: TokenSlots,
+    /// Signs verified responses when an attestation key is configured.
+    pub attestor: Option<Attestor>,
+    /// Shares verified headers and proofs with peers when P2P is enabled.
+    pub p2p: Option<P2pHandle>,
+    /// Why the upstream or consensus RPC was last found on another chain.
+    pub chain_error: Mutex<Option<String>>,
+    /// Reads the config again for reloads.
+    loader: Option<ConfigLoader>,
+    /// RPC requests being served, limited by `server.max_connections`.
+    in_flight: AtomicUsize,
 }
 
+/// Loads the config the node was started with, CLI overrides included.
+pub type ConfigLoader = Arc<dyn Fn() -> Result<Config> + Send + Sync>;
+
 /// Check if X-DVRPC-Proof header is set to true.
 fn wants_proof(headers: &HeaderMap) -> bool {
     headers
@@ -38,32 +105,359 @@ fn wants_proof(headers: &HeaderMap) -> bool {
         .unwrap_or(false)
 }
 
+/// The client's request ID if it is printable and short, otherwise a new one.
+fn request_id(headers: &HeaderMap) -> String {
+    headers
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LEN
+                && id.bytes().all(|b| b.is_ascii_graphic())
+        })
+        .map(str::to_string)
+        .unwrap_or_else(new_request_id)
+}
+
+/// A request ID unique to this process: a random per-process prefix and a
+/// sequence number.
+fn new_request_id() -> String {
+    static PREFIX: OnceLock<u32> = OnceLock::new();
+    static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+    let prefix = PREFIX.get_or_init(|| RandomState::new().hash_one(std::process::id()) as u32);
+    format!(
+        "{:08x}{:08x}",
+        prefix,
+        SEQUENCE.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
 /// Main RPC handler - routes to method-specific handlers.
 async fn rpc_handler(
     State(state): State<Arc<AppState>>,
+    client: Option<Extension<ClientCertificate>>,
+    headers: HeaderMap,
+    request: Result<Json<RpcRequest>, JsonRejection>,
+) -> impl IntoResponse {
+    let api_key = api_key(&state, client.as_deref(), &headers, None);
+    handle_rpc(state, headers, api_key, request).await
+}
+
+/// RPC handler for requests carrying the API key in the URL path.
+async fn rpc_key_handler(
+    State(state): State<Arc<AppState>>,
+    client: Option<Extension<ClientCertificate>>,
+    Path(path_key): Path<String>,
     headers: HeaderMap,
-    Json(request): Json<RpcRequest>,
+    request: Result<Json<RpcRequest>, JsonRejection>,
 ) -> impl IntoResponse {
+    let api_key = api_key(&state, client.as_deref(), &headers, Some(path_key));
+    handle_rpc(state, headers, api_key, request).await
+}
+
+/// The API key a request is made with: the key mapped to the client
+/// certificate, then the key in the header, then the key in the URL path.
+fn api_key(
+    state: &AppState,
+    client: Option<&ClientCertificate>,
+    headers: &HeaderMap,
+    path_key: Option<String>,
+) -> Option<String> {
+    let config = state.config();
+    let identity = client.and_then(|client| {
+        let name = config
+            .server
+            .tls
+            .as_ref()?
+            .identities
+            .get(&client.common_name)?;
+        key_named(&config, name)
+    });
+    if identity.is_some() {
+        return identity;
+    }
+
+    headers
+        .get(state.auth().header())
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .or(path_key)
+}
+
+/// The API key of the `[[auth.keys]]` entry called `name`.
+fn key_named(config: &Config, name: &str) -> Option<String> {
+    config
+        .auth
+        .keys
+        .iter()
+        .find(|key| key.name == name)
+        .map(|key| key.key.clone())
+}
+
+async fn handle_rpc(
+    state: Arc<AppState>,
+    headers: HeaderMap,
+    api_key: Option<String>,
+    request: Result<Json<RpcRequest>, JsonRejection>,
+) -> Response {
+    let id = request_id(&headers);
+    let span = info_span!("request", id = %id);
+    telemetry::set_parent(&span, &headers);
+    let mut response = handle_request(state, headers, api_key, request)
+        .instrument(span)
+        .await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+async fn handle_request(
+    state: Arc<AppState>,
+    headers: HeaderMap,
+    api_key: Option<String>,
+    request: Result<Json<RpcRequest>, JsonRejection>,
+) -> Response {
+    let request = match request {
+        Ok(Json(request)) => request,
+        Err(rejection) => {
+            let status = rejection.status();
+            if status == StatusCode::PAYLOAD_TOO_LARGE {
+                state.metrics.record_rejected("body");
+            }
+            warn!(%status, "Rejected request body: {}", rejection.body_text());
+            let response =
+                serde_json::to_value(RpcError::parse_error(rejection.body_text())).unwrap();
+            return (status, Json(response)).into_response();
+        }
+    };
+
     let include_proof = wants_proof(&headers);
+
+    let outcome = call(&state, api_key.as_deref(), include_proof, request).await;
+    let mut response = (outcome.status, Json(outcome.body)).into_response();
+    if !outcome.verifiable {
+        response
+            .headers_mut()
+            .insert(VERIFIED_HEADER, HeaderValue::from_static("false"));
+    }
+    response
+}
+
+/// Result of one JSON-RPC call, independent of the transport it came over.
+struct RpcOutcome {
+    status: StatusCode,
+    body: serde_json::Value,
+    /// False for passthrough responses the node could not verify.
+    verifiable: bool,
+}
+
+impl RpcOutcome {
+    fn new(status: StatusCode, body: serde_json::Value) -> Self {
+        Self {
+            status,
+            body,
+            verifiable: true,
+        }
+    }
+}
+
+/// Check the API key for a request, recording the result per key.
+fn authorize(
+    state: &AppState,
+    api_key: Option<&str>,
+    request: &RpcRequest,
+) -> Result<(), RpcOutcome> {
+    match state.auth().authorize(api_key, &request.method) {
+        Ok(Some(name)) => {
+            debug!(key = %name, "Authorized");
+            state.metrics.record_api_key(&name, "allowed");
+            Ok(())
+        }
+        Ok(None) => Ok(()),
+        Err(e) => {
+            warn!(method = %request.method, error = ?e, "Request refused");
+            match &e {
+                AuthError::RateLimited { key } => state.metrics.record_api_key(key, "rate_limited"),
+                AuthError::MethodNotAllowed { key, .. } => {
+                    state.metrics.record_api_key(key, "denied")
+                }
+                AuthError::MissingKey | AuthError::InvalidKey => {}
+            }
+            let response = serde_json::to_value(e.to_rpc_error(request.id.clone())).unwrap();
+            Err(RpcOutcome::new(e.status(), response))
+        }
+    }
+}
+
+/// Handle one request and write its access log line.
+async fn call(
+    state: &AppState,
+    api_key: Option<&str>,
+    include_proof: bool,
+    request: RpcRequest,
+) -> RpcOutcome {
+    let method = request.method.clone();
+    let start = Instant::now();
+    let (outcome, entry) = access::scope(authorize_and_dispatch(
+        state,
+        api_key,
+        include_proof,
+        request,
+    ))
+    .await;
+
+    info!(
+        target: "access",
+        method = %method,
+        status = outcome.status.as_u16(),
+        error = outcome.body["error"]["code"].as_i64(),
+        duration_ms = start.elapsed().as_millis() as u64,
+        upstream = entry.upstream.as_deref(),
+        verification = entry.verification.map(|v| v.as_str()),
+        block = entry.block,
+        "RPC request served"
+    );
+    outcome
+}
+
+/// Authorize, dispatch and time one request.
+async fn authorize_and_dispatch(
+    state: &AppState,
+    api_key: Option<&str>,
+    include_proof: bool,
+    request: RpcRequest,
+) -> RpcOutcome {
     debug!(method = %request.method, include_proof, "RPC request");
 
+    if let Err(refused) = authorize(state, api_key, &request) {
+        return refused;
+    }
+
+    let start = Instant::now();
+    let timeout = state.config().server.timeout_for(&request.method);
+
+    let handled = tokio::time::timeout(timeout, async {
+        match dispatch(state, &request, include_proof).await {
+            Some(response) => Some((response, true)),
+            None if state.config().proxy.allows(&request.method) => {
+                Some((handlers::passthrough(state, &request).await, false))
+            }
+            None => None,
+        }
+    });
+
+    let (response, verifiable) = match handled.await {
+        Ok(Some(handled)) => handled,
+        Ok(None) => {
+            error!(method = %request.method, "Method not found");
+            // Unknown methods share one label to keep metric cardinality bounded
+            state.metrics.record_request("unknown", start.elapsed());
+            let response = serde_json::to_value(RpcError::method_not_found(request.id)).unwrap();
+            return RpcOutcome::new(StatusCode::OK, response);
+        }
+        Err(_) => {
+            warn!(method = %request.method, ?timeout, "Request timed out");
+            state.metrics.record_rejected("timeout");
+            state
+                .metrics
+                .record_request(&request.method, start.elapsed());
+            let response = serde_json::to_value(RpcError::timeout(request.id)).unwrap();
+            return RpcOutcome::new(StatusCode::GATEWAY_TIMEOUT, response);
+        }
+    };
+
+    if !verifiable {
+        // Passthrough methods are caller-chosen, so they share one label
+        state.metrics.record_request("passthrough", start.elapsed());
+        return RpcOutcome {
+            verifiable: false,
+            ..RpcOutcome::new(StatusCode::OK, response)
+        };
+    }
+
+    state
+        .metrics
+        .record_request(&request.method, start.elapsed());
+
+    RpcOutcome::new(StatusCode::OK, response)
+}
+
+/// Run a request against its method handler, or `None` for unknown methods.
+async fn dispatch(
+    state: &AppState,
+    request: &RpcRequest,
+    include_proof: bool,
+) -> Option<serde_json::Value> {
     let response = match request.method.as_str() {
-        "eth_getBalance" => handlers::eth_get_balance(&state, &request, include_proof).await,
-        "eth_getStorageAt" => handlers::eth_get_storage_at(&state, &request, include_proof).await,
+        "eth_getBalance" => handlers::eth_get_balance(state, request, include_proof).await,
+        "eth_getStorageAt" => handlers::eth_get_storage_at(state, request, include_proof).await,
         "eth_getTransactionCount" => {
-            handlers::eth_get_transaction_count(&state, &request, include_proof).await
+            handlers::eth_get_transaction_count(state, request, include_proof).await
         }
-        "eth_getCode" => handlers::eth_get_code(&state, &request, include_proof).await,
-        "eth_getProof" => handlers::eth_get_proof(&state, &request).await,
-        "eth_blockNumber" => handlers::eth_block_number(&state, &request).await,
-        "eth_chainId" => handlers::eth_chain_id(&state, &request).await,
-        _ => {
-            error!(method = %request.method, "Method not found");
-            serde_json::to_value(RpcError::method_not_found(request.id)).unwrap()
+        "eth_getCode" => handlers::eth_get_code(state, request, include_proof).await,
+        "eth_getProof" => handlers::eth_get_proof(state, request).await,
+        "eth_blockNumber" => handlers::eth_block_number(state, request).await,
+        "eth_chainId" => handlers::eth_chain_id(state, request).await,
+        "dvrpc_nodeInfo" => handlers::dvrpc_node_info(state, request),
+        "admin_reloadConfig" => handlers::admin_reload_config(state, request).await,
+        "eth_sendRawTransaction" => handlers::eth_send_raw_transaction(state, request).await,
+        "dvrpc_getTokenBalance" => {
+            handlers::dvrpc_get_token_balance(state, request, include_proof).await
         }
+        "dvrpc_getTokenAllowance" => {
+            handlers::dvrpc_get_token_allowance(state, request, include_proof).await
+        }
+        "dvrpc_waitForTransaction" => handlers::dvrpc_wait_for_transaction(state, request).await,
+        "dvrpc_resolveName" => handlers::dvrpc_resolve_name(state, request).await,
+        "dvrpc_lookupAddress" => handlers::dvrpc_lookup_address(state, request).await,
+        "dvrpc_getMultiAccountProof" => {
+            handlers::dvrpc_get_multi_account_proof(state, request, include_proof).await
+        }
+        _ => return None,
+    };
+    Some(response)
+}
+
+/// Shed requests beyond `server.max_connections` in flight, and cap bodies
+/// at `server.max_body_size`. Both are read from the current config, so a
+/// reload applies to the next request.
+async fn limit_requests(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let config = state.config();
+    let Some(_slot) = InFlight::acquire(&state.in_flight, config.server.max_connections) else {
+        state.metrics.record_rejected("overloaded");
+        let error = serde_json::to_value(RpcError::overloaded()).unwrap();
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(error)).into_response();
     };
 
-    (StatusCode::OK, Json(response))
+    // Reading past the limit fails, which the JSON extractor reports as 413
+    let max_body_size = config.server.max_body_size;
+    let request = request.map(|body| Body::new(Limited::new(body, max_body_size)));
+    next.run(request).await
+}
+
+/// A request counted against `server.max_connections` until dropped.
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl<'a> InFlight<'a> {
+    fn acquire(count: &'a AtomicUsize, max: usize) -> Option<Self> {
+        count
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < max).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| Self(count))
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
 }
 
 /// Health check endpoint.
@@ -71,39 +465,525 @@ async fn health_handler() -> &'static str {
     "OK"
 }
 
+/// Readiness endpoint - succeeds only while the light client is synced and
+/// the upstream is reachable, so traffic goes only to verifying nodes.
+async fn ready_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let status = node_status(&state).await;
+    if status.ready {
+        (StatusCode::OK, "OK".to_string())
+    } else if status.consensus.state != "synced" {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("NOT READY: consensus {}", status.consensus.state),
+        )
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "NOT READY: upstream unreachable".to_string(),
+        )
+    }
+}
+
+/// Detailed node status.
+async fn status_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(node_status(&state).await)
+}
+
+async fn node_status(state: &AppState) -> NodeStatus {
+    let consensus = match state.consensus {
+        None if !state.config().consensus.enabled => ConsensusStatusInfo::new("disabled"),
+        None => ConsensusStatusInfo::new("unavailable"),
+        Some(ref consensus) => match consensus.status().await {
+            Ok(status) => {
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default();
+                ConsensusStatusInfo {
+                    verified_head_block: Some(status.head_block),
+                    verified_head_slot: Some(status.head_slot),
+                    finalized_block: Some(status.finalized_block),
+                    finalized_slot: Some(status.finalized_slot),
+                    checkpoint_age_secs: Some(now.saturating_sub(status.finalized_timestamp)),
+                    ..ConsensusStatusInfo::new("synced")
+                }
+            }
+            Err(e) => ConsensusStatusInfo {
+                error: Some(e.to_string()),
+                ..ConsensusStatusInfo::new("error")
+            },
+        },
+    };
+
+    let health = state.upstream().health();
+    let upstream = UpstreamStatusInfo {
+        endpoint: state.upstream().endpoint().to_string(),
+        reachable: health.is_reachable(),
+        last_success_secs_ago: health.last_success.map(|t| t.elapsed().as_secs()),
+        consecutive_failures: health.consecutive_failures,
+        last_error: health.last_error,
+    };
+
+    let chain_error = state.chain_error.lock().unwrap().clone();
+
+    NodeStatus {
+        version: env!("CARGO_PKG_VERSION"),
+        network: state.config().ethereum.network.to_string(),
+        chain_id: state.config().ethereum.chain_id,
+        ready: consensus.state == "synced" && upstream.reachable && chain_error.is_none(),
+        chain_error,
+        consensus,
+        upstream,
+    }
+}
+
+/// Prometheus metrics endpoint.
+async fn metrics_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(),
+    )
+}
+
+/// Periodically poll the upstream head, keeping upstream health current and
+/// recording the verified head against it. New verified heads are announced
+/// to peers.
+async fn track_heads(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(HEAD_TRACKING_INTERVAL);
+    let mut announced = None;
+    loop {
+        interval.tick().await;
+
+        match state.upstream().eth_block_number().await {
+            Ok(upstream) => {
+                if let Some(ref consensus) = state.consensus {
+                    let verified = consensus.get_block_number().await;
+                    state.metrics.record_heads(verified, upstream);
+                }
+            }
+            Err(e) => warn!("Failed to fetch upstream head: {}", e),
+        }
+
+        if let (Some(consensus), Some(p2p)) = (&state.consensus, &state.p2p) {
+            let verified = consensus.get_block_number().await;
+            if announced != Some(verified) {
+                if let Ok(Some(header)) = consensus.get_header(verified).await {
+                    p2p.publish(GossipMessage::Header {
+                        number: header.number,
+                        hash: header.hash,
+                        state_root: header.state_root,
+                    });
+                    announced = Some(verified);
+                }
+            }
+        }
+    }
+}
+
+/// Periodically repeat the startup chain checks. While the upstream or the
+/// consensus RPC is on another chain, the node reports itself not ready.
+async fn watch_chain(state: Arc<AppState>) {
+    let Ok(spec) = state.config().ethereum.spec() else {
+        return;
+    };
+    let mut interval = tokio::time::interval(CHAIN_CHECK_INTERVAL);
+    // The first tick is immediate, and startup has just checked
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+
+        let checked = async {
+            if !state.upstream().is_peer() {
+                chain::check_upstream(&state.upstream(), state.config().ethereum.chain_id).await?;
+            }
+            if state.config().consensus.enabled {
+                chain::check_beacon(&state.config().ethereum.consensus_rpc, &spec).await?;
+            }
+            Ok::<_, eyre::Report>(())
+        }
+        .await;
+
+        let mut chain_error = state.chain_error.lock().unwrap();
+        match checked {
+            Ok(()) => *chain_error = None,
+            Err(e) => match e.downcast_ref::<ChainMismatch>() {
+                Some(mismatch) => {
+                    error!("{}", mismatch);
+                    *chain_error = Some(mismatch.to_string());
+                }
+                // An unreachable endpoint leaves the last verdict standing
+                None => warn!("Failed to check chain: {}", e),
+            },
+        }
+    }
+}
+
+impl AppState {
+    /// State for a node fetching from `upstream` and verifying against
+    /// `consensus`, with the rest set up from `config`.
+    pub fn new(
+        config: Config,
+        consensus: Option<ConsensusClient>,
+        proof_generator: ProofGenerator,
+        upstream: UpstreamClient,
+        metrics: Arc<Metrics>,
+        p2p: Option<P2pHandle>,
+    ) -> Result<Self> {
+        let proof_cache = ProofCache::new(
+            config.proof.cache_size,
+            config.proof.cache_entries,
+            config.proof.cache_entries_per_block,
+        );
+        let auth = Authenticator::new(&config.auth);
+        let token_slots = TokenSlots::new(&config.tokens);
+        let attestor = config
+            .attestation
+            .as_ref()
+            .map(Attestor::load)
+            .transpose()?;
+        if auth.enabled() {
+            info!(
+                keys = config.auth.keys.len(),
+                "API key authentication enabled"
+            );
+        }
+
+        if let Some(ref attestor) = attestor {
+            info!(
+                scheme = ?attestor.scheme(),
+                public_key = %attestor.public_key(),
+                "Signing verified responses"
+            );
+        }
+
+        if config.proxy.enabled {
+            info!(denylist = ?config.proxy.denylist, "Passthrough of unverified methods enabled");
+        }
+
+        Ok(Self {
+            config: RwLock::new(Arc::new(config)),
+            consensus,
+            proof_generator,
+            proof_cache,
+            upstream: RwLock::new(Arc::new(upstream)),
+            metrics,
+            auth: RwLock::new(Arc::new(auth)),
+            token_slots,
+            attestor,
+            p2p,
+            chain_error: Mutex::new(None),
+            loader: None,
+            in_flight: AtomicUsize::new(0),
+        })
+    }
+
+    /// Let the config be reloaded with `loader`.
+    pub fn with_loader(mut self, loader: ConfigLoader) -> Self {
+        self.loader = Some(loader);
+        self
+    }
+
+    pub fn config(&self) -> Arc<Config> {
+        self.config.read().unwrap().clone()
+    }
+
+    pub fn upstream(&self) -> Arc<UpstreamClient> {
+        self.upstream.read().unwrap().clone()
+    }
+
+    pub fn auth(&self) -> Arc<Authenticator> {
+        self.auth.read().unwrap().clone()
+    }
+
+    /// Load the config again and apply changes to upstreams, auth and limits.
+    /// Nothing is applied if the new config is invalid, changes settings that
+    /// need a restart, or names an upstream on another chain or unreachable.
+    pub async fn reload(&self) -> Result<()> {
+        let loader = self
+            .loader
+            .clone()
+            .ok_or_else(|| eyre::eyre!("Config reload is not available"))?;
+        // Files are read on the blocking pool, off the request path
+        let new = tokio::task::spawn_blocking(move || loader()).await??;
+        let current = self.config();
+
+        let restart = current.restart_required(&new);
+        if !restart.is_empty() {
+            eyre::bail!(
+                "Changes to {} need a restart; config not reloaded",
+                restart.join(", ")
+            );
+        }
+
+        let upstream_changed = (
+            &current.ethereum.execution_rpc,
+            &current.ethereum.execution_source,
+            &current.ethereum.record,
+            &current.ethereum.replay,
+        ) != (
+            &new.ethereum.execution_rpc,
+            &new.ethereum.execution_source,
+            &new.ethereum.record,
+            &new.ethereum.replay,
+        );
+        // Build and check everything before swapping anything in
+        let upstream = match upstream_changed {
+            true => {
+                let (config, p2p, metrics) = (new.clone(), self.p2p.clone(), self.metrics.clone());
+                let upstream = tokio::task::spawn_blocking(move || {
+                    UpstreamClient::from_config(&config, p2p.as_ref(), metrics)
+                })
+                .await??;
+                if !upstream.is_peer() {
+                    chain::check_upstream(&upstream, new.ethereum.chain_id)
+                        .await
+                        .wrap_err("New upstream failed the chain check; config not reloaded")?;
+                }
+                Some(upstream)
+            }
+            false => None,
+        };
+        let auth = (current.auth != new.auth).then(|| Authenticator::new(&new.auth));
+
+        if let Some(upstream) = upstream {
+            info!(endpoint = upstream.endpoint(), "Upstream replaced");
+            *self.upstream.write().unwrap() = Arc::new(upstream);
+        }
+        if let Some(auth) = auth {
+            info!(keys = new.auth.keys.len(), "API keys replaced");
+            *self.auth.write().unwrap() = Arc::new(auth);
+        }
+        *self.config.write().unwrap() = Arc::new(new);
+
+        info!("Config reloaded");
+        Ok(())
+    }
+}
+
+/// Reload the config on SIGHUP.
+#[cfg(unix)]
+async fn reload_on_hangup(state: Arc<AppState>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            warn!("Failed to install SIGHUP handler: {}", e);
+            return;
+        }
+    };
+    while hangup.recv().await.is_some() {
+        info!("SIGHUP received, reloading config");
+        if let Err(e) = state.reload().await {
+            error!("Config reload failed: {}", e);
+        }
+    }
+}
+
+/// HTTP and WebSocket routes of the node.
+pub fn router(state: Arc<AppState>) -> Router {
+    // Requests beyond max_connections are shed immediately rather than queued;
+    // health endpoints stay outside the limit
+    let rpc = Router::new()
+        .route("/", post(rpc_handler))
+        .route("/:api_key", post(rpc_key_handler))
+        .route("/ws", axum::routing::get(ws::ws_handler))
+        .route("/ws/:api_key", axum::routing::get(ws::ws_key_handler))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            limit_requests,
+        ))
+        .layer(DefaultBodyLimit::disable());
+
+    Router::new()
+        .merge(rpc)
+        .route("/health", axum::routing::get(health_handler))
+        .route("/ready", axum::routing::get(ready_handler))
+        .route("/status", axum::routing::get(status_handler))
+        .layer(
+            CorsLayer::new()
+                .allow_origin(Any)
+                .allow_headers(Any)
+                .expose_headers([VERIFIED_HEADER, REQUEST_ID_HEADER]),
+        )
+        .with_state(state)
+}
+
+/// Routes of the metrics listener, which is separate from the RPC listener so
+/// metrics need not be exposed to RPC clients.
+pub fn metrics_router(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/metrics", axum::routing::get(metrics_handler))
+        .with_state(state)
+}
+
 /// Start the RPC server.
 pub async fn serve(
     addr: SocketAddr,
     config: Config,
     consensus: Option<ConsensusClient>,
     proof_generator: ProofGenerator,
+    loader: ConfigLoader,
 ) -> Result<()> {
-    let upstream = UpstreamClient::new(&config.ethereum.execution_rpc);
-
-    let state = Arc::new(AppState {
-        config,
-        consensus,
-        proof_generator,
-        upstream,
-    });
+    let metrics = Arc::new(Metrics::new());
+    let (p2p, p2p_events) = match config.p2p.enabled {
+        true => {
+            let (handle, events) = p2p::spawn(&config.p2p, config.ethereum.chain_id)?;
+            (Some(handle), Some(events))
+        }
+        false => (None, None),
+    };
+    let upstream = UpstreamClient::from_config(&config, p2p.as_ref(), metrics.clone())?;
+    if !upstream.is_peer() {
+        match chain::check_upstream(&upstream, config.ethereum.chain_id).await {
+            Ok(()) => info!(
+                chain_id = config.ethereum.chain_id,
+                "Upstream chain ID matches"
+            ),
+            Err(e) if e.downcast_ref::<ChainMismatch>().is_some() => return Err(e),
+            Err(e) => warn!("Failed to check upstream chain ID: {}", e),
+        }
+    }
+    let state = Arc::new(
+        AppState::new(config, consensus, proof_generator, upstream, metrics, p2p)?
+            .with_loader(loader),
+    );
 
-    let app = Router::new()
-        .route("/", post(rpc_handler))
-        .route("/health", axum::routing::get(health_handler))
-        .layer(CorsLayer::new().allow_origin(Any).allow_headers(Any))
-        .with_state(state);
+    tokio::spawn(track_heads(state.clone()));
+    tokio::spawn(watch_chain(state.clone()));
+    #[cfg(unix)]
+    tokio::spawn(reload_on_hangup(state.clone()));
+    if let (Some(handle), Some(events)) = (state.p2p.clone(), p2p_events) {
+        tokio::spawn(gossip::validate_gossip(
+            state.clone(),
+            handle.clone(),
+            events.gossip,
+        ));
+        tokio::spawn(peer::serve_peer_queries(
+            state.clone(),
+            handle,
+            events.queries,
+        ));
+    }
 
-    info!(%addr, "RPC server starting");
+    let terminator = state
+        .config()
+        .server
+        .tls
+        .as_ref()
+        .map(TlsTerminator::new)
+        .transpose()?;
+    #[cfg(unix)]
+    let ipc = match state.config().server.ipc_path {
+        Some(ref path) => {
+            let listener = bind_ipc(path, state.config().server.ipc_permissions)?;
+            info!(path = %path.display(), "IPC server starting");
+            Some((
+                path.clone(),
+                tokio::spawn(serve_ipc(state.clone(), listener)),
+            ))
+        }
+        None => None,
+    };
+    let metrics_addr = state.config().server.metrics_addr;
+    let metrics_listener = tokio::net::TcpListener::bind(metrics_addr)
+        .await
+        .wrap_err_with(|| format!("Failed to bind metrics address {}", metrics_addr))?;
+    info!(addr = %metrics_addr, "Metrics server starting");
+    let metrics_app = metrics_router(state.clone());
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(metrics_listener, metrics_app).await {
+            error!("Metrics server failed: {}", e);
+        }
+    });
+    let app = router(state);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    match terminator {
+        Some(terminator) => {
+            info!(%addr, "RPC server starting with TLS");
+            tokio::spawn(terminator.certificate().watch());
+            serve_tls(listener, terminator, app).await;
+        }
+        None => {
+            info!(%addr, "RPC server starting");
+            axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown_signal())
+                .await?;
+        }
+    }
+
+    #[cfg(unix)]
+    if let Some((path, task)) = ipc {
+        task.abort();
+        if let Err(e) = std::fs::remove_file(&path) {
+            warn!(path = %path.display(), "Failed to remove IPC socket: {}", e);
+        }
+    }
 
     info!("RPC server stopped");
     Ok(())
 }
 
+/// Accept TLS connections until shutdown, attaching the client certificate
+/// to each request, then wait for open connections to finish.
+pub async fn serve_tls(listener: tokio::net::TcpListener, terminator: TlsTerminator, app: Router) {
+    let graceful = GracefulShutdown::new();
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Failed to accept connection: {}", e);
+                    continue;
+                }
+            },
+            _ = &mut shutdown => break,
+        };
+
+        let acceptor = terminator.acceptor();
+        let app = app.clone();
+        let watcher = graceful.watcher();
+        tokio::spawn(async move {
+            let handshake = tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream));
+            let stream = match handshake.await {
+                Ok(Ok(stream)) => stream,
+                Ok(Err(e)) => {
+                    debug!(%peer, "TLS handshake failed: {}", e);
+                    return;
+                }
+                Err(_) => {
+                    debug!(%peer, "TLS handshake timed out");
+                    return;
+                }
+            };
+
+            // Wrapping the service, rather than layering the router, leaves
+            // its routes as they are
+            let service = match tls::client_certificate(stream.get_ref().1) {
+                Some(client) => {
+                    debug!(%peer, subject = %client.common_name, "Client certificate accepted");
+                    Either::Left(AddExtension::new(app, client))
+                }
+                None => Either::Right(app),
+            };
+            let builder = auto::Builder::new(TokioExecutor::new());
+            let connection = builder.serve_connection_with_upgrades(
+                TokioIo::new(stream),
+                TowerToHyperService::new(service),
+            );
+            if let Err(e) = watcher.watch(connection).await {
+                debug!(%peer, "Connection closed with error: {}", e);
+            }
+        });
+    }
+
+    graceful.shutdown().await;
+}
+
 async fn shutdown_signal() {
     tokio::signal::ctrl_c()
         .await
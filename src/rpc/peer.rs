@@ -0,0 +1,142 @@
+//! Proofs served to other nodes over the P2P proof protocol.
+//!
+//! Requests are relayed to this node's RPC provider. Peers verify what they
+//! get against their own light client, so nothing is proven here.
+
+use libp2p::PeerId;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{mpsc, Semaphore};
+use tracing::{debug, warn};
+
+use super::AppState;
+use crate::auth::TokenBucket;
+use crate::p2p::{P2pHandle, PeerQuery, PeerResponse};
+
+/// Methods peers may ask for. Anything else would make the node an open proxy
+/// for its RPC provider.
+const SERVED_METHODS: &[&str] = &["eth_getProof", "eth_blockNumber"];
+
+/// Requests in one peer batch.
+const MAX_PEER_BATCH: usize = 64;
+
+/// Requests per second each peer may have relayed, with bursts of up to one
+/// full batch.
+const PEER_REQUESTS_PER_SECOND: f64 = 10.0;
+
+/// Relays to the RPC provider in flight at once, across all peers.
+const MAX_CONCURRENT_RELAYS: usize = 16;
+
+/// Peers whose rate limits are tracked before idle ones are forgotten.
+const MAX_TRACKED_PEERS: usize = 1024;
+
+/// Answer proof requests from peers, within per-peer rate limits and a cap on
+/// concurrent relays.
+pub async fn serve_peer_queries(
+    state: Arc<AppState>,
+    p2p: P2pHandle,
+    mut queries: mpsc::Receiver<PeerQuery>,
+) {
+    let relays = Arc::new(Semaphore::new(MAX_CONCURRENT_RELAYS));
+    let mut buckets: HashMap<PeerId, TokenBucket> = HashMap::new();
+
+    while let Some(query) = queries.recv().await {
+        let now = Instant::now();
+        if buckets.len() >= MAX_TRACKED_PEERS && !buckets.contains_key(&query.peer) {
+            buckets.retain(|_, bucket| !bucket.is_full(now));
+        }
+        let bucket = buckets
+            .entry(query.peer)
+            .or_insert_with(|| TokenBucket::new(PEER_REQUESTS_PER_SECOND, MAX_PEER_BATCH as f64));
+        if !bucket.try_take_n(now, request_count(&query.body) as f64) {
+            debug!(peer = %query.peer, "Peer request rate limited");
+            p2p.respond(query, PeerResponse::Error("rate limited".to_string()));
+            continue;
+        }
+        let Ok(permit) = relays.clone().try_acquire_owned() else {
+            debug!(peer = %query.peer, "Too many peer requests in flight");
+            p2p.respond(query, PeerResponse::Error("busy".to_string()));
+            continue;
+        };
+
+        let state = state.clone();
+        let p2p = p2p.clone();
+        tokio::spawn(async move {
+            let response = answer(&state, &query.body).await;
+            p2p.respond(query, response);
+            drop(permit);
+        });
+    }
+}
+
+/// Requests in a single or batch JSON-RPC body.
+fn request_count(body: &serde_json::Value) -> usize {
+    match body {
+        serde_json::Value::Array(requests) => requests.len().max(1),
+        _ => 1,
+    }
+}
+
+async fn answer(state: &AppState, body: &serde_json::Value) -> PeerResponse {
+    if !state.config().p2p.serve_proofs {
+        return PeerResponse::Error("not serving proofs".to_string());
+    }
+    // Relaying to other peers could loop between nodes
+    if state.upstream().is_peer() {
+        return PeerResponse::Error("no RPC provider".to_string());
+    }
+    if let Err(e) = check_methods(body) {
+        return PeerResponse::Error(e);
+    }
+
+    match state.upstream().relay(body).await {
+        Ok(response) => PeerResponse::Result(response),
+        Err(e) => {
+            // The error may name the provider URL, which stays private
+            warn!("Failed to relay peer request: {}", e);
+            PeerResponse::Error("upstream request failed".to_string())
+        }
+    }
+}
+
+/// Check every request of a single or batch JSON-RPC body is for a served method.
+fn check_methods(body: &serde_json::Value) -> Result<(), String> {
+    let requests = match body {
+        serde_json::Value::Array(requests) if !requests.is_empty() => requests.as_slice(),
+        serde_json::Value::Array(_) => return Err("empty batch".to_string()),
+        request => std::slice::from_ref(request),
+    };
+    if requests.len() > MAX_PEER_BATCH {
+        return Err(format!("batch over {} requests", MAX_PEER_BATCH));
+    }
+    for request in requests {
+        let method = request["method"].as_str().unwrap_or_default();
+        if !SERVED_METHODS.contains(&method) {
+            debug!(method, "Declining peer request");
+            return Err(format!("method {:?} not served", method));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_check_methods() {
+        let proof = json!({"jsonrpc": "2.0", "id": 1, "method": "eth_getProof", "params": []});
+        let call = json!({"jsonrpc": "2.0", "id": 2, "method": "eth_call", "params": []});
+
+        assert!(check_methods(&proof).is_ok());
+        assert!(check_methods(&json!([proof.clone(), proof.clone()])).is_ok());
+        assert!(check_methods(&call).is_err());
+        assert!(check_methods(&json!([proof.clone(), call])).is_err());
+        assert!(check_methods(&json!([])).is_err());
+        assert!(check_methods(&json!({"id": 3})).is_err());
+        assert!(check_methods(&json!(vec![proof.clone(); MAX_PEER_BATCH])).is_ok());
+        assert!(check_methods(&json!(vec![proof; MAX_PEER_BATCH + 1])).is_err());
+    }
+}
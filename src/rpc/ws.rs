@@ -0,0 +1,327 @@
+//! JSON-RPC over WebSocket, with subscriptions to transaction inclusion.
+
+use alloy_primitives::B256;
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Extension, Path, State,
+    },
+    http::HeaderMap,
+    response::Response,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, info_span, warn, Instrument};
+
+use super::inclusion::{InclusionWatch, POLL_INTERVAL};
+use super::{api_key, authorize, call, new_request_id, wants_proof, AppState};
+use crate::tls::ClientCertificate;
+use crate::types::{InclusionState, RpcError, RpcRequest, RpcResponse};
+
+/// Subscription name for transaction inclusion updates.
+const INCLUSION_SUBSCRIPTION: &str = "dvrpc_transactionInclusion";
+
+/// Open subscriptions allowed per connection.
+const MAX_SUBSCRIPTIONS: usize = 64;
+
+/// Consecutive failed inclusion checks before a subscription gives up.
+const MAX_POLL_FAILURES: u32 = 5;
+
+/// WebSocket endpoint.
+pub async fn ws_handler(
+    State(state): State<Arc<AppState>>,
+    client: Option<Extension<ClientCertificate>>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let api_key = api_key(&state, client.as_deref(), &headers, None);
+    upgrade(state, headers, api_key, ws)
+}
+
+/// WebSocket endpoint with the API key in the URL path, for clients that
+/// cannot set headers on the upgrade request.
+pub async fn ws_key_handler(
+    State(state): State<Arc<AppState>>,
+    client: Option<Extension<ClientCertificate>>,
+    Path(path_key): Path<String>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let api_key = api_key(&state, client.as_deref(), &headers, Some(path_key));
+    upgrade(state, headers, api_key, ws)
+}
+
+fn upgrade(
+    state: Arc<AppState>,
+    headers: HeaderMap,
+    api_key: Option<String>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let include_proof = wants_proof(&headers);
+
+    ws.max_message_size(state.config().server.max_body_size)
+        .on_upgrade(move |socket| serve_socket(socket, state, api_key, include_proof))
+}
+
+/// Options of a `dvrpc_transactionInclusion` subscription.
+#[derive(Deserialize)]
+struct InclusionOptions {
+    /// Keep reporting until the inclusion block is finalized.
+    #[serde(default = "default_true")]
+    finalized: bool,
+}
+
+impl Default for InclusionOptions {
+    fn default() -> Self {
+        Self { finalized: true }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Per-connection state, shared by the WebSocket and IPC endpoints.
+pub(super) struct Connection {
+    state: Arc<AppState>,
+    api_key: Option<String>,
+    include_proof: bool,
+    notifications: mpsc::Sender<serde_json::Value>,
+    subscriptions: HashMap<String, JoinHandle<()>>,
+    next_id: u64,
+}
+
+async fn serve_socket(
+    mut socket: WebSocket,
+    state: Arc<AppState>,
+    api_key: Option<String>,
+    include_proof: bool,
+) {
+    let (mut connection, mut pending) = Connection::new(state, api_key, include_proof);
+
+    loop {
+        let outgoing = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => connection.handle(&text).await,
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            Some(notification) = pending.recv() => notification,
+        };
+
+        if socket
+            .send(Message::Text(outgoing.to_string()))
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+
+    debug!("WebSocket connection closed");
+}
+
+impl Connection {
+    /// A connection and the subscription notifications to send on it.
+    pub(super) fn new(
+        state: Arc<AppState>,
+        api_key: Option<String>,
+        include_proof: bool,
+    ) -> (Self, mpsc::Receiver<serde_json::Value>) {
+        let (notifications, pending) = mpsc::channel(64);
+        let connection = Self {
+            state,
+            api_key,
+            include_proof,
+            notifications,
+            subscriptions: HashMap::new(),
+            next_id: 1,
+        };
+        (connection, pending)
+    }
+
+    /// Handle one message and return its response.
+    pub(super) async fn handle(&mut self, text: &str) -> serde_json::Value {
+        let request = match serde_json::from_str::<RpcRequest>(text) {
+            Ok(request) => request,
+            Err(e) => return serde_json::to_value(RpcError::parse_error(e.to_string())).unwrap(),
+        };
+
+        match request.method.as_str() {
+            "eth_subscribe" => self.subscribe(request),
+            "eth_unsubscribe" => self.unsubscribe(request),
+            _ => {
+                call(
+                    &self.state,
+                    self.api_key.as_deref(),
+                    self.include_proof,
+                    request,
+                )
+                .instrument(info_span!("request", id = %new_request_id()))
+                .await
+                .body
+            }
+        }
+    }
+
+    fn subscribe(&mut self, request: RpcRequest) -> serde_json::Value {
+        if let Err(refused) = authorize(&self.state, self.api_key.as_deref(), &request) {
+            return refused.body;
+        }
+
+        let params = request.params.as_array().cloned().unwrap_or_default();
+        if params.first().and_then(|v| v.as_str()) != Some(INCLUSION_SUBSCRIPTION) {
+            return serde_json::to_value(RpcError::invalid_params(
+                request.id,
+                format!(
+                    "unsupported subscription, expected {}",
+                    INCLUSION_SUBSCRIPTION
+                ),
+            ))
+            .unwrap();
+        }
+
+        let hash = match params.get(1).cloned().map(serde_json::from_value::<B256>) {
+            Some(Ok(hash)) => hash,
+            _ => {
+                return serde_json::to_value(RpcError::invalid_params(
+                    request.id,
+                    "missing or invalid transaction hash",
+                ))
+                .unwrap()
+            }
+        };
+        let options = match params.get(2).cloned().map(serde_json::from_value) {
+            Some(Ok(options)) => options,
+            Some(Err(e)) => {
+                return serde_json::to_value(RpcError::invalid_params(
+                    request.id,
+                    format!("invalid options: {}", e),
+                ))
+                .unwrap()
+            }
+            None => InclusionOptions::default(),
+        };
+
+        self.subscriptions.retain(|_, task| !task.is_finished());
+        if self.subscriptions.len() >= MAX_SUBSCRIPTIONS {
+            return serde_json::to_value(RpcError::new(
+                request.id,
+                -32005,
+                "Too many subscriptions on this connection",
+            ))
+            .unwrap();
+        }
+
+        let id = format!("0x{:x}", self.next_id);
+        self.next_id += 1;
+
+        let task = tokio::spawn(watch_transaction(
+            self.state.clone(),
+            hash,
+            options.finalized,
+            id.clone(),
+            self.notifications.clone(),
+        ));
+        self.subscriptions.insert(id.clone(), task);
+
+        serde_json::to_value(RpcResponse::new(request.id, id)).unwrap()
+    }
+
+    fn unsubscribe(&mut self, request: RpcRequest) -> serde_json::Value {
+        let removed = request
+            .params
+            .get(0)
+            .and_then(|v| v.as_str())
+            .and_then(|id| self.subscriptions.remove(id))
+            .map(|task| task.abort())
+            .is_some();
+
+        serde_json::to_value(RpcResponse::new(request.id, removed)).unwrap()
+    }
+}
+
+/// Subscriptions end with their connection.
+impl Drop for Connection {
+    fn drop(&mut self) {
+        for (_, task) in self.subscriptions.drain() {
+            task.abort();
+        }
+    }
+}
+
+/// Send a notification whenever the transaction's inclusion changes, until it
+/// reaches the requested state.
+async fn watch_transaction(
+    state: Arc<AppState>,
+    hash: B256,
+    until_finalized: bool,
+    subscription: String,
+    notifications: mpsc::Sender<serde_json::Value>,
+) {
+    let mut watch = InclusionWatch::new(hash);
+    let mut last = None;
+    let mut failures = 0;
+
+    loop {
+        let status = match watch.poll(&state).await {
+            Ok(status) => {
+                failures = 0;
+                status
+            }
+            Err(e) if failures + 1 < MAX_POLL_FAILURES => {
+                failures += 1;
+                warn!(%hash, %subscription, failures, "Inclusion check failed, retrying: {}", e);
+                tokio::time::sleep(POLL_INTERVAL).await;
+                continue;
+            }
+            Err(e) => {
+                warn!(%hash, %subscription, "Inclusion check failed, ending subscription: {}", e);
+                let notification = serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "method": "eth_subscription",
+                    "params": {
+                        "subscription": subscription,
+                        "error": {
+                            "code": -32603,
+                            "message": format!("Inclusion check failed: {}", e),
+                        },
+                    },
+                });
+                let _ = notifications.send(notification).await;
+                return;
+            }
+        };
+
+        let current = (status.state, status.block_hash);
+        if last != Some(current) {
+            last = Some(current);
+
+            let notification = serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "eth_subscription",
+                "params": {
+                    "subscription": subscription,
+                    "result": status,
+                },
+            });
+            if notifications.send(notification).await.is_err() {
+                return;
+            }
+        }
+
+        let done = match status.state {
+            InclusionState::Pending => false,
+            InclusionState::Included => !until_finalized,
+            InclusionState::Finalized => true,
+        };
+        if done {
+            return;
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
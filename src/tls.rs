@@ -0,0 +1,184 @@
+//! TLS termination for the RPC server, with optional client certificates.
+//!
+//! The certificate and key are read again when either file changes, so a
+//! renewed certificate is served to new connections without a restart.
+
+use eyre::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio_rustls::rustls::crypto::{ring, CryptoProvider};
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier};
+use tokio_rustls::rustls::sign::CertifiedKey;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig, ServerConnection};
+use tokio_rustls::TlsAcceptor;
+use tracing::{info, warn};
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use crate::config::TlsConfig;
+
+/// How often the certificate and key files are checked for changes.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Subject of the certificate a client connected with, attached to each of
+/// its requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCertificate {
+    pub common_name: String,
+}
+
+/// Serves the certificate loaded last from `cert` and `key`.
+#[derive(Debug)]
+pub struct CertificateResolver {
+    cert: PathBuf,
+    key: PathBuf,
+    provider: Arc<CryptoProvider>,
+    current: RwLock<Arc<CertifiedKey>>,
+    /// Modification times of the files the current certificate came from.
+    modified: Mutex<(Option<SystemTime>, Option<SystemTime>)>,
+}
+
+impl CertificateResolver {
+    fn load(config: &TlsConfig, provider: Arc<CryptoProvider>) -> Result<Self> {
+        let modified = (modified(&config.cert), modified(&config.key));
+        let current = certified_key(&config.cert, &config.key, &provider)?;
+        Ok(Self {
+            cert: config.cert.clone(),
+            key: config.key.clone(),
+            provider,
+            current: RwLock::new(Arc::new(current)),
+            modified: Mutex::new(modified),
+        })
+    }
+
+    /// Load the certificate again if its files changed since the last load.
+    /// Returns whether a new certificate is being served. A certificate that
+    /// fails to load leaves the current one in place.
+    pub fn reload_if_changed(&self) -> Result<bool> {
+        let modified = (modified(&self.cert), modified(&self.key));
+        if *self.modified.lock().unwrap() == modified {
+            return Ok(false);
+        }
+
+        let key = certified_key(&self.cert, &self.key, &self.provider)?;
+        *self.current.write().unwrap() = Arc::new(key);
+        *self.modified.lock().unwrap() = modified;
+        Ok(true)
+    }
+
+    /// Check the certificate files for changes until the server stops.
+    pub async fn watch(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            match self.reload_if_changed() {
+                Ok(true) => info!(cert = %self.cert.display(), "TLS certificate reloaded"),
+                Ok(false) => {}
+                Err(e) => warn!("Failed to reload TLS certificate: {:#}", e),
+            }
+        }
+    }
+}
+
+impl ResolvesServerCert for CertificateResolver {
+    fn resolve(&self, _: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current.read().unwrap().clone())
+    }
+}
+
+/// Accepts TLS connections as configured under `[server.tls]`.
+pub struct TlsTerminator {
+    acceptor: TlsAcceptor,
+    certificate: Arc<CertificateResolver>,
+}
+
+impl TlsTerminator {
+    pub fn new(config: &TlsConfig) -> Result<Self> {
+        let provider = Arc::new(ring::default_provider());
+        let certificate = Arc::new(CertificateResolver::load(config, provider.clone())?);
+
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .wrap_err("Unsupported TLS protocol versions")?;
+        let builder = match config.client_ca {
+            Some(ref client_ca) => {
+                let mut roots = RootCertStore::empty();
+                for cert in certificates(client_ca)? {
+                    roots.add(cert).wrap_err_with(|| {
+                        format!("Invalid client CA certificate in {}", client_ca.display())
+                    })?;
+                }
+                let verifier =
+                    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+                let verifier = match config.client_cert_optional {
+                    true => verifier.allow_unauthenticated(),
+                    false => verifier,
+                };
+                builder.with_client_cert_verifier(
+                    verifier
+                        .build()
+                        .wrap_err("Failed to build client certificate verifier")?,
+                )
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        let mut server = builder.with_cert_resolver(certificate.clone());
+        server.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+        Ok(Self {
+            acceptor: TlsAcceptor::from(Arc::new(server)),
+            certificate,
+        })
+    }
+
+    pub fn acceptor(&self) -> TlsAcceptor {
+        self.acceptor.clone()
+    }
+
+    pub fn certificate(&self) -> Arc<CertificateResolver> {
+        self.certificate.clone()
+    }
+}
+
+/// Common name of the verified certificate the client presented, if any.
+pub fn client_certificate(connection: &ServerConnection) -> Option<ClientCertificate> {
+    let der = connection.peer_certificates()?.first()?;
+    let (_, cert) = X509Certificate::from_der(der.as_ref()).ok()?;
+    let common_name = cert.subject().iter_common_name().next()?.as_str().ok()?;
+    Some(ClientCertificate {
+        common_name: common_name.to_string(),
+    })
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn certificates(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .wrap_err_with(|| format!("Failed to read certificates from {}", path.display()))?;
+    if certs.is_empty() {
+        eyre::bail!("No certificates found in {}", path.display());
+    }
+    Ok(certs)
+}
+
+fn certified_key(cert: &Path, key: &Path, provider: &CryptoProvider) -> Result<CertifiedKey> {
+    let certs = certificates(cert)?;
+    let key = PrivateKeyDer::from_pem_file(key)
+        .wrap_err_with(|| format!("Failed to read private key from {}", key.display()))?;
+    let key = provider
+        .key_provider
+        .load_private_key(key)
+        .wrap_err("Unsupported TLS private key")?;
+    let certified = CertifiedKey::new(certs, key);
+    certified
+        .keys_match()
+        .wrap_err("TLS private key does not match the certificate")?;
+    Ok(certified)
+}
@@ -0,0 +1,234 @@
+//! ERC-20 storage layout: where balances and allowances live in token storage.
+//!
+//! Balances are a `mapping(address => uint256)` and allowances a nested
+//! `mapping(address => mapping(address => uint256))` at some declared slot.
+//! Solidity hashes the key before the slot, Vyper the slot before the key.
+
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::config::TokenConfig;
+
+/// Slots probed when detecting a token's layout.
+pub const MAX_PROBE_SLOT: u64 = 20;
+
+/// Detected slots kept. Past this, detection still answers each request but
+/// its result is not kept.
+const MAX_DETECTED: usize = 10_000;
+
+/// `balanceOf(address)` selector.
+const BALANCE_OF: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+
+/// `allowance(address,address)` selector.
+const ALLOWANCE: [u8; 4] = [0xdd, 0x62, 0xed, 0x3e];
+
+/// Order in which a compiler hashes mapping keys and slots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// `keccak256(key . slot)`
+    Solidity,
+    /// `keccak256(slot . key)`
+    Vyper,
+}
+
+/// A mapping's declared slot and the layout used to hash into it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MappingSlot {
+    pub index: u64,
+    pub layout: Layout,
+}
+
+impl MappingSlot {
+    /// Storage key of `mapping[key]`.
+    pub fn key(&self, key: Address) -> B256 {
+        mapping_key(self.layout, U256::from(self.index).into(), key)
+    }
+
+    /// Storage key of `mapping[outer][inner]`.
+    pub fn nested_key(&self, outer: Address, inner: Address) -> B256 {
+        mapping_key(self.layout, self.key(outer), inner)
+    }
+
+    /// Every slot and layout combination tried during detection.
+    pub fn candidates() -> Vec<MappingSlot> {
+        (0..=MAX_PROBE_SLOT)
+            .flat_map(|index| {
+                [Layout::Solidity, Layout::Vyper]
+                    .into_iter()
+                    .map(move |layout| MappingSlot { index, layout })
+            })
+            .collect()
+    }
+}
+
+fn mapping_key(layout: Layout, slot: B256, key: Address) -> B256 {
+    let key = B256::left_padding_from(key.as_slice());
+    match layout {
+        Layout::Solidity => keccak256([key.as_slice(), slot.as_slice()].concat()),
+        Layout::Vyper => keccak256([slot.as_slice(), key.as_slice()].concat()),
+    }
+}
+
+/// The token mapping being queried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenField {
+    Balance,
+    Allowance,
+}
+
+impl TokenField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenField::Balance => "balance",
+            TokenField::Allowance => "allowance",
+        }
+    }
+}
+
+/// Calldata for `balanceOf(owner)`.
+pub fn balance_of_call(owner: Address) -> Bytes {
+    [
+        &BALANCE_OF[..],
+        B256::left_padding_from(owner.as_slice()).as_slice(),
+    ]
+    .concat()
+    .into()
+}
+
+/// Calldata for `allowance(owner, spender)`.
+pub fn allowance_call(owner: Address, spender: Address) -> Bytes {
+    [
+        &ALLOWANCE[..],
+        B256::left_padding_from(owner.as_slice()).as_slice(),
+        B256::left_padding_from(spender.as_slice()).as_slice(),
+    ]
+    .concat()
+    .into()
+}
+
+/// Mapping slots per token, from `[[tokens]]` or found by detection.
+pub struct TokenSlots {
+    configured: HashMap<(Address, TokenField), MappingSlot>,
+    detected: Mutex<HashMap<(Address, TokenField), MappingSlot>>,
+}
+
+impl TokenSlots {
+    pub fn new(tokens: &[TokenConfig]) -> Self {
+        let mut slots = HashMap::new();
+        for token in tokens {
+            if let Some(index) = token.balance_slot {
+                slots.insert(
+                    (token.address, TokenField::Balance),
+                    MappingSlot {
+                        index,
+                        layout: token.layout,
+                    },
+                );
+            }
+            if let Some(index) = token.allowance_slot {
+                slots.insert(
+                    (token.address, TokenField::Allowance),
+                    MappingSlot {
+                        index,
+                        layout: token.layout,
+                    },
+                );
+            }
+        }
+
+        Self {
+            configured: slots,
+            detected: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, token: Address, field: TokenField) -> Option<MappingSlot> {
+        let key = (token, field);
+        match self.configured.get(&key) {
+            Some(slot) => Some(*slot),
+            None => self.detected.lock().unwrap().get(&key).copied(),
+        }
+    }
+
+    /// Remember a detected slot. Only a slot that was the sole candidate
+    /// holding a non-zero getter value should be kept.
+    pub fn insert(&self, token: Address, field: TokenField, slot: MappingSlot) {
+        let mut detected = self.detected.lock().unwrap();
+        if detected.len() < MAX_DETECTED {
+            detected.insert((token, field), slot);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{address, b256};
+
+    #[test]
+    fn test_solidity_mapping_key() {
+        // keccak256(abi.encode(owner, uint256(0)))
+        let owner = address!("0000000000000000000000000000000000000001");
+        let slot = MappingSlot {
+            index: 0,
+            layout: Layout::Solidity,
+        };
+        assert_eq!(
+            slot.key(owner),
+            b256!("ada5013122d395ba3c54772283fb069b10426056ef8ca54750cb9bb552a59e7d")
+        );
+    }
+
+    #[test]
+    fn test_nested_and_vyper_keys_differ() {
+        let owner = Address::repeat_byte(1);
+        let spender = Address::repeat_byte(2);
+        let solidity = MappingSlot {
+            index: 3,
+            layout: Layout::Solidity,
+        };
+        let vyper = MappingSlot {
+            layout: Layout::Vyper,
+            ..solidity
+        };
+
+        assert_ne!(solidity.key(owner), vyper.key(owner));
+        assert_eq!(
+            solidity.nested_key(owner, spender),
+            mapping_key(Layout::Solidity, solidity.key(owner), spender)
+        );
+        assert_ne!(
+            solidity.nested_key(owner, spender),
+            solidity.nested_key(spender, owner)
+        );
+        assert_eq!(
+            MappingSlot::candidates().len(),
+            2 * (MAX_PROBE_SLOT as usize + 1)
+        );
+    }
+
+    #[test]
+    fn test_detected_slots_kept() {
+        let weth = Address::repeat_byte(0xee);
+        let slots = TokenSlots::new(&[TokenConfig {
+            address: weth,
+            balance_slot: Some(3),
+            allowance_slot: None,
+            layout: Layout::Solidity,
+        }]);
+        let detected = MappingSlot {
+            index: 4,
+            layout: Layout::Solidity,
+        };
+        assert_eq!(slots.get(weth, TokenField::Allowance), None);
+        slots.insert(weth, TokenField::Allowance, detected);
+        assert_eq!(slots.get(weth, TokenField::Allowance), Some(detected));
+
+        // Configured slots win over detected ones
+        slots.insert(weth, TokenField::Balance, detected);
+        assert_eq!(slots.get(weth, TokenField::Balance).unwrap().index, 3);
+    }
+}
@@ -0,0 +1,395 @@
+//! Decoding of signed raw transactions for local pre-validation.
+//!
+//! Only the fields needed to validate a transaction against proven sender
+//! state are extracted. The sender is recovered from the signature and the
+//! hash is computed from the raw bytes, so neither is taken from the upstream.
+
+use alloy_primitives::{keccak256, Address, Signature, B256, U256};
+use alloy_rlp::{Decodable, Header};
+use eyre::{bail, ensure, Result};
+
+/// Gas consumed by one EIP-4844 blob.
+const GAS_PER_BLOB: u64 = 131_072;
+
+/// The fields of a signed transaction relevant to pre-validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedTransaction {
+    /// EIP-2718 type, 0 for legacy transactions.
+    pub tx_type: u8,
+    /// `None` for legacy transactions signed without EIP-155 replay protection.
+    pub chain_id: Option<u64>,
+    pub nonce: u64,
+    pub gas_limit: u64,
+    /// `maxFeePerGas`, or `gasPrice` for legacy and EIP-2930 transactions.
+    pub max_fee_per_gas: u128,
+    pub value: U256,
+    pub max_fee_per_blob_gas: Option<u128>,
+    pub blob_count: usize,
+    pub hash: B256,
+    pub sender: Address,
+}
+
+impl DecodedTransaction {
+    /// Most the sender can be charged: `gas * maxFee + value`, plus blob gas.
+    pub fn max_cost(&self) -> U256 {
+        let gas = U256::from(self.gas_limit) * U256::from(self.max_fee_per_gas);
+        let blob_gas = U256::from(self.blob_count as u64 * GAS_PER_BLOB)
+            * U256::from(self.max_fee_per_blob_gas.unwrap_or_default());
+        gas.saturating_add(blob_gas).saturating_add(self.value)
+    }
+}
+
+/// Field positions within a transaction payload.
+struct Layout {
+    fields: usize,
+    nonce: usize,
+    max_fee: usize,
+    gas_limit: usize,
+    value: usize,
+}
+
+const LEGACY: Layout = Layout {
+    fields: 9,
+    nonce: 0,
+    max_fee: 1,
+    gas_limit: 2,
+    value: 4,
+};
+
+const EIP2930: Layout = Layout {
+    fields: 11,
+    nonce: 1,
+    max_fee: 2,
+    gas_limit: 3,
+    value: 5,
+};
+
+const EIP1559: Layout = Layout {
+    fields: 12,
+    nonce: 1,
+    max_fee: 3,
+    gas_limit: 4,
+    value: 6,
+};
+
+const EIP4844: Layout = Layout {
+    fields: 14,
+    ..EIP1559
+};
+
+const EIP7702: Layout = Layout {
+    fields: 13,
+    ..EIP1559
+};
+
+/// Decode a raw signed transaction as passed to `eth_sendRawTransaction`.
+///
+/// Accepts legacy transactions and EIP-2930, EIP-1559, EIP-4844 (including the
+/// network form carrying blobs) and EIP-7702 typed transactions.
+pub fn decode_raw_transaction(raw: &[u8]) -> Result<DecodedTransaction> {
+    let Some(&first) = raw.first() else {
+        bail!("empty transaction");
+    };
+
+    if first >= 0xc0 {
+        return decode_legacy(raw);
+    }
+
+    let layout = match first {
+        0x01 => EIP2930,
+        0x02 => EIP1559,
+        0x03 => EIP4844,
+        0x04 => EIP7702,
+        _ => bail!("unsupported transaction type 0x{:02x}", first),
+    };
+
+    let mut payload = &raw[1..];
+    let mut fields = split_list(&mut payload)?;
+    ensure!(payload.is_empty(), "trailing bytes after transaction");
+
+    // Blob transactions are broadcast wrapped with their blobs, commitments
+    // and proofs; the hash covers only the inner transaction
+    let mut hashed = raw.to_vec();
+    if first == 0x03 && fields.first().is_some_and(|f| f[0] >= 0xc0) {
+        let mut inner = fields[0];
+        hashed = [&[0x03], inner].concat();
+        fields = split_list(&mut inner)?;
+    }
+
+    ensure!(
+        fields.len() == layout.fields,
+        "type 0x{:02x} transaction must have {} fields, got {}",
+        first,
+        layout.fields,
+        fields.len()
+    );
+
+    let (unsigned, signature) = fields.split_at(layout.fields - 3);
+    let y_parity = bool::decode(&mut &signature[0][..])?;
+    let signature = decode_signature(signature, y_parity)?;
+
+    let mut signing = vec![first];
+    encode_list(unsigned, &[], &mut signing);
+    let sender = signature
+        .recover_address_from_prehash(&keccak256(&signing))
+        .map_err(|e| eyre::eyre!("invalid signature: {}", e))?;
+
+    let (max_fee_per_blob_gas, blob_count) = if first == 0x03 {
+        let mut hashes = fields[10];
+        (
+            Some(u128::decode(&mut &fields[9][..])?),
+            split_list(&mut hashes)?.len(),
+        )
+    } else {
+        (None, 0)
+    };
+
+    Ok(DecodedTransaction {
+        tx_type: first,
+        chain_id: Some(u64::decode(&mut &fields[0][..])?),
+        max_fee_per_blob_gas,
+        blob_count,
+        hash: keccak256(&hashed),
+        sender,
+        ..decode_common(&fields, &layout)?
+    })
+}
+
+fn decode_legacy(raw: &[u8]) -> Result<DecodedTransaction> {
+    let mut payload = raw;
+    let fields = split_list(&mut payload)?;
+    ensure!(payload.is_empty(), "trailing bytes after transaction");
+    ensure!(
+        fields.len() == LEGACY.fields,
+        "legacy transaction must have {} fields, got {}",
+        LEGACY.fields,
+        fields.len()
+    );
+
+    let (unsigned, signature) = fields.split_at(6);
+    let v = u64::decode(&mut &signature[0][..])?;
+
+    // EIP-155 signatures commit to the chain ID in place of the signature
+    let mut signing = Vec::new();
+    let (chain_id, y_parity) = match v {
+        27 | 28 => {
+            encode_list(unsigned, &[], &mut signing);
+            (None, v == 28)
+        }
+        v if v >= 35 => {
+            let chain_id = (v - 35) / 2;
+            let mut suffix = Vec::new();
+            alloy_rlp::Encodable::encode(&chain_id, &mut suffix);
+            suffix.extend_from_slice(&[0x80, 0x80]);
+            encode_list(unsigned, &suffix, &mut signing);
+            (Some(chain_id), (v - 35) % 2 == 1)
+        }
+        _ => bail!("invalid legacy signature v value {}", v),
+    };
+
+    let signature = decode_signature(signature, y_parity)?;
+    let sender = signature
+        .recover_address_from_prehash(&keccak256(&signing))
+        .map_err(|e| eyre::eyre!("invalid signature: {}", e))?;
+
+    Ok(DecodedTransaction {
+        chain_id,
+        hash: keccak256(raw),
+        sender,
+        ..decode_common(&fields, &LEGACY)?
+    })
+}
+
+/// Decode the fields shared by every transaction type.
+fn decode_common(fields: &[&[u8]], layout: &Layout) -> Result<DecodedTransaction> {
+    Ok(DecodedTransaction {
+        tx_type: 0,
+        chain_id: None,
+        nonce: u64::decode(&mut &fields[layout.nonce][..])?,
+        gas_limit: u64::decode(&mut &fields[layout.gas_limit][..])?,
+        max_fee_per_gas: u128::decode(&mut &fields[layout.max_fee][..])?,
+        value: U256::decode(&mut &fields[layout.value][..])?,
+        max_fee_per_blob_gas: None,
+        blob_count: 0,
+        hash: B256::ZERO,
+        sender: Address::ZERO,
+    })
+}
+
+/// Build a signature from the trailing `[v, r, s]` fields.
+fn decode_signature(fields: &[&[u8]], y_parity: bool) -> Result<Signature> {
+    let r = U256::decode(&mut &fields[1][..])?;
+    let s = U256::decode(&mut &fields[2][..])?;
+    let signature = Signature::new(r, s, y_parity);
+
+    // EIP-2: high-s signatures are malleable and rejected by every client
+    ensure!(
+        signature.normalize_s().is_none(),
+        "invalid signature: s value too high"
+    );
+    Ok(signature)
+}
+
+/// Split an RLP list into the raw encodings of its items, advancing `buf`
+/// past the list.
+fn split_list<'a>(buf: &mut &'a [u8]) -> Result<Vec<&'a [u8]>> {
+    let header = Header::decode(buf)?;
+    ensure!(header.list, "expected RLP list");
+    ensure!(
+        buf.len() >= header.payload_length,
+        "RLP list longer than input"
+    );
+
+    let (mut payload, rest) = buf.split_at(header.payload_length);
+    *buf = rest;
+
+    let mut items = Vec::new();
+    while !payload.is_empty() {
+        let start = payload;
+        let item = Header::decode(&mut payload)?;
+        ensure!(
+            payload.len() >= item.payload_length,
+            "RLP item longer than list"
+        );
+        payload = &payload[item.payload_length..];
+        items.push(&start[..start.len() - payload.len()]);
+    }
+    Ok(items)
+}
+
+/// Append an RLP list of already-encoded items followed by `suffix`.
+fn encode_list(items: &[&[u8]], suffix: &[u8], out: &mut Vec<u8>) {
+    let payload_length = items.iter().map(|i| i.len()).sum::<usize>() + suffix.len();
+    Header {
+        list: true,
+        payload_length,
+    }
+    .encode(out);
+    for item in items {
+        out.extend_from_slice(item);
+    }
+    out.extend_from_slice(suffix);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_rlp::Encodable;
+    use k256::ecdsa::SigningKey;
+
+    fn key() -> SigningKey {
+        SigningKey::from_slice(&[0x42; 32]).unwrap()
+    }
+
+    fn address(key: &SigningKey) -> Address {
+        Address::from_public_key(key.verifying_key())
+    }
+
+    fn sign(key: &SigningKey, hash: B256) -> Signature {
+        let (sig, recid) = key.sign_prehash_recoverable(hash.as_slice()).unwrap();
+        Signature::from_signature_and_parity(sig, recid.is_y_odd())
+    }
+
+    fn encode(items: &[&dyn Encodable]) -> Vec<Vec<u8>> {
+        items
+            .iter()
+            .map(|item| {
+                let mut out = Vec::new();
+                item.encode(&mut out);
+                out
+            })
+            .collect()
+    }
+
+    fn list(items: &[Vec<u8>]) -> Vec<u8> {
+        let items: Vec<&[u8]> = items.iter().map(Vec::as_slice).collect();
+        let mut out = Vec::new();
+        encode_list(&items, &[], &mut out);
+        out
+    }
+
+    #[test]
+    fn test_decode_eip1559() {
+        let key = key();
+        let to = Address::repeat_byte(0x11);
+        let empty: Vec<Vec<u8>> = vec![];
+        let mut fields = encode(&[
+            &1u64,
+            &7u64,
+            &2_000_000_000u128,
+            &30_000_000_000u128,
+            &21_000u64,
+            &to,
+            &U256::from(1_000u64),
+            &alloy_primitives::Bytes::new(),
+        ]);
+        fields.push(list(&empty));
+
+        let signing = [vec![0x02], list(&fields)].concat();
+        let sig = sign(&key, keccak256(&signing));
+        fields.extend(encode(&[&sig.v(), &sig.r(), &sig.s()]));
+        let raw = [vec![0x02], list(&fields)].concat();
+
+        let tx = decode_raw_transaction(&raw).unwrap();
+        assert_eq!(tx.tx_type, 2);
+        assert_eq!(tx.chain_id, Some(1));
+        assert_eq!(tx.nonce, 7);
+        assert_eq!(tx.sender, address(&key));
+        assert_eq!(tx.hash, keccak256(&raw));
+        assert_eq!(
+            tx.max_cost(),
+            U256::from(21_000u64 * 30_000_000_000u64 + 1_000)
+        );
+
+        assert!(decode_raw_transaction(&[&raw[..], &[0x00]].concat()).is_err());
+    }
+
+    #[test]
+    fn test_decode_legacy_eip155() {
+        let key = key();
+        let chain_id = 11155111u64;
+        let mut fields = encode(&[
+            &0u64,
+            &1_000_000_000u128,
+            &21_000u64,
+            &Address::repeat_byte(0x22),
+            &U256::from(5u64),
+            &alloy_primitives::Bytes::new(),
+        ]);
+
+        let mut signing_fields = fields.clone();
+        signing_fields.extend(encode(&[&chain_id, &0u8, &0u8]));
+        let sig = sign(&key, keccak256(list(&signing_fields)));
+        let v = 35 + 2 * chain_id + sig.v() as u64;
+        fields.extend(encode(&[&v, &sig.r(), &sig.s()]));
+        let raw = list(&fields);
+
+        let tx = decode_raw_transaction(&raw).unwrap();
+        assert_eq!(tx.tx_type, 0);
+        assert_eq!(tx.chain_id, Some(chain_id));
+        assert_eq!(tx.sender, address(&key));
+        assert_eq!(tx.max_cost(), U256::from(21_000_000_000_005u64));
+    }
+
+    #[test]
+    fn test_decode_eip155_example() {
+        // Signed example transaction from EIP-155
+        let raw = alloy_primitives::hex!("f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83");
+
+        let tx = decode_raw_transaction(&raw).unwrap();
+        assert_eq!(tx.chain_id, Some(1));
+        assert_eq!(tx.nonce, 9);
+        assert_eq!(
+            tx.sender,
+            alloy_primitives::address!("9d8A62f656a8d1615C1294fd71e9CFb3E4855A4F")
+        );
+    }
+
+    #[test]
+    fn test_rejects_malformed() {
+        assert!(decode_raw_transaction(&[]).is_err());
+        assert!(decode_raw_transaction(&[0x05, 0xc0]).is_err());
+        assert!(decode_raw_transaction(&[0x02, 0xc0]).is_err());
+        assert!(decode_raw_transaction(&[0x02, 0xc5, 0x01]).is_err());
+    }
+}
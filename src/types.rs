@@ -1,13 +1,16 @@
 //! RPC types and response structures.
 
-use alloy_primitives::{Bytes, B256, U256, U64};
+use alloy_primitives::{Address, Bytes, B256, U256, U64};
 use serde::{Deserialize, Serialize};
 
+use crate::attestation::Scheme;
+
 /// Consensus proof containing state root and sync committee attestation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ConsensusProof {
     pub state_root: B256,
+    pub block_hash: B256,
     pub slot: u64,
     pub block_number: u64,
 }
@@ -25,6 +28,20 @@ pub struct ProofData {
     pub storage_proof: Vec<StorageProofData>,
 }
 
+impl ProofData {
+    /// Total size in bytes of the account and storage proof nodes.
+    pub fn proof_size(&self) -> usize {
+        let account: usize = self.account_proof.iter().map(|n| n.len()).sum();
+        let storage: usize = self
+            .storage_proof
+            .iter()
+            .flat_map(|sp| &sp.proof)
+            .map(|n| n.len())
+            .sum();
+        account + storage
+    }
+}
+
 /// Storage slot proof.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageProofData {
@@ -33,6 +50,236 @@ pub struct StorageProofData {
     pub proof: Vec<Bytes>,
 }
 
+/// Per-account result of a multi-account proof request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountResult {
+    pub address: alloy_primitives::Address,
+    pub verified: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<U64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_hash: Option<B256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage_hash: Option<B256>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub storage: Vec<StorageValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AccountResult {
+    pub fn failed(address: alloy_primitives::Address, error: impl Into<String>) -> Self {
+        Self {
+            address,
+            verified: false,
+            balance: None,
+            nonce: None,
+            code_hash: None,
+            storage_hash: None,
+            storage: vec![],
+            error: Some(error.into()),
+        }
+    }
+}
+
+/// Proven storage slot value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageValue {
+    pub key: B256,
+    pub value: U256,
+}
+
+/// Result of `dvrpc_getMultiAccountProof`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiAccountProof {
+    pub accounts: Vec<AccountResult>,
+    /// Deduplicated trie nodes covering every account and slot.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nodes: Option<Vec<Bytes>>,
+}
+
+/// Transaction receipt as returned by the upstream, with the fields needed
+/// to rebuild its entry in the receipts trie.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiptData {
+    #[serde(rename = "type", default)]
+    pub tx_type: U64,
+    pub transaction_hash: B256,
+    pub transaction_index: U64,
+    pub block_hash: B256,
+    pub block_number: U64,
+    pub status: U64,
+    pub cumulative_gas_used: U64,
+    pub gas_used: U64,
+    pub logs_bloom: Bytes,
+    pub logs: Vec<LogData>,
+    #[serde(default)]
+    pub contract_address: Option<alloy_primitives::Address>,
+}
+
+/// Log entry of a `ReceiptData`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogData {
+    pub address: alloy_primitives::Address,
+    pub topics: Vec<B256>,
+    pub data: Bytes,
+}
+
+/// How far a transaction has progressed, as proven against verified headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InclusionState {
+    /// Not in any block the light client has verified.
+    Pending,
+    /// Proven in a verified block that is not yet finalized.
+    Included,
+    /// Proven in a finalized block.
+    Finalized,
+}
+
+/// Result of `dvrpc_waitForTransaction` and `dvrpc_transactionInclusion`
+/// subscription notifications.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InclusionStatus {
+    pub transaction_hash: B256,
+    pub state: InclusionState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<U64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_hash: Option<B256>,
+    /// Verified blocks on top of and including the inclusion block.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirmations: Option<u64>,
+    /// Receipt proven against the block's receipts root.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receipt: Option<ReceiptData>,
+    /// Times the transaction was dropped from a block it had been proven in.
+    pub reorgs: u32,
+}
+
+/// Result of `dvrpc_resolveName` and `dvrpc_lookupAddress`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnsResolution {
+    pub name: Option<String>,
+    pub address: Option<Address>,
+    /// Namehash of the resolved name, or of the reverse record name.
+    pub node: B256,
+    pub resolver: Option<Address>,
+    /// The resolver was read from registry storage proven against the
+    /// verified state root.
+    pub resolver_proven: bool,
+    /// The record was read from proven resolver storage. False when it came
+    /// from an unrecognized resolver layout, a wildcard resolver or a gateway.
+    pub record_proven: bool,
+    /// The record was served by a CCIP-read gateway.
+    pub offchain: bool,
+}
+
+/// Operator signature over a verified response.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attestation {
+    pub scheme: Scheme,
+    /// Public key of the signing node.
+    pub signer: Bytes,
+    pub block_number: U64,
+    pub block_hash: B256,
+    pub state_root: B256,
+    /// `r . s . v` for secp256k1, 64 bytes for Ed25519.
+    pub signature: Bytes,
+}
+
+/// Result of `dvrpc_nodeInfo`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeInfo {
+    pub version: &'static str,
+    pub network: String,
+    pub chain_id: u64,
+    /// Attestation key, if the node signs its responses.
+    pub attestation: Option<AttestationKey>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationKey {
+    pub scheme: Scheme,
+    pub public_key: Bytes,
+    /// Address of a secp256k1 key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<Address>,
+}
+
+/// Node status served at `/status`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeStatus {
+    pub version: &'static str,
+    pub network: String,
+    pub chain_id: u64,
+    pub ready: bool,
+    /// Set while the upstream or consensus RPC is on another chain.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_error: Option<String>,
+    pub consensus: ConsensusStatusInfo,
+    pub upstream: UpstreamStatusInfo,
+}
+
+/// Light client part of `NodeStatus`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsensusStatusInfo {
+    /// One of "disabled", "unavailable", "error" or "synced".
+    pub state: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified_head_block: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified_head_slot: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finalized_block: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finalized_slot: Option<u64>,
+    /// Seconds since the latest finalized checkpoint.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checkpoint_age_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ConsensusStatusInfo {
+    pub fn new(state: &'static str) -> Self {
+        Self {
+            state,
+            verified_head_block: None,
+            verified_head_slot: None,
+            finalized_block: None,
+            finalized_slot: None,
+            checkpoint_age_secs: None,
+            error: None,
+        }
+    }
+}
+
+/// Upstream part of `NodeStatus`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamStatusInfo {
+    pub endpoint: String,
+    pub reachable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_success_secs_ago: Option<u64>,
+    pub consecutive_failures: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
 /// Standard JSON-RPC request.
 #[derive(Debug, Clone, Deserialize)]
 #[allow(dead_code)]
@@ -53,6 +300,8 @@ pub struct RpcResponse<T: Serialize> {
     pub proof: Option<ProofData>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub consensus: Option<ConsensusProof>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<Attestation>,
 }
 
 /// JSON-RPC error response.
@@ -77,6 +326,7 @@ impl<T: Serialize> RpcResponse<T> {
             result,
             proof: None,
             consensus: None,
+            attestation: None,
         }
     }
 
@@ -85,6 +335,16 @@ impl<T: Serialize> RpcResponse<T> {
         self.consensus = Some(consensus);
         self
     }
+
+    pub fn with_consensus(mut self, consensus: ConsensusProof) -> Self {
+        self.consensus = Some(consensus);
+        self
+    }
+
+    pub fn with_attestation(mut self, attestation: Attestation) -> Self {
+        self.attestation = Some(attestation);
+        self
+    }
 }
 
 impl RpcError {
@@ -110,4 +370,20 @@ impl RpcError {
     pub fn invalid_params(id: serde_json::Value, message: impl Into<String>) -> Self {
         Self::new(id, -32602, message)
     }
+
+    pub fn parse_error(message: impl Into<String>) -> Self {
+        Self::new(serde_json::Value::Null, -32700, message)
+    }
+
+    pub fn overloaded() -> Self {
+        Self::new(
+            serde_json::Value::Null,
+            -32005,
+            "Server overloaded, try again later",
+        )
+    }
+
+    pub fn timeout(id: serde_json::Value) -> Self {
+        Self::new(id, -32000, "Request timed out")
+    }
 }
@@ -0,0 +1,214 @@
+//! In-memory execution source serving canned data, for tests.
+
+use alloy_primitives::{Address, Bytes, B256};
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use std::collections::HashMap;
+
+use super::{CallOutput, ExecutionSource};
+use crate::types::{ProofData, ReceiptData};
+
+/// Execution source answering from fixtures. Blocks are keyed by the tag the
+/// caller queries with (`"0x10"`, `"latest"`); anything not loaded is an error.
+#[derive(Default)]
+pub struct FixtureSource {
+    block_number: u64,
+    chain_id: u64,
+    /// Full proofs, including every storage slot that may be asked for.
+    proofs: HashMap<(String, Address), ProofData>,
+    state_roots: HashMap<String, B256>,
+    code: HashMap<(String, Address), Bytes>,
+    calls: HashMap<(String, Address, Bytes), CallOutput>,
+    receipts: HashMap<B256, ReceiptData>,
+    blocks: HashMap<B256, (Vec<ReceiptData>, Vec<Bytes>)>,
+}
+
+impl FixtureSource {
+    pub fn new(block_number: u64) -> Self {
+        Self {
+            block_number,
+            chain_id: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    pub fn with_proof(mut self, block: &str, proof: ProofData) -> Self {
+        self.proofs
+            .insert((block.to_string(), proof.address), proof);
+        self
+    }
+
+    pub fn with_state_root(mut self, block: &str, state_root: B256) -> Self {
+        self.state_roots.insert(block.to_string(), state_root);
+        self
+    }
+
+    pub fn with_code(mut self, block: &str, address: Address, code: Bytes) -> Self {
+        self.code.insert((block.to_string(), address), code);
+        self
+    }
+
+    pub fn with_call(mut self, block: &str, to: Address, data: Bytes, output: CallOutput) -> Self {
+        self.calls.insert((block.to_string(), to, data), output);
+        self
+    }
+
+    pub fn with_receipt(mut self, receipt: ReceiptData) -> Self {
+        self.receipts.insert(receipt.transaction_hash, receipt);
+        self
+    }
+
+    /// Receipts and EIP-2718 encoded transactions of a block, in order.
+    pub fn with_block(
+        mut self,
+        block_hash: B256,
+        receipts: Vec<ReceiptData>,
+        transactions: Vec<Bytes>,
+    ) -> Self {
+        self.blocks.insert(block_hash, (receipts, transactions));
+        self
+    }
+}
+
+#[async_trait]
+impl ExecutionSource for FixtureSource {
+    async fn eth_get_proof(
+        &self,
+        address: Address,
+        storage_keys: Vec<B256>,
+        block: &str,
+    ) -> Result<ProofData> {
+        let proof = self
+            .proofs
+            .get(&(block.to_string(), address))
+            .ok_or_else(|| eyre!("No fixture proof for {} at {}", address, block))?;
+
+        let storage_proof = storage_keys
+            .iter()
+            .map(|key| {
+                proof
+                    .storage_proof
+                    .iter()
+                    .find(|sp| sp.key == *key)
+                    .cloned()
+                    .ok_or_else(|| eyre!("No fixture proof for slot {} of {}", key, address))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(ProofData {
+            storage_proof,
+            ..proof.clone()
+        })
+    }
+
+    async fn eth_block_number(&self) -> Result<u64> {
+        Ok(self.block_number)
+    }
+
+    async fn eth_chain_id(&self) -> Result<u64> {
+        Ok(self.chain_id)
+    }
+
+    async fn eth_get_block_by_number(&self, block: &str) -> Result<B256> {
+        self.state_roots
+            .get(block)
+            .copied()
+            .ok_or_else(|| eyre!("No fixture block {}", block))
+    }
+
+    async fn eth_get_code(&self, address: Address, block: &str) -> Result<Bytes> {
+        Ok(self
+            .code
+            .get(&(block.to_string(), address))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn eth_try_call(&self, to: Address, data: Bytes, block: &str) -> Result<CallOutput> {
+        self.calls
+            .get(&(block.to_string(), to, data))
+            .cloned()
+            .ok_or_else(|| eyre!("No fixture call to {} at {}", to, block))
+    }
+
+    async fn eth_get_transaction_receipt(&self, hash: B256) -> Result<Option<ReceiptData>> {
+        Ok(self.receipts.get(&hash).cloned())
+    }
+
+    async fn eth_get_block_receipts(&self, block_hash: B256) -> Result<Vec<ReceiptData>> {
+        self.blocks
+            .get(&block_hash)
+            .map(|(receipts, _)| receipts.clone())
+            .ok_or_else(|| eyre!("No fixture block {}", block_hash))
+    }
+
+    async fn eth_get_raw_transactions(&self, block_hash: B256, count: usize) -> Result<Vec<Bytes>> {
+        let (_, transactions) = self
+            .blocks
+            .get(&block_hash)
+            .ok_or_else(|| eyre!("No fixture block {}", block_hash))?;
+        if transactions.len() != count {
+            return Err(eyre!(
+                "Fixture block {} has {} transactions, not {}",
+                block_hash,
+                transactions.len(),
+                count
+            ));
+        }
+        Ok(transactions.clone())
+    }
+
+    async fn request(&self, body: serde_json::Value) -> Result<serde_json::Value> {
+        Err(eyre!(
+            "Fixture source cannot forward {}",
+            body["method"].as_str().unwrap_or("requests")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::StorageProofData;
+    use alloy_primitives::{U256, U64};
+
+    #[tokio::test]
+    async fn test_fixture_proof_selects_requested_slots() {
+        let address = Address::repeat_byte(1);
+        let slot = |n: u8| StorageProofData {
+            key: B256::repeat_byte(n),
+            value: U256::from(n),
+            proof: vec![],
+        };
+        let source = FixtureSource::new(10).with_proof(
+            "0xa",
+            ProofData {
+                address,
+                balance: U256::from(5),
+                code_hash: B256::ZERO,
+                nonce: U64::ZERO,
+                storage_hash: B256::ZERO,
+                account_proof: vec![],
+                storage_proof: vec![slot(1), slot(2)],
+            },
+        );
+
+        let proof = source
+            .eth_get_proof(address, vec![B256::repeat_byte(2)], "0xa")
+            .await
+            .unwrap();
+        assert_eq!(proof.storage_proof.len(), 1);
+        assert_eq!(proof.storage_proof[0].value, U256::from(2));
+
+        assert!(source
+            .eth_get_proof(address, vec![B256::repeat_byte(3)], "0xa")
+            .await
+            .is_err());
+        assert!(source.eth_get_proof(address, vec![], "0xb").await.is_err());
+    }
+}
@@ -0,0 +1,215 @@
+//! JSON-RPC over the IPC socket of a local execution node (reth, geth).
+
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use super::mux::Link;
+#[cfg(unix)]
+use super::mux::Multiplexer;
+use super::Transport;
+
+/// Time allowed for one request, including connecting.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// One connection to the socket, opened on first use and reopened once it
+/// fails. Requests are multiplexed over it by JSON-RPC id, so a slow request
+/// does not hold up the others.
+pub struct IpcTransport {
+    path: PathBuf,
+    link: Mutex<Option<Arc<Link>>>,
+}
+
+impl IpcTransport {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            link: Mutex::new(None),
+        }
+    }
+
+    /// The open connection, connecting if there is none.
+    async fn link(&self) -> Result<Arc<Link>> {
+        let mut link = self.link.lock().await;
+        if let Some(open) = link.as_ref().filter(|open| !open.mux.is_closed()) {
+            return Ok(open.clone());
+        }
+        let open = Arc::new(open(&self.path).await?);
+        *link = Some(open.clone());
+        Ok(open)
+    }
+}
+
+#[async_trait]
+impl Transport for IpcTransport {
+    async fn send(&self, body: serde_json::Value) -> Result<serde_json::Value> {
+        tokio::time::timeout(REQUEST_TIMEOUT, async {
+            self.link().await?.mux.request(body).await
+        })
+        .await
+        .unwrap_or_else(|_| Err(eyre!("IPC request timed out")))
+    }
+}
+
+/// Connect and start the tasks writing requests to and reading responses
+/// from the socket. Responses are JSON values written back to back.
+#[cfg(unix)]
+async fn open(path: &std::path::Path) -> Result<Link> {
+    use crate::framing::JsonFrames;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let stream = tokio::net::UnixStream::connect(path)
+        .await
+        .map_err(|e| eyre!("Failed to connect to IPC socket {}: {}", path.display(), e))?;
+    let (mut reader, mut writer) = stream.into_split();
+    let (mux, mut outgoing) = Multiplexer::new();
+    let mux = Arc::new(mux);
+
+    let writer = tokio::spawn({
+        let mux = mux.clone();
+        async move {
+            while let Some(text) = outgoing.recv().await {
+                if writer.write_all(text.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+            mux.close();
+        }
+    });
+    let reader = tokio::spawn({
+        let mux = mux.clone();
+        async move {
+            let mut frames = JsonFrames::new();
+            let mut chunk = [0u8; 8192];
+            'read: loop {
+                match reader.read(&mut chunk).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => frames.extend(&chunk[..read]),
+                }
+                loop {
+                    match frames.next_frame() {
+                        Ok(Some(frame)) => match serde_json::from_slice(&frame) {
+                            Ok(message) => mux.dispatch(message),
+                            Err(_) => break 'read,
+                        },
+                        Ok(None) => break,
+                        // The stream cannot be resynchronized
+                        Err(_) => break 'read,
+                    }
+                }
+            }
+            mux.close();
+        }
+    });
+
+    Ok(Link {
+        mux,
+        tasks: vec![writer, reader],
+    })
+}
+
+#[cfg(not(unix))]
+async fn open(_path: &std::path::Path) -> Result<Link> {
+    Err(eyre!("IPC upstreams are only supported on Unix"))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::framing::JsonFrames;
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn socket_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("dvrpc-ipc-{}-{}.sock", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    /// Read `count` requests from the socket.
+    async fn read_requests(
+        stream: &mut tokio::net::UnixStream,
+        count: usize,
+    ) -> Vec<serde_json::Value> {
+        let mut frames = JsonFrames::new();
+        let mut requests = Vec::new();
+        let mut chunk = [0u8; 1024];
+        while requests.len() < count {
+            let read = stream.read(&mut chunk).await.unwrap();
+            frames.extend(&chunk[..read]);
+            while let Some(frame) = frames.next_frame().unwrap() {
+                requests.push(serde_json::from_slice(&frame).unwrap());
+            }
+        }
+        requests
+    }
+
+    #[tokio::test]
+    async fn test_ipc_response_split_across_reads() {
+        let path = socket_path("split");
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let request = read_requests(&mut stream, 1).await.remove(0);
+            // A notification, then the response in two pieces
+            let reply = format!(
+                r#"{{"jsonrpc":"2.0","method":"eth_subscription","params":{{}}}}{{"jsonrpc":"2.0","id":{},"result":"0x10"}}"#,
+                request["id"]
+            );
+            let (first, rest) = reply.as_bytes().split_at(70);
+            stream.write_all(first).await.unwrap();
+            stream.flush().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            stream.write_all(rest).await.unwrap();
+        });
+
+        let transport = IpcTransport::new(&path);
+        let response = transport
+            .send(json!({"jsonrpc": "2.0", "id": 1, "method": "eth_blockNumber", "params": []}))
+            .await
+            .unwrap();
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"], "0x10");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_ipc_requests_answered_out_of_order() {
+        let path = socket_path("concurrent");
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            // Both requests are in flight before either is answered
+            let requests = read_requests(&mut stream, 2).await;
+            for request in requests.iter().rev() {
+                let reply =
+                    json!({"jsonrpc": "2.0", "id": request["id"], "result": request["method"]});
+                stream
+                    .write_all(reply.to_string().as_bytes())
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let transport = IpcTransport::new(&path);
+        let (first, second) = tokio::join!(
+            transport
+                .send(json!({"jsonrpc": "2.0", "id": 7, "method": "eth_chainId", "params": []})),
+            transport.send(
+                json!({"jsonrpc": "2.0", "id": 7, "method": "eth_blockNumber", "params": []})
+            ),
+        );
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert_eq!(first["result"], "eth_chainId");
+        assert_eq!(second["result"], "eth_blockNumber");
+        assert_eq!(first["id"], 7);
+        assert_eq!(second["id"], 7);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,403 @@
+//! Execution source speaking JSON-RPC over any transport.
+
+use alloy_primitives::{Address, Bytes, B256, U256, U64};
+use async_trait::async_trait;
+use eyre::Result;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::time::Duration;
+
+use super::{CallOutput, ExecutionSource};
+use crate::config::redact_url;
+use crate::p2p::P2pHandle;
+use crate::telemetry;
+use crate::types::{ProofData, ReceiptData, StorageProofData};
+
+/// Carries JSON-RPC bodies to an execution node and back.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    async fn send(&self, body: serde_json::Value) -> Result<serde_json::Value>;
+}
+
+#[async_trait]
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    async fn send(&self, body: serde_json::Value) -> Result<serde_json::Value> {
+        (**self).send(body).await
+    }
+}
+
+/// Time allowed to connect to the upstream.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time allowed for one request, including connecting.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// JSON-RPC over HTTP POST.
+pub struct HttpTransport {
+    client: reqwest::Client,
+    url: String,
+    /// The URL without path or query, which often carry an API key.
+    redacted: String,
+}
+
+impl HttpTransport {
+    pub fn new(url: &str) -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("HTTP client");
+        Self {
+            client,
+            url: url.to_string(),
+            redacted: redact_url(url),
+        }
+    }
+
+    async fn post(&self, body: serde_json::Value) -> reqwest::Result<serde_json::Value> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        telemetry::inject(&mut headers);
+        self.client
+            .post(&self.url)
+            .headers(headers)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+}
+
+#[async_trait]
+impl Transport for HttpTransport {
+    async fn send(&self, body: serde_json::Value) -> Result<serde_json::Value> {
+        // reqwest errors name the full URL, so report the redacted one instead
+        self.post(body)
+            .await
+            .map_err(|e| eyre::eyre!("{} ({})", error_chain(&e.without_url()), self.redacted))
+    }
+}
+
+/// An error and its sources, e.g. "error sending request: connection refused".
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message = format!("{}: {}", message, cause);
+        source = cause.source();
+    }
+    message
+}
+
+/// JSON-RPC bodies sent to peers over the proof protocol.
+#[async_trait]
+impl Transport for P2pHandle {
+    async fn send(&self, body: serde_json::Value) -> Result<serde_json::Value> {
+        self.request(body).await
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct JsonRpcRequest<'a, T: Serialize> {
+    jsonrpc: &'static str,
+    method: &'a str,
+    params: T,
+    id: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct CallRequest {
+    to: Address,
+    data: Bytes,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcResponse<T> {
+    id: Option<u64>,
+    result: Option<T>,
+    error: Option<JsonRpcError>,
+}
+
+/// Error object returned by the upstream. The upstream was reachable; it
+/// refused or failed the request.
+#[derive(Debug, Deserialize)]
+pub struct JsonRpcError {
+    pub code: i32,
+    pub message: String,
+    #[serde(default)]
+    pub data: Option<serde_json::Value>,
+}
+
+impl std::fmt::Display for JsonRpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Upstream RPC error {}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for JsonRpcError {}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EthProofResponse {
+    address: Address,
+    balance: U256,
+    code_hash: B256,
+    nonce: U64,
+    storage_hash: B256,
+    account_proof: Vec<Bytes>,
+    storage_proof: Vec<StorageProofResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StorageProofResponse {
+    key: B256,
+    value: U256,
+    proof: Vec<Bytes>,
+}
+
+impl From<EthProofResponse> for ProofData {
+    fn from(proof: EthProofResponse) -> Self {
+        ProofData {
+            address: proof.address,
+            balance: proof.balance,
+            code_hash: proof.code_hash,
+            nonce: proof.nonce,
+            storage_hash: proof.storage_hash,
+            account_proof: proof.account_proof,
+            storage_proof: proof
+                .storage_proof
+                .into_iter()
+                .map(|sp| StorageProofData {
+                    key: sp.key,
+                    value: sp.value,
+                    proof: sp.proof,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Execution source backed by a JSON-RPC node reached over `T`.
+pub struct JsonRpcSource<T> {
+    transport: T,
+}
+
+impl<T: Transport> JsonRpcSource<T> {
+    pub fn new(transport: T) -> Self {
+        Self { transport }
+    }
+
+    async fn send<P: Serialize, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: P,
+    ) -> Result<JsonRpcResponse<R>> {
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0",
+            method,
+            params,
+            id: 1,
+        };
+        let response = self.transport.send(serde_json::to_value(request)?).await?;
+        Ok(serde_json::from_value(response)?)
+    }
+
+    /// Single request whose result is required.
+    async fn call<P: Serialize, R: DeserializeOwned>(&self, method: &str, params: P) -> Result<R> {
+        let response: JsonRpcResponse<R> = self.send(method, params).await?;
+
+        if let Some(error) = response.error {
+            return Err(error.into());
+        }
+
+        response
+            .result
+            .ok_or_else(|| eyre::eyre!("No result in upstream response"))
+    }
+
+    /// One request per entry of `params` in a single JSON-RPC batch, with
+    /// results returned in the same order.
+    async fn batch<P: Serialize, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Vec<P>,
+    ) -> Result<Vec<R>> {
+        let count = params.len();
+        let requests: Vec<_> = params
+            .into_iter()
+            .enumerate()
+            .map(|(id, params)| JsonRpcRequest {
+                jsonrpc: "2.0",
+                method,
+                params,
+                id: id as u64,
+            })
+            .collect();
+
+        let response = self.transport.send(serde_json::to_value(requests)?).await?;
+        let responses: Vec<JsonRpcResponse<R>> = serde_json::from_value(response)?;
+
+        let mut results: Vec<Option<R>> = (0..count).map(|_| None).collect();
+        for response in responses {
+            if let Some(error) = response.error {
+                return Err(error.into());
+            }
+            let slot = response
+                .id
+                .and_then(|id| results.get_mut(id as usize))
+                .ok_or_else(|| eyre::eyre!("Unexpected id in upstream batch response"))?;
+            *slot = response.result;
+        }
+
+        results
+            .into_iter()
+            .map(|r| r.ok_or_else(|| eyre::eyre!("No result in upstream response")))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl<T: Transport> ExecutionSource for JsonRpcSource<T> {
+    async fn eth_get_proof(
+        &self,
+        address: Address,
+        storage_keys: Vec<B256>,
+        block: &str,
+    ) -> Result<ProofData> {
+        let proof: EthProofResponse = self
+            .call("eth_getProof", (address, storage_keys, block))
+            .await?;
+        Ok(proof.into())
+    }
+
+    async fn eth_get_proofs(
+        &self,
+        accounts: &[(Address, Vec<B256>)],
+        block: &str,
+    ) -> Result<Vec<ProofData>> {
+        let params = accounts
+            .iter()
+            .map(|(address, storage_keys)| (address, storage_keys, block))
+            .collect();
+        let proofs: Vec<EthProofResponse> = self.batch("eth_getProof", params).await?;
+        Ok(proofs.into_iter().map(Into::into).collect())
+    }
+
+    async fn eth_block_number(&self) -> Result<u64> {
+        let number: U64 = self.call("eth_blockNumber", Vec::<()>::new()).await?;
+        Ok(number.to())
+    }
+
+    async fn eth_chain_id(&self) -> Result<u64> {
+        let chain_id: U64 = self.call("eth_chainId", Vec::<()>::new()).await?;
+        Ok(chain_id.to())
+    }
+
+    async fn eth_get_block_by_number(&self, block: &str) -> Result<B256> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct BlockResponse {
+            state_root: B256,
+        }
+
+        let block: BlockResponse = self.call("eth_getBlockByNumber", (block, false)).await?;
+        Ok(block.state_root)
+    }
+
+    async fn eth_get_code(&self, address: Address, block: &str) -> Result<Bytes> {
+        self.call("eth_getCode", (address, block)).await
+    }
+
+    async fn eth_try_call(&self, to: Address, data: Bytes, block: &str) -> Result<CallOutput> {
+        let response: JsonRpcResponse<Bytes> = self
+            .send("eth_call", (CallRequest { to, data }, block))
+            .await?;
+
+        if let Some(error) = response.error {
+            // Reverts carry their data as a hex string in the error's `data`
+            let revert = error
+                .data
+                .as_ref()
+                .and_then(|data| serde_json::from_value::<Bytes>(data.clone()).ok());
+            if let Some(revert) = revert {
+                return Ok(CallOutput::Revert(revert));
+            }
+            return Err(error.into());
+        }
+
+        response
+            .result
+            .map(CallOutput::Success)
+            .ok_or_else(|| eyre::eyre!("No result in upstream response"))
+    }
+
+    async fn eth_get_transaction_receipt(&self, hash: B256) -> Result<Option<ReceiptData>> {
+        let response: JsonRpcResponse<ReceiptData> =
+            self.send("eth_getTransactionReceipt", (hash,)).await?;
+
+        if let Some(error) = response.error {
+            return Err(error.into());
+        }
+
+        Ok(response.result)
+    }
+
+    async fn eth_get_block_receipts(&self, block_hash: B256) -> Result<Vec<ReceiptData>> {
+        self.call("eth_getBlockReceipts", (block_hash,)).await
+    }
+
+    async fn eth_get_raw_transactions(&self, block_hash: B256, count: usize) -> Result<Vec<Bytes>> {
+        let params = (0..count)
+            .map(|index| (block_hash, U64::from(index)))
+            .collect();
+        self.batch("eth_getRawTransactionByBlockHashAndIndex", params)
+            .await
+    }
+
+    async fn request(&self, body: serde_json::Value) -> Result<serde_json::Value> {
+        self.transport.send(body).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Answers every request with a fixed body.
+    struct Canned(serde_json::Value);
+
+    #[async_trait]
+    impl Transport for Canned {
+        async fn send(&self, _body: serde_json::Value) -> Result<serde_json::Value> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_call_revert_and_error_responses() {
+        let revert = JsonRpcSource::new(Canned(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "error": {"code": 3, "message": "execution reverted", "data": "0x08c379a0"}
+        })));
+        let output = revert
+            .eth_try_call(Address::ZERO, Bytes::new(), "latest")
+            .await
+            .unwrap();
+        assert!(
+            matches!(output, CallOutput::Revert(data) if data.as_ref() == [0x08, 0xc3, 0x79, 0xa0])
+        );
+
+        let failing = JsonRpcSource::new(Canned(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "error": {"code": -32000, "message": "header not found"}
+        })));
+        let err = failing.eth_block_number().await.unwrap_err();
+        assert_eq!(err.downcast_ref::<JsonRpcError>().unwrap().code, -32000);
+        assert_eq!(
+            err.to_string(),
+            "Upstream RPC error -32000: header not found"
+        );
+    }
+}
@@ -0,0 +1,442 @@
+//! Upstream RPC client for fetching proofs from execution layer.
+//!
+//! The upstream is an [`ExecutionSource`]: an RPC provider over HTTP or
+//! WebSocket, a local node over IPC, other DVRPC nodes reached over the P2P
+//! network, or in-memory fixtures in tests. Whatever the source, its answers
+//! are unverified until checked against the light client.
+//!
+//! Traffic to a JSON-RPC source can be recorded to a file and replayed in
+//! place of the source, to reproduce a verification failure offline.
+
+use alloy_primitives::{Address, Bytes, B256};
+use async_trait::async_trait;
+use eyre::Result;
+use std::future::Future;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::{debug, info, info_span, Instrument};
+
+use crate::config::Config;
+use crate::metrics::Metrics;
+use crate::p2p::P2pHandle;
+use crate::types::{ProofData, ReceiptData};
+
+mod fixture;
+mod ipc;
+mod jsonrpc;
+mod mux;
+mod record;
+mod ws;
+
+pub use fixture::FixtureSource;
+pub use ipc::IpcTransport;
+pub use jsonrpc::{HttpTransport, JsonRpcError, JsonRpcSource, Transport};
+pub use record::{Recording, RecordingTransport, ReplayTransport};
+pub use ws::WsTransport;
+
+/// A source of execution-layer data.
+#[async_trait]
+pub trait ExecutionSource: Send + Sync {
+    /// EIP-1186 proof of an account and the given storage slots.
+    async fn eth_get_proof(
+        &self,
+        address: Address,
+        storage_keys: Vec<B256>,
+        block: &str,
+    ) -> Result<ProofData>;
+
+    /// Proofs for many accounts, in the same order as `accounts`.
+    async fn eth_get_proofs(
+        &self,
+        accounts: &[(Address, Vec<B256>)],
+        block: &str,
+    ) -> Result<Vec<ProofData>> {
+        let mut proofs = Vec::with_capacity(accounts.len());
+        for (address, storage_keys) in accounts {
+            proofs.push(
+                self.eth_get_proof(*address, storage_keys.clone(), block)
+                    .await?,
+            );
+        }
+        Ok(proofs)
+    }
+
+    async fn eth_block_number(&self) -> Result<u64>;
+
+    async fn eth_chain_id(&self) -> Result<u64>;
+
+    /// State root of a block.
+    async fn eth_get_block_by_number(&self, block: &str) -> Result<B256>;
+
+    async fn eth_get_code(&self, address: Address, block: &str) -> Result<Bytes>;
+
+    /// Execute a call, returning revert data instead of failing on a revert.
+    async fn eth_try_call(&self, to: Address, data: Bytes, block: &str) -> Result<CallOutput>;
+
+    /// Receipt of a transaction, or `None` if it has not been mined.
+    async fn eth_get_transaction_receipt(&self, hash: B256) -> Result<Option<ReceiptData>>;
+
+    /// All receipts of a block, in transaction order.
+    async fn eth_get_block_receipts(&self, block_hash: B256) -> Result<Vec<ReceiptData>>;
+
+    /// EIP-2718 encoded transactions of a block.
+    async fn eth_get_raw_transactions(&self, block_hash: B256, count: usize) -> Result<Vec<Bytes>>;
+
+    /// Send a JSON-RPC body (single or batch) and return the response
+    /// unchanged, including any error object.
+    async fn request(&self, body: serde_json::Value) -> Result<serde_json::Value>;
+}
+
+/// Transport for `url`, and the label it is reported under in metrics.
+/// Anything but an `http(s)://`, `ws(s)://` or `ipc://` URL or an absolute
+/// socket path is refused, so a mistyped URL is not taken for a path.
+fn connect(url: &str) -> Result<(Box<dyn Transport>, String)> {
+    let endpoint = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let scheme = url.split_once("://").map(|(scheme, _)| scheme);
+    match scheme {
+        Some("http" | "https") => Ok((Box::new(HttpTransport::new(url)), endpoint)),
+        Some("ws" | "wss") => Ok((Box::new(WsTransport::new(url)), endpoint)),
+        Some("ipc") => {
+            let path = &url["ipc://".len()..];
+            Ok((Box::new(IpcTransport::new(path)), "ipc".to_string()))
+        }
+        None if Path::new(url).is_absolute() => {
+            Ok((Box::new(IpcTransport::new(url)), "ipc".to_string()))
+        }
+        // The URL is left out of the error, as it may hold an API key
+        Some(scheme) => eyre::bail!(
+            "Unsupported upstream URL scheme {}://: expected http(s)://, ws(s)://, or ipc://",
+            scheme
+        ),
+        None => eyre::bail!("Upstream IPC socket path must be absolute, or an ipc:// URL"),
+    }
+}
+
+/// Outcome of an `eth_call` that may revert.
+#[derive(Debug, Clone)]
+pub enum CallOutput {
+    Success(Bytes),
+    Revert(Bytes),
+}
+
+/// Execution source used by the node, recording latency and health of every
+/// request.
+pub struct UpstreamClient {
+    source: Box<dyn ExecutionSource>,
+    /// Host of the upstream URL, used as the metrics label so API keys in
+    /// the path never end up in metrics.
+    endpoint: String,
+    peer: bool,
+    metrics: Arc<Metrics>,
+    health: Mutex<UpstreamHealth>,
+}
+
+/// Reachability of the upstream, as seen by recent requests.
+#[derive(Debug, Clone, Default)]
+pub struct UpstreamHealth {
+    pub last_success: Option<Instant>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+}
+
+impl UpstreamHealth {
+    /// The upstream has answered and the latest request succeeded.
+    pub fn is_reachable(&self) -> bool {
+        self.last_success.is_some() && self.consecutive_failures == 0
+    }
+}
+
+impl UpstreamClient {
+    /// Connect to `url`: `http(s)://` and `ws(s)://` URLs are RPC providers,
+    /// an `ipc://` URL or absolute path is a local node's IPC socket.
+    pub fn new(url: &str, metrics: Arc<Metrics>) -> Result<Self> {
+        let (transport, endpoint) = connect(url)?;
+        Ok(Self::with_source(
+            JsonRpcSource::new(transport),
+            &endpoint,
+            metrics,
+        ))
+    }
+
+    /// Upstream described by the config: a recording being replayed, peers
+    /// when `p2p.upstream` is set, or the execution source. Traffic is
+    /// recorded if `ethereum.record` is set.
+    pub fn from_config(
+        config: &Config,
+        p2p: Option<&P2pHandle>,
+        metrics: Arc<Metrics>,
+    ) -> Result<Self> {
+        let ethereum = &config.ethereum;
+        let (transport, endpoint, peer) = match (&ethereum.replay, p2p) {
+            (Some(path), _) => {
+                let replay: Box<dyn Transport> = Box::new(ReplayTransport::load(path)?);
+                (replay, "replay".to_string(), false)
+            }
+            (None, Some(handle)) if config.p2p.upstream => {
+                info!("Fetching proofs from P2P peers instead of an RPC provider");
+                let peers: Box<dyn Transport> = Box::new(handle.clone());
+                (peers, "p2p".to_string(), true)
+            }
+            _ => {
+                let url = ethereum
+                    .execution_source
+                    .as_ref()
+                    .unwrap_or(&ethereum.execution_rpc);
+                let (transport, endpoint) = connect(url)?;
+                (transport, endpoint, false)
+            }
+        };
+
+        let transport = match &ethereum.record {
+            Some(path) => Box::new(RecordingTransport::new(transport, path)?),
+            None => transport,
+        };
+
+        Ok(Self {
+            peer,
+            ..Self::with_source(JsonRpcSource::new(transport), &endpoint, metrics)
+        })
+    }
+
+    /// Upstream backed by any source, labelled `endpoint` in metrics.
+    pub fn with_source(
+        source: impl ExecutionSource + 'static,
+        endpoint: &str,
+        metrics: Arc<Metrics>,
+    ) -> Self {
+        Self {
+            source: Box::new(source),
+            endpoint: endpoint.to_string(),
+            peer: false,
+            metrics,
+            health: Mutex::new(UpstreamHealth::default()),
+        }
+    }
+
+    /// Host of the upstream URL.
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Whether requests go to peers rather than an RPC provider.
+    pub fn is_peer(&self) -> bool {
+        self.peer
+    }
+
+    pub fn health(&self) -> UpstreamHealth {
+        self.health.lock().unwrap().clone()
+    }
+
+    /// Await a request to the source, recording latency and errors. Error
+    /// responses from the upstream count as errors in metrics, but still as
+    /// it being reachable for health.
+    async fn observe<T>(
+        &self,
+        method: &str,
+        request: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let start = Instant::now();
+        let span = info_span!("upstream", method, endpoint = %self.endpoint);
+        let result = request.instrument(span).await;
+        let reachable = match &result {
+            Ok(_) => true,
+            Err(e) => e.downcast_ref::<JsonRpcError>().is_some(),
+        };
+
+        self.metrics
+            .record_upstream(&self.endpoint, method, start.elapsed(), result.is_ok());
+
+        let mut health = self.health.lock().unwrap();
+        match &result {
+            Err(e) if !reachable => {
+                health.last_error = Some(e.to_string());
+                health.consecutive_failures += 1;
+            }
+            _ => {
+                health.last_success = Some(Instant::now());
+                health.consecutive_failures = 0;
+            }
+        }
+
+        result
+    }
+
+    pub async fn eth_get_proof(
+        &self,
+        address: Address,
+        storage_keys: Vec<B256>,
+        block: &str,
+    ) -> Result<ProofData> {
+        debug!(%address, ?storage_keys, block, "Fetching proof from upstream");
+        self.observe(
+            "eth_getProof",
+            self.source.eth_get_proof(address, storage_keys, block),
+        )
+        .await
+    }
+
+    /// Fetch proofs for many accounts in a single request where the source
+    /// supports batching.
+    ///
+    /// Results are returned in the same order as `accounts`.
+    pub async fn eth_get_proofs(
+        &self,
+        accounts: &[(Address, Vec<B256>)],
+        block: &str,
+    ) -> Result<Vec<ProofData>> {
+        debug!(
+            count = accounts.len(),
+            block, "Fetching proof batch from upstream"
+        );
+
+        if accounts.is_empty() {
+            return Ok(vec![]);
+        }
+
+        self.observe(
+            "eth_getProof_batch",
+            self.source.eth_get_proofs(accounts, block),
+        )
+        .await
+    }
+
+    #[allow(dead_code)]
+    pub async fn eth_get_block_by_number(&self, block: &str) -> Result<B256> {
+        debug!(block, "Fetching block from upstream");
+        self.observe(
+            "eth_getBlockByNumber",
+            self.source.eth_get_block_by_number(block),
+        )
+        .await
+    }
+
+    /// Contract code of an account. The result is unverified.
+    pub async fn eth_get_code(&self, address: Address, block: &str) -> Result<Bytes> {
+        debug!(%address, block, "Fetching code from upstream");
+        self.observe("eth_getCode", self.source.eth_get_code(address, block))
+            .await
+    }
+
+    /// Execute a call against the upstream's state. The result is unverified.
+    pub async fn eth_call(&self, to: Address, data: Bytes, block: &str) -> Result<Bytes> {
+        match self.eth_try_call(to, data, block).await? {
+            CallOutput::Success(output) => Ok(output),
+            CallOutput::Revert(_) => eyre::bail!("Upstream call to {} reverted", to),
+        }
+    }
+
+    /// Execute a call, returning revert data instead of failing when the
+    /// upstream reports one. The result is unverified.
+    pub async fn eth_try_call(&self, to: Address, data: Bytes, block: &str) -> Result<CallOutput> {
+        debug!(%to, block, "Calling upstream");
+        self.observe("eth_call", self.source.eth_try_call(to, data, block))
+            .await
+    }
+
+    /// Receipt of a transaction, or `None` if the upstream has not seen it mined.
+    pub async fn eth_get_transaction_receipt(&self, hash: B256) -> Result<Option<ReceiptData>> {
+        self.observe(
+            "eth_getTransactionReceipt",
+            self.source.eth_get_transaction_receipt(hash),
+        )
+        .await
+    }
+
+    /// All receipts of a block, in transaction order.
+    pub async fn eth_get_block_receipts(&self, block_hash: B256) -> Result<Vec<ReceiptData>> {
+        self.observe(
+            "eth_getBlockReceipts",
+            self.source.eth_get_block_receipts(block_hash),
+        )
+        .await
+    }
+
+    /// EIP-2718 encoded transactions of a block, fetched in a single batch.
+    pub async fn eth_get_raw_transactions(
+        &self,
+        block_hash: B256,
+        count: usize,
+    ) -> Result<Vec<Bytes>> {
+        debug!(%block_hash, count, "Fetching raw transactions from upstream");
+
+        if count == 0 {
+            return Ok(vec![]);
+        }
+
+        self.observe(
+            "eth_getRawTransactionByBlockHashAndIndex_batch",
+            self.source.eth_get_raw_transactions(block_hash, count),
+        )
+        .await
+    }
+
+    /// Forward an arbitrary request and return the upstream's JSON-RPC
+    /// response unchanged, including any error object.
+    pub async fn forward(
+        &self,
+        method: &str,
+        params: &serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        debug!(method, "Forwarding request to upstream");
+
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+            "id": 1,
+        });
+
+        // Methods are caller-chosen, so they share one metrics label
+        self.observe("passthrough", self.source.request(body)).await
+    }
+
+    /// Send a JSON-RPC body (single or batch) on behalf of a peer and return
+    /// the upstream's response unchanged.
+    pub async fn relay(&self, body: &serde_json::Value) -> Result<serde_json::Value> {
+        self.observe("peer_relay", self.source.request(body.clone()))
+            .await
+    }
+
+    /// Chain ID the upstream serves.
+    pub async fn eth_chain_id(&self) -> Result<u64> {
+        self.observe("eth_chainId", self.source.eth_chain_id())
+            .await
+    }
+
+    /// Latest block number according to the upstream.
+    pub async fn eth_block_number(&self) -> Result<u64> {
+        self.observe("eth_blockNumber", self.source.eth_block_number())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connect_schemes() {
+        for (url, endpoint) in [
+            ("https://eth.example.com/v2/key", "eth.example.com"),
+            ("wss://eth.example.com", "eth.example.com"),
+            ("ipc:///run/reth.ipc", "ipc"),
+            ("/run/reth.ipc", "ipc"),
+        ] {
+            assert_eq!(connect(url).unwrap().1, endpoint, "{}", url);
+        }
+
+        for url in [
+            "htps://eth.example.com/v2/key",
+            "reth.ipc",
+            "localhost:8545",
+        ] {
+            let error = connect(url).err().unwrap().to_string();
+            assert!(!error.contains("key"), "{}", error);
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! Many JSON-RPC requests in flight on one connection.
+//!
+//! Each request is sent with ids of our own, so responses can be matched to
+//! the request waiting for them whatever order they arrive in; the caller's
+//! ids are put back before the response is returned. A batch gets one id per
+//! element, all tagged with the batch's sequence number.
+
+use eyre::{bail, eyre, Result};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// Ids are `sequence << INDEX_BITS | index in batch`.
+const INDEX_BITS: u32 = 16;
+
+/// Requests multiplexed over one connection. The connection's reader passes
+/// every message it receives to [`Multiplexer::dispatch`], and calls
+/// [`Multiplexer::close`] when the connection ends.
+pub struct Multiplexer {
+    outgoing: mpsc::UnboundedSender<String>,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    next: u64,
+    waiting: HashMap<u64, Waiting>,
+    closed: bool,
+}
+
+struct Waiting {
+    /// The caller's ids, by index in the batch.
+    ids: Vec<serde_json::Value>,
+    reply: oneshot::Sender<serde_json::Value>,
+}
+
+impl Multiplexer {
+    /// A multiplexer and the messages to write to the connection, in order.
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<String>) {
+        let (outgoing, messages) = mpsc::unbounded_channel();
+        let mux = Self {
+            outgoing,
+            state: Mutex::new(State::default()),
+        };
+        (mux, messages)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+
+    /// Send a single or batch body and wait for its response.
+    pub async fn request(&self, mut body: serde_json::Value) -> Result<serde_json::Value> {
+        let (reply, response) = oneshot::channel();
+        let sequence = {
+            let mut state = self.state.lock().unwrap();
+            if state.closed {
+                bail!("connection closed");
+            }
+            let sequence = state.next;
+            state.next += 1;
+
+            let requests = match body {
+                serde_json::Value::Array(ref mut requests) => requests.iter_mut().collect(),
+                ref mut request => vec![request],
+            };
+            if requests.len() >= 1 << INDEX_BITS {
+                bail!("batch of {} requests is too large", requests.len());
+            }
+            let mut ids = Vec::with_capacity(requests.len());
+            for (index, request) in requests.into_iter().enumerate() {
+                let id = sequence << INDEX_BITS | index as u64;
+                ids.push(request["id"].take());
+                request["id"] = id.into();
+            }
+            state.waiting.insert(sequence, Waiting { ids, reply });
+            sequence
+        };
+
+        // Stop waiting if the caller gives up, e.g. on a timeout
+        let _waiting = Forget(self, sequence);
+        if self.outgoing.send(body.to_string()).is_err() {
+            bail!("connection closed");
+        }
+        response
+            .await
+            .map_err(|_| eyre!("connection closed before responding"))
+    }
+
+    /// Hand a message from the connection to the request it answers.
+    /// Notifications and unknown responses are dropped.
+    pub fn dispatch(&self, mut message: serde_json::Value) {
+        let responses = match message {
+            serde_json::Value::Array(ref mut responses) => responses.iter_mut().collect(),
+            ref mut response => vec![response],
+        };
+        let Some(sequence) = responses
+            .iter()
+            .find_map(|response| response["id"].as_u64())
+            .map(|id| id >> INDEX_BITS)
+        else {
+            return;
+        };
+        let Some(waiting) = self.state.lock().unwrap().waiting.remove(&sequence) else {
+            return;
+        };
+
+        for response in responses {
+            let index = response["id"]
+                .as_u64()
+                .map(|id| id & ((1 << INDEX_BITS) - 1));
+            if let Some(id) = index.and_then(|index| waiting.ids.get(index as usize)) {
+                response["id"] = id.clone();
+            }
+        }
+        let _ = waiting.reply.send(message);
+    }
+
+    /// Fail every request in flight and refuse new ones.
+    pub fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        state.waiting.clear();
+    }
+}
+
+struct Forget<'a>(&'a Multiplexer, u64);
+
+impl Drop for Forget<'_> {
+    fn drop(&mut self) {
+        self.0.state.lock().unwrap().waiting.remove(&self.1);
+    }
+}
+
+/// A multiplexed connection and the tasks reading and writing it, which end
+/// with it.
+pub struct Link {
+    pub mux: std::sync::Arc<Multiplexer>,
+    pub tasks: Vec<JoinHandle<()>>,
+}
+
+impl Drop for Link {
+    fn drop(&mut self) {
+        self.mux.close();
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_responses_matched_out_of_order() {
+        let (mux, mut messages) = Multiplexer::new();
+        let mux = Arc::new(mux);
+
+        let first = tokio::spawn({
+            let mux = mux.clone();
+            async move { mux.request(json!({"id": 1, "method": "a"})).await }
+        });
+        let sent_first: serde_json::Value =
+            serde_json::from_str(&messages.recv().await.unwrap()).unwrap();
+        let second = tokio::spawn({
+            let mux = mux.clone();
+            async move {
+                mux.request(json!([{"id": 1, "method": "b"}, {"id": "x", "method": "c"}]))
+                    .await
+            }
+        });
+        let sent_second: serde_json::Value =
+            serde_json::from_str(&messages.recv().await.unwrap()).unwrap();
+
+        // Both use id 1, but travel with distinct ones
+        assert_ne!(sent_first["id"], sent_second[0]["id"]);
+
+        mux.dispatch(json!({"method": "eth_subscription", "params": {}}));
+        mux.dispatch(json!([
+            {"id": sent_second[1]["id"], "result": "c"},
+            {"id": sent_second[0]["id"], "result": "b"},
+        ]));
+        mux.dispatch(json!({"id": sent_first["id"], "result": "a"}));
+
+        assert_eq!(
+            first.await.unwrap().unwrap(),
+            json!({"id": 1, "result": "a"})
+        );
+        assert_eq!(
+            second.await.unwrap().unwrap(),
+            json!([{"id": "x", "result": "c"}, {"id": 1, "result": "b"}])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_close_fails_requests_in_flight() {
+        let (mux, mut messages) = Multiplexer::new();
+        let mux = Arc::new(mux);
+
+        let request = tokio::spawn({
+            let mux = mux.clone();
+            async move { mux.request(json!({"id": 1, "method": "a"})).await }
+        });
+        messages.recv().await.unwrap();
+        mux.close();
+
+        assert!(request.await.unwrap().is_err());
+        assert!(mux.request(json!({"id": 2})).await.is_err());
+    }
+}
@@ -0,0 +1,244 @@
+//! Recording of upstream traffic to a JSONL file, and replay of a recording
+//! as the upstream.
+//!
+//! Each line holds one exchange: the request body and either the response
+//! body, proof bytes included, or the error the transport failed with. The
+//! light client's answers are recorded the same way (see
+//! [`crate::consensus::RecordingSource`]), so replaying a recording
+//! reproduces a node's view of both the upstream and the chain offline.
+
+use async_trait::async_trait;
+use eyre::{eyre, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::{mpsc, Mutex};
+use tracing::{info, warn};
+
+use super::Transport;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Exchange {
+    request: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    response: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// A recording file. Exchanges are handed to a writer thread, so requests
+/// never wait on the disk.
+pub struct Recording {
+    lines: mpsc::Sender<Vec<u8>>,
+}
+
+impl Recording {
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .wrap_err_with(|| format!("Failed to open recording {}", path.display()))?;
+
+        let (lines, queued) = mpsc::channel::<Vec<u8>>();
+        std::thread::Builder::new()
+            .name("recording".to_string())
+            .spawn(move || {
+                // Runs until every sender is dropped
+                for line in queued {
+                    // A failed write loses the recording, not the request
+                    if let Err(e) = file.write_all(&line) {
+                        warn!("Failed to record upstream exchange: {}", e);
+                    }
+                }
+            })
+            .wrap_err("Failed to start recording thread")?;
+
+        info!(path = %path.display(), "Recording upstream traffic");
+        Ok(Self { lines })
+    }
+
+    /// Queue an exchange to be appended.
+    pub fn append(&self, request: serde_json::Value, result: &Result<serde_json::Value>) {
+        let exchange = match result {
+            Ok(response) => Exchange {
+                request,
+                response: Some(response.clone()),
+                error: None,
+            },
+            Err(e) => Exchange {
+                request,
+                response: None,
+                error: Some(e.to_string()),
+            },
+        };
+        let Ok(mut line) = serde_json::to_vec(&exchange) else {
+            return;
+        };
+        line.push(b'\n');
+        let _ = self.lines.send(line);
+    }
+}
+
+/// Sends requests over `T`, appending each exchange to a recording.
+pub struct RecordingTransport<T> {
+    inner: T,
+    recording: Recording,
+}
+
+impl<T: Transport> RecordingTransport<T> {
+    pub fn new(inner: T, path: &Path) -> Result<Self> {
+        Ok(Self {
+            inner,
+            recording: Recording::open(path)?,
+        })
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for RecordingTransport<T> {
+    async fn send(&self, body: serde_json::Value) -> Result<serde_json::Value> {
+        let result = self.inner.send(body.clone()).await;
+        self.recording.append(body, &result);
+        result
+    }
+}
+
+/// Answers requests from a recording. Identical requests are answered in
+/// the order they were recorded, the last answer repeating once the rest are
+/// used up; requests that were never recorded fail.
+pub struct ReplayTransport {
+    exchanges: Mutex<HashMap<String, VecDeque<Exchange>>>,
+}
+
+impl ReplayTransport {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .wrap_err_with(|| format!("Failed to open recording {}", path.display()))?;
+
+        let mut exchanges: HashMap<String, VecDeque<Exchange>> = HashMap::new();
+        let mut count = 0;
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let exchange: Exchange = serde_json::from_str(&line)
+                .wrap_err_with(|| format!("Invalid recording at line {}", number + 1))?;
+            exchanges
+                .entry(exchange.request.to_string())
+                .or_default()
+                .push_back(exchange);
+            count += 1;
+        }
+
+        info!(path = %path.display(), exchanges = count, "Replaying upstream traffic");
+        Ok(Self {
+            exchanges: Mutex::new(exchanges),
+        })
+    }
+}
+
+#[async_trait]
+impl Transport for ReplayTransport {
+    async fn send(&self, body: serde_json::Value) -> Result<serde_json::Value> {
+        let exchange = {
+            let mut exchanges = self.exchanges.lock().unwrap();
+            let recorded = exchanges
+                .get_mut(&body.to_string())
+                .ok_or_else(|| eyre!("No recorded response for {}", method(&body)))?;
+            match recorded.len() {
+                1 => recorded[0].clone(),
+                _ => recorded.pop_front().unwrap(),
+            }
+        };
+
+        match exchange {
+            Exchange {
+                response: Some(response),
+                ..
+            } => Ok(response),
+            Exchange { error, .. } => Err(eyre!(error.unwrap_or_default())),
+        }
+    }
+}
+
+/// Method of a request, or of the first request in a batch.
+fn method(body: &serde_json::Value) -> &str {
+    let request = body.as_array().and_then(|b| b.first()).unwrap_or(body);
+    request["method"].as_str().unwrap_or("request")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// Answers with an incrementing block number.
+    struct Counter(AtomicU64);
+
+    #[async_trait]
+    impl Transport for Counter {
+        async fn send(&self, body: serde_json::Value) -> Result<serde_json::Value> {
+            if body["method"] == "eth_chainId" {
+                return Err(eyre!("connection refused"));
+            }
+            let number = self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(json!({"jsonrpc": "2.0", "id": 1, "result": format!("0x{:x}", number)}))
+        }
+    }
+
+    /// Wait for the writer thread to append `count` lines.
+    async fn wait_for_lines(path: &Path, count: usize) {
+        for _ in 0..100 {
+            let written = std::fs::read_to_string(path).unwrap_or_default();
+            if written.lines().count() >= count {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("recording was not written");
+    }
+
+    #[tokio::test]
+    async fn test_replay_recorded_exchanges() {
+        let path =
+            std::env::temp_dir().join(format!("dvrpc-record-test-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let request =
+            |method: &str| json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": []});
+
+        let recorder = RecordingTransport::new(Counter(AtomicU64::new(7)), &path).unwrap();
+        recorder.send(request("eth_blockNumber")).await.unwrap();
+        recorder.send(request("eth_blockNumber")).await.unwrap();
+        assert!(recorder.send(request("eth_chainId")).await.is_err());
+        drop(recorder);
+        wait_for_lines(&path, 3).await;
+
+        let replay = ReplayTransport::load(&path).unwrap();
+        let block = |response: serde_json::Value| response["result"].clone();
+        assert_eq!(
+            block(replay.send(request("eth_blockNumber")).await.unwrap()),
+            "0x7"
+        );
+        assert_eq!(
+            block(replay.send(request("eth_blockNumber")).await.unwrap()),
+            "0x8"
+        );
+        // The last answer repeats
+        assert_eq!(
+            block(replay.send(request("eth_blockNumber")).await.unwrap()),
+            "0x8"
+        );
+
+        let err = replay.send(request("eth_chainId")).await.unwrap_err();
+        assert_eq!(err.to_string(), "connection refused");
+        let err = replay.send(request("eth_gasPrice")).await.unwrap_err();
+        assert_eq!(err.to_string(), "No recorded response for eth_gasPrice");
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
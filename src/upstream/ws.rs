@@ -0,0 +1,115 @@
+//! JSON-RPC over a WebSocket connection.
+
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use futures::{SinkExt, StreamExt};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use super::mux::{Link, Multiplexer};
+use super::Transport;
+use crate::config::redact_url;
+
+/// Time allowed for one request, including connecting.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// One WebSocket connection, opened on first use and reopened once it
+/// fails. Requests are multiplexed over it by JSON-RPC id, so a slow request
+/// does not hold up the others.
+pub struct WsTransport {
+    url: String,
+    /// The URL without path or query, which often carry an API key.
+    redacted: String,
+    link: Mutex<Option<Arc<Link>>>,
+}
+
+impl WsTransport {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            redacted: redact_url(url),
+            link: Mutex::new(None),
+        }
+    }
+
+    /// The open connection, connecting if there is none.
+    async fn link(&self) -> Result<Arc<Link>> {
+        let mut link = self.link.lock().await;
+        if let Some(open) = link.as_ref().filter(|open| !open.mux.is_closed()) {
+            return Ok(open.clone());
+        }
+        let (stream, _) = tokio_tungstenite::connect_async(self.url.as_str())
+            .await
+            .map_err(|e| match e {
+                // URL errors repeat the full URL
+                tungstenite::Error::Url(_) => {
+                    eyre!("WebSocket connection to {} failed", self.redacted)
+                }
+                e => eyre!("WebSocket connection to {} failed: {}", self.redacted, e),
+            })?;
+        let open = Arc::new(open(stream));
+        *link = Some(open.clone());
+        Ok(open)
+    }
+}
+
+#[async_trait]
+impl Transport for WsTransport {
+    async fn send(&self, body: serde_json::Value) -> Result<serde_json::Value> {
+        tokio::time::timeout(REQUEST_TIMEOUT, async {
+            self.link().await?.mux.request(body).await
+        })
+        .await
+        .unwrap_or_else(|_| Err(eyre!("WebSocket request timed out")))
+    }
+}
+
+/// Start the tasks writing requests to and reading responses from `stream`.
+fn open(stream: WebSocketStream<MaybeTlsStream<TcpStream>>) -> Link {
+    let (mux, mut outgoing) = Multiplexer::new();
+    let mux = Arc::new(mux);
+    let (mut sink, mut stream) = stream.split();
+
+    let writer = tokio::spawn({
+        let mux = mux.clone();
+        async move {
+            while let Some(text) = outgoing.recv().await {
+                if sink.send(Message::Text(text)).await.is_err() {
+                    break;
+                }
+            }
+            mux.close();
+        }
+    });
+    let reader = tokio::spawn({
+        let mux = mux.clone();
+        async move {
+            while let Some(Ok(message)) = stream.next().await {
+                let text = match message {
+                    Message::Text(text) => text,
+                    Message::Binary(data) => match String::from_utf8(data) {
+                        Ok(text) => text,
+                        Err(_) => break,
+                    },
+                    Message::Close(_) => break,
+                    // Pings are answered by the next read or write
+                    _ => continue,
+                };
+                match serde_json::from_str(&text) {
+                    Ok(message) => mux.dispatch(message),
+                    Err(_) => break,
+                }
+            }
+            mux.close();
+        }
+    });
+
+    Link {
+        mux,
+        tasks: vec![writer, reader],
+    }
+}
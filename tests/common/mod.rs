@@ -0,0 +1,371 @@
+//! A node running the real router against a mock execution RPC server and a
+//! fixed consensus chain.
+//!
+//! The mock server holds state in an in-memory trie and answers
+//! `eth_getProof` with genuine EIP-1186 proofs, which can be tampered with
+//! to check that the node refuses them.
+
+// Each test binary uses a different subset of these helpers
+#![allow(dead_code)]
+
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256, U64};
+use alloy_rlp::{Encodable, RlpEncodable};
+use axum::{extract::State, http::HeaderMap, routing::post, Json, Router};
+use rcgen::{BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use dvrpc_node::chain::ChainSpec;
+use dvrpc_node::config::Config;
+use dvrpc_node::consensus::{ConsensusClient, FixedChain, VerifiedHeader};
+use dvrpc_node::metrics::Metrics;
+use dvrpc_node::proof::trie::MemoryTrie;
+use dvrpc_node::proof::ProofGenerator;
+use dvrpc_node::rpc::{self, AppState};
+use dvrpc_node::types::{ProofData, StorageProofData};
+use dvrpc_node::upstream::UpstreamClient;
+
+/// Block the mock chain is at.
+pub const HEAD: u64 = 100;
+
+#[derive(Debug, Clone, Default)]
+pub struct Account {
+    pub nonce: u64,
+    pub balance: U256,
+    pub code: Bytes,
+    pub storage: BTreeMap<B256, U256>,
+    /// `eth_call` outputs by calldata.
+    pub calls: BTreeMap<Bytes, Bytes>,
+}
+
+/// How the mock server lies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tamper {
+    None,
+    /// Report a balance one wei higher than the proven one.
+    Balance,
+    /// Flip a byte in the last node of every account proof.
+    ProofNode,
+    /// Serve code that does not match the proven code hash.
+    Code,
+    /// Answer every proof request with a genuine proof of another account.
+    OtherAccount,
+}
+
+/// Account whose proof `Tamper::OtherAccount` serves.
+pub const DECOY: Address = Address::repeat_byte(0xdc);
+
+#[derive(RlpEncodable)]
+struct AccountRlp {
+    nonce: u64,
+    balance: U256,
+    storage_root: B256,
+    code_hash: B256,
+}
+
+struct MockExecution {
+    accounts: BTreeMap<Address, Account>,
+    state: MemoryTrie,
+    tamper: Tamper,
+    /// `traceparent` headers of requests received from the node.
+    traceparents: Mutex<Vec<String>>,
+}
+
+impl MockExecution {
+    fn new(accounts: BTreeMap<Address, Account>, tamper: Tamper) -> Self {
+        let mut state = MemoryTrie::new();
+        for (address, account) in &accounts {
+            let mut encoded = Vec::new();
+            AccountRlp {
+                nonce: account.nonce,
+                balance: account.balance,
+                storage_root: storage_trie(account).root(),
+                code_hash: keccak256(&account.code),
+            }
+            .encode(&mut encoded);
+            state.insert(keccak256(address), encoded);
+        }
+        Self {
+            accounts,
+            state,
+            tamper,
+            traceparents: Mutex::new(vec![]),
+        }
+    }
+
+    fn proof(&self, address: Address, slots: Vec<B256>) -> ProofData {
+        let address = match self.tamper {
+            Tamper::OtherAccount => DECOY,
+            _ => address,
+        };
+        let account = self.accounts.get(&address).cloned().unwrap_or_default();
+        let storage = storage_trie(&account);
+
+        let mut account_proof = self.state.proof(keccak256(address));
+        if self.tamper == Tamper::ProofNode {
+            let last = account_proof.last_mut().unwrap();
+            let mut node = last.to_vec();
+            let end = node.len() - 1;
+            node[end] ^= 0x01;
+            *last = node.into();
+        }
+
+        ProofData {
+            address,
+            balance: match self.tamper {
+                Tamper::Balance => account.balance + U256::from(1),
+                _ => account.balance,
+            },
+            // Absent accounts prove as empty, with the hash of empty code
+            code_hash: keccak256(&account.code),
+            nonce: U64::from(account.nonce),
+            storage_hash: storage.root(),
+            account_proof,
+            storage_proof: slots
+                .into_iter()
+                .map(|key| StorageProofData {
+                    key,
+                    value: account.storage.get(&key).copied().unwrap_or_default(),
+                    proof: storage.proof(keccak256(key)),
+                })
+                .collect(),
+        }
+    }
+
+    fn answer(&self, request: &Value) -> Value {
+        let id = request["id"].clone();
+        let params = &request["params"];
+        let block = |index: usize| params[index].as_str().unwrap_or("latest");
+        let at_head = |block: &str| block == "latest" || block == format!("0x{:x}", HEAD);
+
+        let result = match request["method"].as_str().unwrap_or_default() {
+            "eth_blockNumber" => json!(format!("0x{:x}", HEAD)),
+            "eth_getProof" if at_head(block(2)) => {
+                let address = serde_json::from_value(params[0].clone()).unwrap();
+                let slots = serde_json::from_value(params[1].clone()).unwrap();
+                serde_json::to_value(self.proof(address, slots)).unwrap()
+            }
+            "eth_getCode" if at_head(block(1)) => {
+                let address: Address = serde_json::from_value(params[0].clone()).unwrap();
+                let code = self
+                    .accounts
+                    .get(&address)
+                    .map(|a| a.code.clone())
+                    .unwrap_or_default();
+                match self.tamper {
+                    Tamper::Code => json!(Bytes::from([code.as_ref(), &[0x00]].concat())),
+                    _ => json!(code),
+                }
+            }
+            "eth_call" => {
+                let to: Address = serde_json::from_value(params[0]["to"].clone()).unwrap();
+                let data: Bytes = serde_json::from_value(params[0]["data"].clone()).unwrap();
+                let output = self
+                    .accounts
+                    .get(&to)
+                    .and_then(|a| a.calls.get(&data).cloned())
+                    .unwrap_or_default();
+                json!(output)
+            }
+            // No transaction is ever mined
+            "eth_getTransactionReceipt" => Value::Null,
+            "eth_getProof" | "eth_getCode" => {
+                return json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {"code": -32000, "message": "header not found"}
+                })
+            }
+            _ => {
+                return json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {"code": -32601, "message": "method not found"}
+                })
+            }
+        };
+        json!({"jsonrpc": "2.0", "id": id, "result": result})
+    }
+}
+
+fn storage_trie(account: &Account) -> MemoryTrie {
+    let mut trie = MemoryTrie::new();
+    for (slot, value) in &account.storage {
+        let mut encoded = Vec::new();
+        value.encode(&mut encoded);
+        trie.insert(keccak256(slot), encoded);
+    }
+    trie
+}
+
+async fn mock_handler(
+    State(mock): State<Arc<MockExecution>>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Json<Value> {
+    if let Some(traceparent) = headers.get("traceparent").and_then(|v| v.to_str().ok()) {
+        mock.traceparents
+            .lock()
+            .unwrap()
+            .push(traceparent.to_string());
+    }
+    Json(match body {
+        Value::Array(requests) => requests.iter().map(|r| mock.answer(r)).collect(),
+        request => mock.answer(&request),
+    })
+}
+
+/// A certificate and its key.
+pub struct Issued {
+    pub cert: rcgen::Certificate,
+    pub key: KeyPair,
+}
+
+/// A self-signed CA to issue test certificates from.
+pub fn new_ca() -> Issued {
+    let key = KeyPair::generate().unwrap();
+    let mut params = CertificateParams::new(vec![]).unwrap();
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "test ca");
+    let cert = params.self_signed(&key).unwrap();
+    Issued { cert, key }
+}
+
+/// A certificate for `name`, as its DNS name and common name, signed by `ca`.
+pub fn issue(ca: &Issued, name: &str, usage: ExtendedKeyUsagePurpose) -> Issued {
+    let key = KeyPair::generate().unwrap();
+    let mut params = CertificateParams::new(vec![name.to_string()]).unwrap();
+    params.distinguished_name.push(DnType::CommonName, name);
+    params.extended_key_usages = vec![usage];
+    let cert = params.signed_by(&key, &ca.cert, &ca.key).unwrap();
+    Issued { cert, key }
+}
+
+/// Serve `app` on an ephemeral local port.
+async fn spawn_server(app: Router) -> SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    addr
+}
+
+/// A running node and the client to query it.
+pub struct TestNode {
+    pub url: String,
+    pub client: reqwest::Client,
+    pub state: Arc<AppState>,
+    mock: Arc<MockExecution>,
+    /// Config the next reload reads.
+    next_config: Arc<Mutex<Config>>,
+}
+
+impl TestNode {
+    pub async fn start(accounts: BTreeMap<Address, Account>, tamper: Tamper) -> Self {
+        Self::start_with(accounts, tamper, "").await
+    }
+
+    /// Start a node with `extra` appended to its TOML config.
+    pub async fn start_with(
+        accounts: BTreeMap<Address, Account>,
+        tamper: Tamper,
+        extra: &str,
+    ) -> Self {
+        let mock = Arc::new(MockExecution::new(accounts, tamper));
+        let header = VerifiedHeader {
+            number: HEAD,
+            hash: B256::repeat_byte(0xbb),
+            state_root: mock.state.root(),
+            transactions_root: B256::ZERO,
+            receipts_root: B256::ZERO,
+            timestamp: 1_700_000_000,
+        };
+        let execution = spawn_server(
+            Router::new()
+                .route("/", post(mock_handler))
+                .with_state(mock.clone()),
+        )
+        .await;
+
+        let config: Config = toml::from_str(&format!(
+            r#"
+            [server]
+            [ethereum]
+            network = "mainnet"
+            execution_rpc = "http://{execution}"
+            consensus_rpc = "http://localhost:5052"
+            [consensus]
+            [proof]
+            {extra}
+            "#
+        ))
+        .unwrap();
+
+        let metrics = Arc::new(Metrics::new());
+        let upstream =
+            UpstreamClient::new(&config.ethereum.execution_rpc, metrics.clone()).unwrap();
+        let consensus =
+            ConsensusClient::with_source(FixedChain::new([header]), ChainSpec::mainnet());
+        let proof_generator = ProofGenerator::new(&config);
+        let state = AppState::new(
+            config,
+            Some(consensus),
+            proof_generator,
+            upstream,
+            metrics,
+            None,
+        )
+        .unwrap();
+
+        let next_config = Arc::new(Mutex::new(config.clone()));
+        let loader = {
+            let next_config = next_config.clone();
+            move || Ok(next_config.lock().unwrap().clone())
+        };
+        let state = Arc::new(state.with_loader(Arc::new(loader)));
+        let addr = spawn_server(rpc::router(state.clone())).await;
+        Self {
+            url: format!("http://{addr}"),
+            client: reqwest::Client::new(),
+            state,
+            mock,
+            next_config,
+        }
+    }
+
+    /// Reload the config, changed by `change`.
+    pub async fn reload_with(&self, change: impl FnOnce(&mut Config)) -> eyre::Result<()> {
+        change(&mut self.next_config.lock().unwrap());
+        self.state.reload().await
+    }
+
+    /// `traceparent` headers the node sent to the execution RPC.
+    pub fn upstream_traceparents(&self) -> Vec<String> {
+        self.mock.traceparents.lock().unwrap().clone()
+    }
+
+    /// Send a JSON-RPC request and return the response body.
+    pub async fn call(&self, method: &str, params: Value) -> Value {
+        self.send(
+            json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params}).to_string(),
+        )
+        .await
+    }
+
+    /// Send a raw request body and return the response body.
+    pub async fn send(&self, body: String) -> Value {
+        self.client
+            .post(&self.url)
+            .header("content-type", "application/json")
+            .body(body)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap()
+    }
+}
@@ -0,0 +1,134 @@
+//! JSON-RPC over the Unix domain socket endpoint.
+
+#![cfg(unix)]
+
+mod common;
+
+use alloy_primitives::{Address, B256, U256};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::os::unix::fs::PermissionsExt;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::unix::OwnedReadHalf;
+use tokio::net::UnixStream;
+
+use common::{Account, Tamper, TestNode};
+use dvrpc_node::rpc;
+
+/// Read the next newline-terminated response.
+async fn response(lines: &mut Lines<BufReader<OwnedReadHalf>>) -> Value {
+    let line = lines.next_line().await.unwrap().unwrap();
+    serde_json::from_str(&line).unwrap()
+}
+
+#[tokio::test]
+async fn test_ipc_requests() {
+    let alice = Address::repeat_byte(0xa1);
+    let accounts = BTreeMap::from([(
+        alice,
+        Account {
+            balance: U256::from(1000),
+            ..Account::default()
+        },
+    )]);
+    let node = TestNode::start(accounts, Tamper::None).await;
+
+    let path = std::env::temp_dir().join(format!("dvrpc-ipc-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = rpc::bind_ipc(&path, 0o660).unwrap();
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o660);
+    assert!(
+        rpc::bind_ipc(&path, 0o600).is_err(),
+        "socket in use rebound"
+    );
+    let server = tokio::spawn(rpc::serve_ipc(node.state.clone(), listener));
+
+    let (reader, mut writer) = UnixStream::connect(&path).await.unwrap().into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    // The first request and part of the second in one write
+    let first =
+        json!({"jsonrpc": "2.0", "id": 1, "method": "eth_getBalance", "params": [alice, "latest"]});
+    let second = json!({"jsonrpc": "2.0", "id": 2, "method": "eth_noSuchMethod", "params": []});
+    let second = second.to_string();
+    let (head, tail) = second.split_at(10);
+    writer
+        .write_all(format!("{first}\n{head}").as_bytes())
+        .await
+        .unwrap();
+    let balance = response(&mut lines).await;
+    assert_eq!(balance["id"], 1);
+    assert_eq!(balance["result"], "0x3e8");
+
+    writer.write_all(tail.as_bytes()).await.unwrap();
+    let missing = response(&mut lines).await;
+    assert_eq!(missing["id"], 2);
+    assert_eq!(missing["error"]["code"], -32601);
+
+    writer.write_all(b"{not json").await.unwrap();
+    assert_eq!(response(&mut lines).await["error"]["code"], -32700);
+    assert!(lines.next_line().await.unwrap().is_none());
+
+    // A socket left behind by a stopped node is replaced
+    server.abort();
+    let _ = server.await;
+    rpc::bind_ipc(&path, 0o600).unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_ipc_subscription() {
+    let node = TestNode::start(BTreeMap::new(), Tamper::None).await;
+
+    let path = std::env::temp_dir().join(format!("dvrpc-ipc-sub-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = rpc::bind_ipc(&path, 0o600).unwrap();
+    let server = tokio::spawn(rpc::serve_ipc(node.state.clone(), listener));
+
+    let (reader, mut writer) = UnixStream::connect(&path).await.unwrap().into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    let hash = B256::repeat_byte(0x7a);
+    let subscribe = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "eth_subscribe",
+        "params": ["dvrpc_transactionInclusion", hash],
+    });
+    writer
+        .write_all(subscribe.to_string().as_bytes())
+        .await
+        .unwrap();
+    let subscribed = response(&mut lines).await;
+    assert_eq!(subscribed["id"], 1);
+    let subscription = subscribed["result"].as_str().unwrap().to_string();
+
+    // The upstream has no receipt, so the transaction is reported pending
+    let notification = response(&mut lines).await;
+    assert_eq!(notification["method"], "eth_subscription");
+    assert_eq!(notification["params"]["subscription"], subscription);
+    assert_eq!(
+        notification["params"]["result"]["transactionHash"],
+        json!(hash)
+    );
+    assert_eq!(notification["params"]["result"]["state"], "pending");
+
+    let unsubscribe = json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "eth_unsubscribe",
+        "params": [subscription],
+    });
+    writer
+        .write_all(unsubscribe.to_string().as_bytes())
+        .await
+        .unwrap();
+    let unsubscribed = response(&mut lines).await;
+    assert_eq!(unsubscribed["id"], 2);
+    assert_eq!(unsubscribed["result"], true);
+
+    server.abort();
+    let _ = server.await;
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,392 @@
+//! End-to-end tests of verified methods through the HTTP router.
+
+mod common;
+
+use alloy_primitives::{Address, Bytes, B256, U256};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+use common::{Account, Tamper, TestNode};
+
+fn alice() -> Address {
+    Address::repeat_byte(0xa1)
+}
+
+fn token() -> Address {
+    Address::repeat_byte(0x70)
+}
+
+fn accounts() -> BTreeMap<Address, Account> {
+    let mut accounts = BTreeMap::new();
+    accounts.insert(
+        alice(),
+        Account {
+            nonce: 3,
+            balance: U256::from(1_000_000_000_000_000_000u64),
+            ..Account::default()
+        },
+    );
+    accounts.insert(
+        token(),
+        Account {
+            nonce: 1,
+            code: Bytes::from_static(&[0x60, 0x80, 0x60, 0x40, 0x52]),
+            storage: [(B256::with_last_byte(1), U256::from(42))].into(),
+            ..Account::default()
+        },
+    );
+    // Enough neighbours that proofs run through branch nodes
+    for i in 0..16u8 {
+        accounts.insert(
+            Address::with_last_byte(i),
+            Account {
+                balance: U256::from(i),
+                ..Account::default()
+            },
+        );
+    }
+    accounts
+}
+
+fn error_code(response: &Value) -> i64 {
+    response["error"]["code"].as_i64().unwrap()
+}
+
+#[tokio::test]
+async fn test_honest_proofs_verify() {
+    let node = TestNode::start(accounts(), Tamper::None).await;
+
+    let balance = node
+        .call("eth_getBalance", json!([alice(), "latest"]))
+        .await;
+    assert_eq!(balance["result"], "0xde0b6b3a7640000");
+
+    let nonce = node
+        .call("eth_getTransactionCount", json!([alice(), "latest"]))
+        .await;
+    assert_eq!(nonce["result"], "0x3");
+
+    let storage = node
+        .call(
+            "eth_getStorageAt",
+            json!([token(), B256::with_last_byte(1), "latest"]),
+        )
+        .await;
+    assert_eq!(
+        storage["result"].as_str().unwrap().parse::<U256>().unwrap(),
+        U256::from(42)
+    );
+
+    let code = node.call("eth_getCode", json!([token(), "latest"])).await;
+    assert_eq!(code["result"], "0x6080604052");
+}
+
+#[tokio::test]
+async fn test_missing_account_proven_absent() {
+    let node = TestNode::start(accounts(), Tamper::None).await;
+
+    let missing = Address::repeat_byte(0xee);
+    let balance = node
+        .call("eth_getBalance", json!([missing, "latest"]))
+        .await;
+    assert_eq!(balance["result"], "0x0");
+
+    let code = node.call("eth_getCode", json!([missing, "latest"])).await;
+    assert_eq!(code["result"], "0x");
+}
+
+#[tokio::test]
+async fn test_tampered_responses_rejected() {
+    for tamper in [Tamper::Balance, Tamper::ProofNode] {
+        let node = TestNode::start(accounts(), tamper).await;
+        let response = node
+            .call("eth_getBalance", json!([alice(), "latest"]))
+            .await;
+        assert!(response.get("result").is_none(), "{tamper:?} accepted");
+        assert_eq!(error_code(&response), -32603);
+    }
+
+    let node = TestNode::start(accounts(), Tamper::Code).await;
+    let response = node.call("eth_getCode", json!([token(), "latest"])).await;
+    assert!(response.get("result").is_none());
+    assert_eq!(error_code(&response), -32603);
+}
+
+#[tokio::test]
+async fn test_multi_account_proof() {
+    let query = json!([[alice(), {"address": token(), "storageKeys": [B256::with_last_byte(1)]}]]);
+
+    let node = TestNode::start(accounts(), Tamper::None).await;
+    let response = node.call("dvrpc_getMultiAccountProof", query.clone()).await;
+    let results = &response["result"]["accounts"];
+    assert_eq!(results[0]["verified"], true);
+    assert_eq!(results[0]["balance"], "0xde0b6b3a7640000");
+    assert_eq!(results[1]["verified"], true);
+    assert_eq!(results[1]["storage"][0]["value"], "0x2a");
+
+    let node = TestNode::start(accounts(), Tamper::Balance).await;
+    let response = node.call("dvrpc_getMultiAccountProof", query.clone()).await;
+    for account in response["result"]["accounts"].as_array().unwrap() {
+        assert_eq!(account["verified"], false);
+        assert!(account.get("balance").is_none());
+    }
+
+    // Valid proofs of the wrong account are refused outright
+    let node = TestNode::start(accounts(), Tamper::OtherAccount).await;
+    let response = node.call("dvrpc_getMultiAccountProof", query).await;
+    assert_eq!(error_code(&response), -32603);
+
+    let many: Vec<Address> = (0..=256u16)
+        .map(|i| {
+            let mut address = [0u8; 20];
+            address[18..].copy_from_slice(&i.to_be_bytes());
+            Address::from(address)
+        })
+        .collect();
+    let response = node.call("dvrpc_getMultiAccountProof", json!([many])).await;
+    assert_eq!(error_code(&response), -32602);
+}
+
+#[tokio::test]
+async fn test_error_codes() {
+    let node = TestNode::start(accounts(), Tamper::None).await;
+
+    let response = node.send("{not json".to_string()).await;
+    assert_eq!(error_code(&response), -32700);
+
+    let response = node.call("eth_noSuchMethod", json!([])).await;
+    assert_eq!(error_code(&response), -32601);
+
+    let response = node
+        .call("eth_getBalance", json!(["0x1234", "latest"]))
+        .await;
+    assert_eq!(error_code(&response), -32602);
+
+    // The upstream does not have the block
+    let response = node.call("eth_getBalance", json!([alice(), "0x5"])).await;
+    assert_eq!(error_code(&response), -32603);
+}
+
+#[tokio::test]
+async fn test_request_id_echoed() {
+    let node = TestNode::start(accounts(), Tamper::None).await;
+    let body = json!({"jsonrpc": "2.0", "id": 1, "method": "eth_chainId", "params": []});
+
+    let response = node
+        .client
+        .post(&node.url)
+        .header("x-request-id", "trace-abc-123")
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["x-request-id"], "trace-abc-123");
+
+    // Without one, or with an unusable one, the node generates an ID
+    let response = node
+        .client
+        .post(&node.url)
+        .header("x-request-id", "has spaces")
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    let generated = response.headers()["x-request-id"].to_str().unwrap();
+    assert_eq!(generated.len(), 16);
+}
+
+#[tokio::test]
+async fn test_metrics_not_on_rpc_listener() {
+    let node = TestNode::start(accounts(), Tamper::None).await;
+    node.call("eth_chainId", json!([])).await;
+
+    let response = node
+        .client
+        .get(format!("{}/metrics", node.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = dvrpc_node::rpc::metrics_router(node.state.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let body = node
+        .client
+        .get(format!("http://{addr}/metrics"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(body.contains("dvrpc_requests_total"));
+}
+
+#[tokio::test]
+async fn test_reload_applies_body_limit() {
+    let node = TestNode::start(accounts(), Tamper::None).await;
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "eth_chainId",
+        "params": ["x".repeat(1024)],
+    })
+    .to_string();
+    assert_eq!(node.send(request.clone()).await["result"], "0x1");
+
+    node.reload_with(|config| config.server.max_body_size = 512)
+        .await
+        .unwrap();
+    let response = node
+        .client
+        .post(&node.url)
+        .header("content-type", "application/json")
+        .body(request)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 413);
+    let response: Value = response.json().await.unwrap();
+    assert_eq!(error_code(&response), -32700);
+}
+
+#[tokio::test]
+async fn test_reload_refuses_unchecked_upstream() {
+    let node = TestNode::start(accounts(), Tamper::None).await;
+    let upstream = node.state.upstream();
+
+    // Nothing listens there, so its chain cannot be checked
+    let error = node
+        .reload_with(|config| {
+            config.ethereum.execution_source = Some("http://127.0.0.1:1".to_string());
+            config.server.request_timeout_ms = 1_000;
+        })
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("chain check"), "{error}");
+    assert!(std::sync::Arc::ptr_eq(&node.state.upstream(), &upstream));
+    assert_ne!(node.state.config().server.request_timeout_ms, 1_000);
+}
+
+#[tokio::test]
+async fn test_send_raw_transaction_needs_proxy() {
+    let node = TestNode::start(accounts(), Tamper::None).await;
+
+    let response = node
+        .call("eth_sendRawTransaction", json!(["0x02f86c0180"]))
+        .await;
+    assert_eq!(error_code(&response), -32601);
+}
+
+#[tokio::test]
+async fn test_unproven_ens_record_not_attested() {
+    use dvrpc_node::ens::{addr_call, namehash, registry_keys, RecordKind, RecordLayout, REGISTRY};
+
+    let resolver = Address::repeat_byte(0x5e);
+    let owner = U256::from(1);
+    let target = alice();
+
+    let mut registry = Account::default();
+    for name in ["eth", "proven.eth", "unproven.eth"] {
+        let (owner_key, resolver_key) = registry_keys(namehash(name));
+        registry.storage.insert(owner_key, owner);
+        if name != "eth" {
+            registry
+                .storage
+                .insert(resolver_key, U256::from_be_slice(resolver.as_slice()));
+        }
+    }
+
+    // Both names answer with the same address; only one is in resolver storage
+    let mut resolver_account = Account::default();
+    for name in ["proven.eth", "unproven.eth"] {
+        let output = B256::left_padding_from(target.as_slice());
+        resolver_account
+            .calls
+            .insert(addr_call(namehash(name)), Bytes::from(output.to_vec()));
+    }
+    let plain = RecordLayout::Plain { slot: 0 };
+    resolver_account.storage.insert(
+        plain.key(RecordKind::Addr, namehash("proven.eth"), 0),
+        U256::from_be_slice(target.as_slice()),
+    );
+
+    let mut accounts = accounts();
+    accounts.insert(REGISTRY, registry);
+    accounts.insert(resolver, resolver_account);
+
+    let key_file =
+        std::env::temp_dir().join(format!("dvrpc-attestation-test-{}", std::process::id()));
+    std::fs::write(&key_file, hex::encode([0x11; 32])).unwrap();
+    let node = TestNode::start_with(
+        accounts,
+        Tamper::None,
+        &format!(
+            "[attestation]\nkey_file = {:?}",
+            key_file.display().to_string()
+        ),
+    )
+    .await;
+    std::fs::remove_file(&key_file).unwrap();
+
+    let response = node.call("dvrpc_resolveName", json!(["proven.eth"])).await;
+    assert_eq!(response["result"]["recordProven"], true);
+    assert_eq!(response["result"]["address"], json!(target));
+    assert!(response["attestation"].is_object());
+
+    let response = node
+        .call("dvrpc_resolveName", json!(["unproven.eth"]))
+        .await;
+    assert_eq!(response["result"]["resolverProven"], true);
+    assert_eq!(response["result"]["recordProven"], false);
+    assert_eq!(response["result"]["address"], json!(target));
+    assert!(response.get("attestation").is_none());
+}
+
+#[tokio::test]
+async fn test_token_slot_detected_once() {
+    use dvrpc_node::token::{balance_of_call, Layout, MappingSlot};
+
+    let erc20 = Address::repeat_byte(0xe2);
+    let (holder, twice, fresh) = (
+        alice(),
+        Address::repeat_byte(0xc3),
+        Address::repeat_byte(0xd4),
+    );
+    let slot = |index| MappingSlot {
+        index,
+        layout: Layout::Solidity,
+    };
+    let word = |value: u64| Bytes::from(U256::from(value).to_be_bytes::<32>().to_vec());
+
+    let mut token = Account {
+        code: Bytes::from_static(&[0x60, 0x80, 0x60, 0x40, 0x52]),
+        ..Account::default()
+    };
+    token.storage.insert(slot(3).key(holder), U256::from(500));
+    // The same balance also sits in another candidate slot
+    token.storage.insert(slot(3).key(twice), U256::from(7));
+    token.storage.insert(slot(0).key(twice), U256::from(7));
+    token.calls.insert(balance_of_call(holder), word(500));
+    token.calls.insert(balance_of_call(twice), word(7));
+    token.calls.insert(balance_of_call(fresh), word(0));
+    let node = TestNode::start([(erc20, token)].into(), Tamper::None).await;
+    let balance =
+        |owner: Address| node.call("dvrpc_getTokenBalance", json!([erc20, owner, "latest"]));
+
+    // Neither a zero nor an ambiguous value can pick the slot
+    assert_eq!(error_code(&balance(fresh).await), -32000);
+    assert_eq!(error_code(&balance(twice).await), -32000);
+
+    let detected = balance(holder).await;
+    assert_eq!(detected["result"], "0x1f4", "{detected}");
+
+    // Once the slot is known, both are served
+    let zero = balance(fresh).await;
+    assert_eq!(zero["result"], "0x0", "{zero}");
+    let seven = balance(twice).await;
+    assert_eq!(seven["result"], "0x7", "{seven}");
+}
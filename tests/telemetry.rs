@@ -0,0 +1,152 @@
+//! Trace export to a stand-in OTLP/HTTP collector.
+
+mod common;
+
+use alloy_primitives::{Address, U256};
+use axum::{body::Bytes, extract::State, routing::post, Router};
+use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+use prost::Message;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::prelude::*;
+
+use common::{Account, Tamper, TestNode};
+use dvrpc_node::config::TelemetryConfig;
+use dvrpc_node::telemetry;
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const CALLER_SPAN_ID: &str = "00f067aa0ba902b7";
+
+/// A received span, with IDs hex-encoded.
+#[derive(Debug, Clone)]
+struct ExportedSpan {
+    name: String,
+    trace_id: String,
+    span_id: String,
+    parent_span_id: String,
+}
+
+/// Collects the spans posted to `/v1/traces`.
+#[derive(Default)]
+struct Collector {
+    spans: Mutex<Vec<ExportedSpan>>,
+}
+
+async fn collect(State(collector): State<Arc<Collector>>, body: Bytes) {
+    let request = ExportTraceServiceRequest::decode(body).unwrap();
+    let spans = request
+        .resource_spans
+        .into_iter()
+        .flat_map(|r| r.scope_spans)
+        .flat_map(|s| s.spans)
+        .map(|span| ExportedSpan {
+            name: span.name,
+            trace_id: hex::encode(span.trace_id),
+            span_id: hex::encode(span.span_id),
+            parent_span_id: hex::encode(span.parent_span_id),
+        });
+    collector.spans.lock().unwrap().extend(spans);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_request_trace_exported() {
+    let collector = Arc::new(Collector::default());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let app = Router::new()
+        .route("/v1/traces", post(collect))
+        .with_state(collector.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let config = TelemetryConfig {
+        otlp_endpoint: Some(endpoint),
+        ..TelemetryConfig::default()
+    };
+    let provider = telemetry::tracer_provider(&config).unwrap().unwrap();
+    tracing_subscriber::registry()
+        .with(telemetry::layer(&provider))
+        .init();
+
+    let alice = Address::repeat_byte(0xa1);
+    let accounts = [(
+        alice,
+        Account {
+            balance: U256::from(5),
+            ..Account::default()
+        },
+    )];
+    let node = TestNode::start(accounts.into(), Tamper::None).await;
+    let response: serde_json::Value = node
+        .client
+        .post(&node.url)
+        .header("traceparent", format!("00-{TRACE_ID}-{CALLER_SPAN_ID}-01"))
+        .json(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_getBalance",
+            "params": [alice, "latest"],
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(response["result"], "0x5");
+
+    tokio::task::spawn_blocking(move || provider.force_flush())
+        .await
+        .unwrap()
+        .unwrap();
+
+    let spans: Vec<ExportedSpan> = collector
+        .spans
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|span| span.trace_id == TRACE_ID)
+        .cloned()
+        .collect();
+    let span = |name: &str| {
+        spans
+            .iter()
+            .find(|span| span.name == name)
+            .unwrap_or_else(|| panic!("no {name} span in {spans:?}"))
+    };
+
+    // Whether following parents up from `span` reaches `ancestor`
+    let descends_from = |span: &ExportedSpan, ancestor: &ExportedSpan| {
+        let mut parent = span.parent_span_id.clone();
+        for _ in 0..spans.len() {
+            if parent == ancestor.span_id {
+                return true;
+            }
+            match spans.iter().find(|span| span.span_id == parent) {
+                Some(span) => parent = span.parent_span_id.clone(),
+                None => return false,
+            }
+        }
+        false
+    };
+
+    // The request joins the caller's trace, and the steps nest under it
+    let request = span("request");
+    assert_eq!(request.parent_span_id, CALLER_SPAN_ID);
+    for step in ["get_consensus_proof", "upstream", "verify_account_proof"] {
+        let step = span(step);
+        assert_ne!(step.span_id, request.span_id);
+        assert!(
+            descends_from(step, request),
+            "{} is not under the request in {spans:?}",
+            step.name
+        );
+    }
+
+    // The upstream was told which trace its work belongs to
+    let upstream = span("upstream");
+    let traceparents = node.upstream_traceparents();
+    assert!(
+        traceparents.contains(&format!("00-{TRACE_ID}-{}-01", upstream.span_id)),
+        "{traceparents:?}"
+    );
+}
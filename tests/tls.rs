@@ -0,0 +1,324 @@
+//! TLS termination: client certificates, certificate reload, and the API
+//! keys of clients authenticated by their certificate.
+
+mod common;
+
+use alloy_primitives::{Address, U256};
+use rcgen::ExtendedKeyUsagePurpose;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivatePkcs8KeyDer, ServerName};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::{client, server, TlsConnector};
+
+use common::{issue, new_ca, Account, Issued, Tamper, TestNode, HEAD};
+use dvrpc_node::config::TlsConfig;
+use dvrpc_node::rpc;
+use dvrpc_node::tls::{client_certificate, ClientCertificate, TlsTerminator};
+
+fn write(dir: &Path, name: &str, issued: &Issued) -> (PathBuf, PathBuf) {
+    let cert = dir.join(format!("{name}.crt"));
+    let key = dir.join(format!("{name}.key"));
+    std::fs::write(&cert, issued.cert.pem()).unwrap();
+    std::fs::write(&key, issued.key.serialize_pem()).unwrap();
+    (cert, key)
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("dvrpc-tls-test-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Client config trusting `ca`, presenting `client` if given.
+fn client_config(ca: &Issued, client: Option<&Issued>) -> ClientConfig {
+    let mut roots = RootCertStore::empty();
+    roots.add(ca.cert.der().clone()).unwrap();
+    let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots);
+    match client {
+        Some(client) => builder
+            .with_client_auth_cert(
+                vec![client.cert.der().clone()],
+                PrivatePkcs8KeyDer::from(client.key.serialize_der()).into(),
+            )
+            .unwrap(),
+        None => builder.with_no_client_auth(),
+    }
+}
+
+/// Handshake with `terminator` over an in-memory stream, presenting `client`
+/// if given.
+async fn handshake(
+    terminator: &TlsTerminator,
+    ca: &Issued,
+    client: Option<&Issued>,
+) -> std::io::Result<(
+    client::TlsStream<DuplexStream>,
+    server::TlsStream<DuplexStream>,
+)> {
+    let connector = TlsConnector::from(Arc::new(client_config(ca, client)));
+    let (client_io, server_io) = tokio::io::duplex(16 * 1024);
+    let name = ServerName::try_from("localhost").unwrap();
+    let (client, server) = tokio::join!(
+        connector.connect(name, client_io),
+        terminator.acceptor().accept(server_io),
+    );
+    Ok((client?, server?))
+}
+
+/// The certificate the server saw from `client`.
+async fn seen_by_server(
+    terminator: &TlsTerminator,
+    ca: &Issued,
+    client: Option<&Issued>,
+) -> std::io::Result<Option<ClientCertificate>> {
+    let (_, server) = handshake(terminator, ca, client).await?;
+    Ok(client_certificate(server.get_ref().1))
+}
+
+/// The certificate `terminator` serves to new connections.
+async fn served(terminator: &TlsTerminator, ca: &Issued) -> CertificateDer<'static> {
+    let (client, _) = handshake(terminator, ca, None).await.unwrap();
+    client.get_ref().1.peer_certificates().unwrap()[0].clone()
+}
+
+#[tokio::test]
+async fn test_client_certificate_identity() {
+    let dir = temp_dir("mtls");
+    let ca = new_ca();
+    let (cert, key) = write(
+        &dir,
+        "server",
+        &issue(&ca, "localhost", ExtendedKeyUsagePurpose::ServerAuth),
+    );
+    let client_ca = dir.join("ca.crt");
+    std::fs::write(&client_ca, ca.cert.pem()).unwrap();
+    let indexer = issue(&ca, "indexer", ExtendedKeyUsagePurpose::ClientAuth);
+
+    let mut config = TlsConfig {
+        cert,
+        key,
+        client_ca: Some(client_ca),
+        client_cert_optional: false,
+        identities: HashMap::new(),
+    };
+    let required = TlsTerminator::new(&config).unwrap();
+    assert_eq!(
+        seen_by_server(&required, &ca, Some(&indexer))
+            .await
+            .unwrap(),
+        Some(ClientCertificate {
+            common_name: "indexer".to_string()
+        })
+    );
+    assert!(seen_by_server(&required, &ca, None).await.is_err());
+
+    config.client_cert_optional = true;
+    let optional = TlsTerminator::new(&config).unwrap();
+    assert_eq!(seen_by_server(&optional, &ca, None).await.unwrap(), None);
+    // A certificate from another CA is refused even when optional
+    let stranger = issue(&new_ca(), "indexer", ExtendedKeyUsagePurpose::ClientAuth);
+    assert!(seen_by_server(&optional, &ca, Some(&stranger))
+        .await
+        .is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_certificate_reload() {
+    let dir = temp_dir("reload");
+    let ca = new_ca();
+    let first = issue(&ca, "localhost", ExtendedKeyUsagePurpose::ServerAuth);
+    let (cert, key) = write(&dir, "server", &first);
+    let config = TlsConfig {
+        cert: cert.clone(),
+        key: key.clone(),
+        client_ca: None,
+        client_cert_optional: false,
+        identities: HashMap::new(),
+    };
+    let terminator = TlsTerminator::new(&config).unwrap();
+    let resolver = terminator.certificate();
+    assert_eq!(served(&terminator, &ca).await, *first.cert.der());
+    assert!(!resolver.reload_if_changed().unwrap());
+
+    let later = SystemTime::now() + Duration::from_secs(10);
+    let set_modified = |path: &Path| {
+        std::fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap()
+    };
+
+    // A key that does not match keeps the old certificate
+    let second = issue(&ca, "localhost", ExtendedKeyUsagePurpose::ServerAuth);
+    std::fs::write(&cert, second.cert.pem()).unwrap();
+    set_modified(&cert);
+    assert!(resolver.reload_if_changed().is_err());
+    assert_eq!(served(&terminator, &ca).await, *first.cert.der());
+
+    std::fs::write(&key, second.key.serialize_pem()).unwrap();
+    set_modified(&key);
+    assert!(resolver.reload_if_changed().unwrap());
+    assert_eq!(served(&terminator, &ca).await, *second.cert.der());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// An HTTPS client presenting a certificate, speaking just enough HTTP/1.1
+/// to post one request per connection.
+struct Client {
+    connector: TlsConnector,
+    addr: std::net::SocketAddr,
+}
+
+impl Client {
+    fn new(ca: &Issued, cert: &Issued, addr: std::net::SocketAddr) -> Self {
+        Self {
+            connector: TlsConnector::from(Arc::new(client_config(ca, Some(cert)))),
+            addr,
+        }
+    }
+
+    /// Call `method`, sending `api_key` in the header if given, and return
+    /// the response body.
+    async fn call(&self, method: &str, params: Value, api_key: Option<&str>) -> Value {
+        let stream = tokio::net::TcpStream::connect(self.addr).await.unwrap();
+        let name = ServerName::try_from("localhost").unwrap();
+        let mut stream = self.connector.connect(name, stream).await.unwrap();
+
+        let body = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params});
+        let body = body.to_string();
+        let key_header = api_key
+            .map(|key| format!("X-API-Key: {key}\r\n"))
+            .unwrap_or_default();
+        let request = format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n{key_header}\r\n{body}",
+            body.len()
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        // The server may close without a TLS close_notify; what was read
+        // before that is the whole response
+        let mut response = Vec::new();
+        let _ = stream.read_to_end(&mut response).await;
+        let response = String::from_utf8(response).unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        serde_json::from_str(body).unwrap()
+    }
+}
+
+#[tokio::test]
+async fn test_client_certificate_api_key() {
+    let dir = temp_dir("keys");
+    let ca = new_ca();
+    let (cert, key) = write(
+        &dir,
+        "server",
+        &issue(&ca, "localhost", ExtendedKeyUsagePurpose::ServerAuth),
+    );
+    let client_ca = dir.join("ca.crt");
+    std::fs::write(&client_ca, ca.cert.pem()).unwrap();
+
+    let alice = Address::repeat_byte(0xa1);
+    let accounts = BTreeMap::from([(
+        alice,
+        Account {
+            balance: U256::from(1000),
+            ..Account::default()
+        },
+    )]);
+    let node = TestNode::start_with(
+        accounts,
+        Tamper::None,
+        &format!(
+            r#"
+            [auth]
+            enabled = true
+            [[auth.keys]]
+            name = "indexer"
+            key = "indexer-key"
+            requests_per_second = 0.001
+            burst = 1
+            allowed_methods = ["eth_blockNumber"]
+            [[auth.keys]]
+            name = "public"
+            key = "public-key"
+            [server.tls]
+            cert = "{}"
+            key = "{}"
+            client_ca = "{}"
+            [server.tls.identities]
+            indexer = "indexer"
+            "#,
+            cert.display(),
+            key.display(),
+            client_ca.display(),
+        ),
+    )
+    .await;
+
+    let terminator = TlsTerminator::new(node.state.config().server.tls.as_ref().unwrap()).unwrap();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(rpc::serve_tls(
+        listener,
+        terminator,
+        rpc::router(node.state.clone()),
+    ));
+
+    // The mapped certificate gets the indexer key's allowlist and limit, even
+    // with another key in the header
+    let indexer = Client::new(
+        &ca,
+        &issue(&ca, "indexer", ExtendedKeyUsagePurpose::ClientAuth),
+        addr,
+    );
+    let head = indexer
+        .call("eth_blockNumber", json!([]), Some("public-key"))
+        .await;
+    assert_eq!(head["result"], format!("0x{HEAD:x}"), "{head}");
+    let balance = indexer
+        .call(
+            "eth_getBalance",
+            json!([alice, "latest"]),
+            Some("public-key"),
+        )
+        .await;
+    assert_eq!(balance["error"]["code"], -32004, "{balance}");
+    let limited = indexer.call("eth_blockNumber", json!([]), None).await;
+    assert_eq!(limited["error"]["code"], -32005, "{limited}");
+
+    // An unmapped certificate falls back to the key in the header
+    let stranger = Client::new(
+        &ca,
+        &issue(&ca, "stranger", ExtendedKeyUsagePurpose::ClientAuth),
+        addr,
+    );
+    let balance = stranger
+        .call(
+            "eth_getBalance",
+            json!([alice, "latest"]),
+            Some("public-key"),
+        )
+        .await;
+    assert_eq!(balance["result"], "0x3e8", "{balance}");
+    let missing = stranger
+        .call("eth_getBalance", json!([alice, "latest"]), None)
+        .await;
+    assert_eq!(missing["error"]["code"], -32001, "{missing}");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}